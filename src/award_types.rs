@@ -0,0 +1,240 @@
+//! Types of awards a chat gives, such as Ls and Ws. Every chat has the
+//! built-in types, which chat admins can change, and can add its own.
+
+use miette::Result;
+use teloxide::{types::ChatId, utils::markdown};
+
+use crate::{
+    db::{
+        models::{AwardType, TypedMemberStat},
+        pool::db,
+        repo::AwardTypeRepo,
+    },
+    error::{BotError, HandlerResult},
+};
+
+/// Code of the L, the award reactions, digests, stats and badges are about
+pub const L: &str = "l";
+/// Code of the W
+pub const W: &str = "w";
+
+/// Longest code an award type can have, in characters
+const MAX_CODE_CHARS: usize = 16;
+/// Longest name an award type can have, in characters
+const MAX_NAME_CHARS: usize = 32;
+/// Longest emoji an award type can have, in characters. Some emojis are
+/// made of several.
+const MAX_EMOJI_CHARS: usize = 8;
+/// Most one award can add to or take from the net score
+const MAX_WEIGHT: i32 = 100;
+
+const SET_USAGE: &str = "Use /awardtypes set <code> <emoji> <weight> <name>";
+
+/// A member's scores of several award types, and the net score they add up
+/// to.
+#[derive(PartialEq, Eq, Debug)]
+pub struct NetScore {
+    pub tg_user_id: String,
+    pub first_name: Option<String>,
+    /// Scores of each award type, in the order the types were given in
+    pub amounts: Vec<i64>,
+    pub net: i64,
+}
+
+impl AwardType {
+    /// `name`, pluralized.
+    pub fn plural(&self) -> String {
+        format!("{}s", self.name)
+    }
+
+    /// `count` awards of this type, such as "1 L" or "3 Ls".
+    pub fn count(&self, count: i64) -> String {
+        if count == 1 {
+            format!("1 {}", self.name)
+        } else {
+            format!("{} {}", count, self.plural())
+        }
+    }
+
+    /// One award of this type, such as "an L" or "a W".
+    pub fn one(&self) -> String {
+        let mut chars = self.name.chars().map(|c| c.to_ascii_uppercase());
+        let an = match (chars.next(), chars.next()) {
+            // Letters are read out on their own
+            (Some(letter), None) => "AEFHILMNORSX".contains(letter),
+            (Some(first), Some(_)) => "AEIOU".contains(first),
+            (None, _) => false,
+        };
+
+        format!("{} {}", if an { "an" } else { "a" }, self.name)
+    }
+}
+
+/// The types every chat starts with.
+pub fn built_in(chat_id: ChatId) -> [AwardType; 2] {
+    [
+        AwardType {
+            chat_id: chat_id.0,
+            code: String::from(L),
+            name: String::from("L"),
+            emoji: String::from("🤡"),
+            weight: -1,
+        },
+        AwardType {
+            chat_id: chat_id.0,
+            code: String::from(W),
+            name: String::from("W"),
+            emoji: String::from("👑"),
+            weight: 1,
+        },
+    ]
+}
+
+/// Every award type of `chat_id`: the built-in ones, as the chat changed
+/// them, followed by the ones it added.
+pub async fn all(chat_id: ChatId) -> Result<Vec<AwardType>> {
+    let mut stored = AwardTypeRepo::new(db()).stored(chat_id).await?;
+    let mut types = built_in(chat_id)
+        .into_iter()
+        .map(|default| {
+            match stored
+                .iter()
+                .position(|changed| changed.code == default.code)
+            {
+                Some(i) => stored.remove(i),
+                None => default,
+            }
+        })
+        .collect::<Vec<_>>();
+    types.append(&mut stored);
+
+    Ok(types)
+}
+
+/// The award type of `chat_id` called `code`, if there is one.
+pub async fn find(chat_id: ChatId, code: &str) -> Result<Option<AwardType>> {
+    Ok(all(chat_id)
+        .await?
+        .into_iter()
+        .find(|award_type| award_type.code == code))
+}
+
+/// The type of awards recorded as `code` in `chat_id`. The chat may have
+/// removed it since, in which case it is named after its code.
+pub async fn get(chat_id: ChatId, code: &str) -> Result<AwardType> {
+    Ok(find(chat_id, code).await?.unwrap_or_else(|| AwardType {
+        chat_id: chat_id.0,
+        code: code.to_owned(),
+        name: code.to_uppercase(),
+        emoji: String::new(),
+        weight: 0,
+    }))
+}
+
+/// Like [`find`], for a code a member wrote.
+pub async fn lookup(chat_id: ChatId, code: &str) -> HandlerResult<AwardType> {
+    let code = code.trim().to_lowercase();
+    match find(chat_id, &code).await? {
+        Some(award_type) => Ok(award_type),
+        None => Err(BotError::user(format!(
+            "There is no award type called {}, see /awardtypes",
+            code
+        ))),
+    }
+}
+
+/// Adds `scores` up into each member's net score, highest first. Scores of
+/// award types other than `types` are left out.
+pub fn net_scores(types: &[AwardType], scores: &[TypedMemberStat]) -> Vec<NetScore> {
+    let mut members: Vec<NetScore> = Vec::new();
+    for score in scores {
+        let Some(column) = types
+            .iter()
+            .position(|award_type| award_type.code == score.award_type)
+        else {
+            continue;
+        };
+        let i = match members
+            .iter()
+            .position(|member| member.tg_user_id == score.tg_user_id)
+        {
+            Some(i) => i,
+            None => {
+                members.push(NetScore {
+                    tg_user_id: score.tg_user_id.clone(),
+                    first_name: score.first_name.clone(),
+                    amounts: vec![0; types.len()],
+                    net: 0,
+                });
+                members.len() - 1
+            }
+        };
+        let amount = i64::from(score.amount);
+        members[i].amounts[column] += amount;
+        members[i].net += amount * i64::from(types[column].weight);
+    }
+    // Ties keep the order members were first seen in
+    members.sort_by_key(|member| -member.net);
+
+    members
+}
+
+/// Parses the arguments of `/awardtypes set` into a type of `chat_id`.
+pub fn parse(chat_id: ChatId, args: &str) -> HandlerResult<AwardType> {
+    let mut args = args.split_whitespace();
+    let (Some(code), Some(emoji), Some(weight)) = (args.next(), args.next(), args.next()) else {
+        return Err(BotError::user(SET_USAGE));
+    };
+    let name = args.collect::<Vec<_>>().join(" ");
+    if name.is_empty() {
+        return Err(BotError::user(SET_USAGE));
+    }
+
+    let code = code.to_lowercase();
+    if code.chars().count() > MAX_CODE_CHARS || !code.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return Err(BotError::user(format!(
+            "Codes are made of up to {} letters and digits",
+            MAX_CODE_CHARS
+        )));
+    }
+    if emoji.chars().count() > MAX_EMOJI_CHARS || emoji.chars().any(|c| c.is_ascii()) {
+        return Err(BotError::user(format!("{} is not an emoji", emoji)));
+    }
+    let Some(weight) = weight
+        .parse::<i32>()
+        .ok()
+        .filter(|weight| weight.abs() <= MAX_WEIGHT)
+    else {
+        return Err(BotError::user(format!(
+            "The weight is a whole number from -{} to {}",
+            MAX_WEIGHT, MAX_WEIGHT
+        )));
+    };
+    if name.chars().count() > MAX_NAME_CHARS {
+        return Err(BotError::user(format!(
+            "Keep the name under {} characters",
+            MAX_NAME_CHARS
+        )));
+    }
+
+    Ok(AwardType {
+        chat_id: chat_id.0,
+        code,
+        name,
+        emoji: emoji.to_owned(),
+        weight,
+    })
+}
+
+/// Lists `types` in MarkdownV2.
+pub fn render(types: &[AwardType]) -> String {
+    let mut lines = vec![String::from("*Award types*")];
+    lines.extend(types.iter().map(|award_type| {
+        markdown::escape(&format!(
+            "{} {}: /give {}, {:+} to the net score each",
+            award_type.emoji, award_type.name, award_type.code, award_type.weight
+        ))
+    }));
+
+    lines.join("\n")
+}
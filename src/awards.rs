@@ -0,0 +1,168 @@
+//! Buttons under awarded Ls and other awards, and the rules every award has
+//! to pass.
+
+use teloxide::types::{ChatId, InlineKeyboardButton, InlineKeyboardMarkup, UserId};
+
+use crate::{
+    cooldown,
+    db::models::{AwardType, DisputeTally},
+    error::{BotError, HandlerResult},
+    settings::ChatSettings,
+};
+
+/// Prefix of the callback data sent by the buttons of [`keyboard`]
+pub const CALLBACK_PREFIX: &str = "award:";
+/// Prefix of the callback data sent by the buttons of [`dispute_keyboard`]
+pub const DISPUTE_CALLBACK_PREFIX: &str = "dispute:";
+
+/// Longest reason an L can be given for, in characters
+const MAX_REASON_CHARS: usize = 200;
+/// Most members one command can give awards to
+pub const MAX_RECIPIENTS: usize = 10;
+
+/// What a button under an award does.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Action {
+    /// Gives the same member another award of the same type
+    PlusOne,
+    /// Takes back the award the presser gave
+    Undo,
+    /// Puts the award to a vote
+    Dispute,
+}
+
+impl Action {
+    const ALL: [Self; 3] = [Self::PlusOne, Self::Undo, Self::Dispute];
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::PlusOne => "plus",
+            Self::Undo => "undo",
+            Self::Dispute => "dispute",
+        }
+    }
+
+    fn label(self, award_type: &AwardType) -> String {
+        match self {
+            Self::PlusOne => format!("+1 {}", award_type.name),
+            Self::Undo => String::from("Undo"),
+            Self::Dispute => String::from("Dispute"),
+        }
+    }
+
+    /// Parses callback data with [`CALLBACK_PREFIX`] stripped into the action
+    /// and the award it refers to.
+    pub fn parse(data: &str) -> Option<(Self, i64)> {
+        let (name, award_id) = data.split_once(':')?;
+        let action = Self::ALL.into_iter().find(|action| action.name() == name)?;
+
+        Some((action, award_id.parse().ok()?))
+    }
+}
+
+/// Parses callback data with [`DISPUTE_CALLBACK_PREFIX`] stripped into
+/// whether the voter wants to keep the award, and the award.
+pub fn parse_vote(data: &str) -> Option<(bool, i64)> {
+    let (vote, award_id) = data.split_once(':')?;
+    let keep = match vote {
+        "keep" => true,
+        "drop" => false,
+        _ => return None,
+    };
+
+    Some((keep, award_id.parse().ok()?))
+}
+
+/// The buttons under the message announcing award `award_id` of
+/// `award_type`.
+pub fn keyboard(award_id: i64, award_type: &AwardType) -> InlineKeyboardMarkup {
+    InlineKeyboardMarkup::new([Action::ALL.map(|action| {
+        InlineKeyboardButton::callback(
+            action.label(award_type),
+            format!("{}{}:{}", CALLBACK_PREFIX, action.name(), award_id),
+        )
+    })])
+}
+
+/// The buttons for voting on disputed award `award_id`.
+pub fn dispute_keyboard(award_id: i64) -> InlineKeyboardMarkup {
+    InlineKeyboardMarkup::new([
+        [("Keep it", "keep"), ("Drop it", "drop")].map(|(label, vote)| {
+            InlineKeyboardButton::callback(
+                label,
+                format!("{}{}:{}", DISPUTE_CALLBACK_PREFIX, vote, award_id),
+            )
+        }),
+    ])
+}
+
+/// Announces a group of `count` awards of `award_type` to the same member.
+pub fn announcement(award_type: &AwardType, count: i64) -> String {
+    if count == 1 {
+        format!("{} has been awarded", award_type.name)
+    } else {
+        format!("{} have been awarded", award_type.count(count))
+    }
+}
+
+/// Announces awards of `award_type` given at once to the members called
+/// `names`.
+pub fn bulk_announcement(award_type: &AwardType, names: &[String]) -> String {
+    let names = match names {
+        [] => String::new(),
+        [name] => name.clone(),
+        [rest @ .., last] => format!("{} and {}", rest.join(", "), last),
+    };
+
+    format!("{} have been awarded to {}", award_type.plural(), names)
+}
+
+/// Describes a dispute vote in progress on an award of `award_type`.
+pub fn dispute_text(award_type: &AwardType, tally: &DisputeTally, needed: u32) -> String {
+    format!(
+        "This {} is disputed. Keep: {}, drop: {}. It is settled once {} members vote either way.",
+        award_type.name, tally.keep_votes, tally.drop_votes, needed
+    )
+}
+
+/// Checks that `giver_id` may give `receiver_id` an award of `award_type` in
+/// `chat_id` right now.
+pub async fn check(
+    chat_id: ChatId,
+    award_type: &AwardType,
+    giver_id: UserId,
+    receiver_id: UserId,
+    settings: &ChatSettings,
+) -> HandlerResult {
+    if giver_id == receiver_id {
+        return Err(BotError::user(format!(
+            "You can't give yourself {}",
+            award_type.one()
+        )));
+    }
+
+    // The cooldown is shared by every award type
+    if let Some(remaining) = cooldown::remaining(chat_id, giver_id, settings.award_cooldown).await?
+    {
+        return Err(BotError::user(format!(
+            "You can give another {} in {} seconds",
+            award_type.name,
+            remaining.as_secs_f64().ceil()
+        )));
+    }
+
+    Ok(())
+}
+
+/// The reason written after a command giving an award, if any.
+pub fn reason(text: &str) -> HandlerResult<Option<&str>> {
+    let reason = text.trim();
+    if reason.chars().count() > MAX_REASON_CHARS {
+        return Err(BotError::user(format!(
+            "Keep the reason under {} characters",
+            MAX_REASON_CHARS
+        )));
+    }
+
+    Ok((!reason.is_empty()).then_some(reason))
+}
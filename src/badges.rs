@@ -0,0 +1,196 @@
+//! Badges members earn in a chat as they give and receive Ls. What earns a
+//! badge is described by its definition in [`BADGES`], so adding one takes
+//! nothing but a new entry there.
+
+use miette::{IntoDiagnostic, Result};
+use teloxide::{prelude::*, types::ParseMode, utils::markdown};
+
+use crate::{
+    db::{
+        models::{EarnedBadge, MemberStat},
+        pool::db,
+        repo::{BadgeRepo, MemberRepo, StatsRepo},
+    },
+    settings, stats,
+};
+
+/// A badge and what earns it.
+#[derive(Debug)]
+pub struct Badge {
+    /// Stored for those who earned it, so it must not change
+    pub id: &'static str,
+    pub emoji: &'static str,
+    pub name: &'static str,
+    pub rule: Rule,
+}
+
+/// What earns a badge.
+#[derive(Clone, Copy, Debug)]
+pub enum Rule {
+    /// Having at least this many Ls on the scoreboard
+    Received(i64),
+    /// Having given at least this many Ls
+    Given(i64),
+    /// Receiving Ls on at least this many days in a row
+    Streak(usize),
+    /// Leading the scoreboard when it is reset
+    SeasonChampion,
+}
+
+/// Every badge there is, in the order they are listed in.
+pub const BADGES: &[Badge] = &[
+    Badge {
+        id: "first-l",
+        emoji: "🥉",
+        name: "First L",
+        rule: Rule::Received(1),
+    },
+    Badge {
+        id: "10-ls",
+        emoji: "🔟",
+        name: "10 Ls",
+        rule: Rule::Received(10),
+    },
+    Badge {
+        id: "streak-7",
+        emoji: "🔥",
+        name: "L streak 7 days",
+        rule: Rule::Streak(7),
+    },
+    Badge {
+        id: "gave-50",
+        emoji: "🎁",
+        name: "Gave 50 Ls",
+        rule: Rule::Given(50),
+    },
+    Badge {
+        id: "season-champion",
+        emoji: "🏆",
+        name: "Season champion",
+        rule: Rule::SeasonChampion,
+    },
+];
+
+/// Definitions of the `earned` badges, in the order of [`BADGES`]. Badges no
+/// longer defined are left out.
+pub fn definitions<'a>(earned: impl IntoIterator<Item = &'a EarnedBadge>) -> Vec<&'static Badge> {
+    let earned = earned
+        .into_iter()
+        .map(|badge| badge.badge.as_str())
+        .collect::<Vec<_>>();
+
+    BADGES
+        .iter()
+        .filter(|badge| earned.contains(&badge.id))
+        .collect()
+}
+
+/// The emojis of the `earned` badges, to show next to a name.
+pub fn emojis<'a>(earned: impl IntoIterator<Item = &'a EarnedBadge>) -> String {
+    definitions(earned)
+        .iter()
+        .map(|badge| badge.emoji)
+        .collect()
+}
+
+/// Grants `members` of `chat_id` the badges they earned and didn't have yet,
+/// announcing them in the chat. Meant to run after an L was awarded.
+pub async fn grant_earned(bot: &Bot, chat_id: ChatId, members: &[UserId]) -> Result<()> {
+    let timezone = settings::get(chat_id).await?.timezone;
+    let stats = StatsRepo::new(db());
+    let repo = BadgeRepo::new(db());
+
+    for member_id in members {
+        let times = stats
+            .received(chat_id, *member_id)
+            .await?
+            .iter()
+            .map(|award| award.created_at)
+            .collect::<Vec<_>>();
+        let ls = stats.total(chat_id, *member_id).await?;
+        let given = stats.given(chat_id, *member_id).await?;
+        let streak = stats::longest_streak(&times, timezone);
+
+        let mut granted = Vec::new();
+        for badge in BADGES {
+            let earned = match badge.rule {
+                Rule::Received(needed) => ls >= needed,
+                Rule::Given(needed) => given >= needed,
+                Rule::Streak(needed) => streak >= needed,
+                Rule::SeasonChampion => false,
+            };
+            if earned && repo.grant(chat_id, *member_id, badge.id).await? {
+                granted.push(badge);
+            }
+        }
+        announce(bot, chat_id, *member_id, &granted).await?;
+    }
+
+    Ok(())
+}
+
+/// Grants the season champion badges to whoever led `scoreboard`, the
+/// scoreboard of `chat_id` just before it was reset.
+pub async fn crown_champions(bot: &Bot, chat_id: ChatId, scoreboard: &[MemberStat]) -> Result<()> {
+    let Some(most) = scoreboard
+        .iter()
+        .map(|stat| stat.amount)
+        .max()
+        .filter(|ls| *ls > 0)
+    else {
+        return Ok(());
+    };
+    let repo = BadgeRepo::new(db());
+
+    // Members tied for first place are all champions
+    for stat in scoreboard.iter().filter(|stat| stat.amount == most) {
+        let Ok(member_id) = stat.tg_user_id.parse().map(UserId) else {
+            continue;
+        };
+        let mut granted = Vec::new();
+        for badge in BADGES {
+            if matches!(badge.rule, Rule::SeasonChampion)
+                && repo.grant(chat_id, member_id, badge.id).await?
+            {
+                granted.push(badge);
+            }
+        }
+        announce(bot, chat_id, member_id, &granted).await?;
+    }
+
+    Ok(())
+}
+
+async fn announce(bot: &Bot, chat_id: ChatId, member_id: UserId, badges: &[&Badge]) -> Result<()> {
+    if badges.is_empty() {
+        return Ok(());
+    }
+
+    let name = MemberRepo::new(db())
+        .get(member_id)
+        .await?
+        .and_then(|member| member.first_name)
+        .unwrap_or_else(|| String::from("(left)"));
+    let badges = badges
+        .iter()
+        .map(|badge| format!("{} *{}*", badge.emoji, markdown::escape(badge.name)))
+        .collect::<Vec<_>>();
+    bot.send_message(
+        chat_id,
+        format!(
+            "__{}__ earned {}: {}",
+            markdown::escape(&name),
+            if badges.len() == 1 {
+                "a badge"
+            } else {
+                "badges"
+            },
+            badges.join(", ")
+        ),
+    )
+    .parse_mode(ParseMode::MarkdownV2)
+    .await
+    .into_diagnostic()?;
+
+    Ok(())
+}
@@ -1,20 +1,61 @@
-use miette::{bail, Context as _, IntoDiagnostic, Result};
+use futures::{future, stream, StreamExt};
+use miette::{miette, Context as _, IntoDiagnostic, Result};
+use nanoid::nanoid;
 use rand::seq::SliceRandom;
-use std::env;
+use std::{collections::HashSet, env, fs};
 use teloxide::{
-    dispatching::UpdateHandler,
+    dispatching::{update_listeners, UpdateHandler},
+    payloads::SendMessage,
     prelude::*,
-    types::{MediaKind, MessageKind, ParseMode},
-    utils::command::BotCommands,
+    requests::JsonRequest,
+    types::{
+        CallbackQuery, InlineQuery, InputFile, MediaKind, MessageEntityKind, MessageKind,
+        ParseMode, PollAnswer, User,
+    },
+    utils::{command::BotCommands, markdown},
 };
-use tracing::{debug, info, warn};
+use tokio::sync::oneshot;
+use tracing::{debug, warn};
 
 use crate::{
-    common::{bot::respond, text},
-    db::{models::*, sqlite::*},
+    award_types,
+    awards::{self, Action},
+    badges, chart,
+    cleanup::{self, Chatter},
+    common::{bot::respond, constants::PROGRAM_NAME, text, time::unix_time},
+    config::{Config, UpdateMode},
+    db::{
+        backup,
+        models::*,
+        pool::*,
+        repo::{
+            AwardRepo, AwardTypeRepo, BadgeRepo, DisputeRepo, JuryRepo, MemberRepo, PhraseRepo,
+            PollRepo, ReactionRepo, RoleRepo, StatsRepo,
+        },
+    },
+    decay, digest,
+    error::{BotError, HandlerResult},
+    health, inline, jury, metrics, permissions,
+    reactions::{Change, MessageReactionUpdated},
+    scheduler::Scheduler,
+    settings::{self, ChatSettings, DigestFrequency, ReplyStyle, Setting},
+    stats,
+    updates::{self, KeepAllowedUpdates},
     utterance::DialogflowSession,
+    webhook,
 };
 
+/// Longest question a poll can ask, in characters
+const MAX_POLL_QUESTION_CHARS: usize = 300;
+/// Most answers a poll can have
+const MAX_POLL_ANSWERS: usize = 10;
+/// Longest answer a poll can have, in characters
+const MAX_POLL_ANSWER_CHARS: usize = 100;
+
+/// Written after a command giving an award, in reply to a poll, to give one
+/// to everyone who voted in it
+const ALL_WHO_VOTED: &str = "all-who-voted";
+
 #[derive(BotCommands, Clone)]
 #[command(
     rename_rule = "lowercase",
@@ -23,22 +64,117 @@ use crate::{
 enum Command {
     #[command(description = "Display this text")]
     Help,
-    #[command(description = "view L scoreboard")]
-    ViewScoreboard,
-    #[command(description = "award L to user")]
-    GiveL,
+    #[command(description = "view the scoreboard, add net or an award type to pick a view")]
+    ViewScoreboard(String),
+    #[command(description = "view the scoreboard and Ls over time as charts")]
+    Chart,
+    #[command(description = "view your L stats")]
+    MyStats,
+    #[command(description = "view someone's L stats, reply to them or add their @username")]
+    Stats(String),
+    #[command(
+        description = "award L to the user replied to or @mentioned, optionally followed by why"
+    )]
+    GiveL(String),
+    #[command(
+        description = "award W to the user replied to or @mentioned, optionally followed by why"
+    )]
+    GiveW(String),
+    #[command(description = "give an award of any type, like /give w, optionally followed by why")]
+    Give(String),
+    #[command(description = "view award types, chat admins can change them")]
+    AwardTypes(String),
+    #[command(description = "start a poll, like /poll question | answer | answer")]
+    Poll(String),
     #[command(description = "learn a new phrase")]
     Learn(String),
+    #[command(description = "view settings, chat admins can change them")]
+    Settings(String),
+    #[command(description = "see when the L digest is posted, or /digest now to see it")]
+    Digest(String),
+    #[command(description = "chat admins: reset the scoreboard of this chat")]
+    ResetScores,
+    #[command(description = "chat admins: reply with admin, member or default to set a role")]
+    SetRole(String),
+    #[command(description = "owners: forget a learned phrase")]
+    Forget(String),
+    #[command(description = "owners: receive a database backup privately")]
+    Backup,
 }
 
 impl Command {
-    async fn from_phrase(input: String, me: teloxide::types::Me) -> Option<Self> {
-        let response = DialogflowSession::new()
-            .await
-            .ok()?
-            .detect_intent_from_text(input, None)
-            .await
-            .ok()?;
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Help => "help",
+            Self::ViewScoreboard(_) => "viewscoreboard",
+            Self::Chart => "chart",
+            Self::MyStats => "mystats",
+            Self::Stats(_) => "stats",
+            Self::GiveL(_) => "givel",
+            Self::GiveW(_) => "givew",
+            Self::Give(_) => "give",
+            Self::AwardTypes(_) => "awardtypes",
+            Self::Poll(_) => "poll",
+            Self::Learn(_) => "learn",
+            Self::Settings(_) => "settings",
+            Self::Digest(_) => "digest",
+            Self::ResetScores => "resetscores",
+            Self::SetRole(_) => "setrole",
+            Self::Forget(_) => "forget",
+            Self::Backup => "backup",
+        }
+    }
+
+    /// The least privileged role allowed to run the command.
+    fn required_role(&self) -> Role {
+        match self {
+            // Anyone can view settings, changing them is checked separately
+            Self::Help
+            | Self::ViewScoreboard(_)
+            | Self::Chart
+            | Self::MyStats
+            | Self::Stats(_)
+            | Self::GiveL(_)
+            | Self::GiveW(_)
+            | Self::Give(_)
+            | Self::AwardTypes(_)
+            | Self::Poll(_)
+            | Self::Learn(_)
+            | Self::Settings(_)
+            | Self::Digest(_) => Role::Member,
+            Self::ResetScores | Self::SetRole(_) => Role::ChatAdmin,
+            // Learned phrases are shared by every chat
+            Self::Forget(_) => Role::Owner,
+            Self::Backup => Role::Owner,
+        }
+    }
+
+    async fn from_phrase(input: String, language: String, me: teloxide::types::Me) -> Option<Self> {
+        let timer = metrics::DIALOGFLOW_LATENCY.start_timer();
+        let response = match DialogflowSession::new().await {
+            Ok(mut session) => session
+                .detect_intent_from_text(input, Some(language))
+                .await
+                .into_diagnostic(),
+            Err(err) => Err(err),
+        };
+        timer.observe_duration();
+
+        let response = match response {
+            Ok(response) => {
+                metrics::DIALOGFLOW_REQUESTS
+                    .with_label_values(&["ok"])
+                    .inc();
+                response
+            }
+            Err(err) => {
+                metrics::DIALOGFLOW_REQUESTS
+                    .with_label_values(&["error"])
+                    .inc();
+                debug!("Dialogflow request failed: {:?}", err);
+                return None;
+            }
+        };
 
         debug!("Dialogflow response: {:#?}", response);
 
@@ -59,135 +195,166 @@ impl Command {
         bot: Bot,
         me: teloxide::types::Me,
         msg: Message,
-    ) -> Result<Option<String>> {
+    ) -> HandlerResult<Option<String>> {
         let Some(author) = msg.from() else {
-            bail!("Message has no author");
+            return Err(BotError::NoMatch("Message has no author"));
         };
 
+        let required_role = self.required_role();
+        if !permissions::has_role(&bot, &msg.chat, author.id, required_role).await? {
+            return Err(BotError::user(format!(
+                "Only {} can use /{}",
+                required_role.holders(),
+                self.name()
+            )));
+        }
+
         match self {
             Self::Help => {
-                bot.send_message(msg.chat.id, {
-                    if msg.chat.is_group() || msg.chat.is_supergroup() {
-                        Command::descriptions().username_from_me(&me).to_string()
-                    } else {
-                        Command::descriptions().to_string()
-                    }
-                })
-                .await
-                .into_diagnostic()?;
+                let sent = bot
+                    .send_message(msg.chat.id, {
+                        if msg.chat.is_group() || msg.chat.is_supergroup() {
+                            Command::descriptions().username_from_me(&me).to_string()
+                        } else {
+                            Command::descriptions().to_string()
+                        }
+                    })
+                    .await
+                    .into_diagnostic()?;
+                let settings = settings::get(msg.chat.id).await?;
+                cleanup::schedule(&settings, msg.chat.id, sent.id, Chatter::Confirmation).await?;
             }
-            Self::ViewScoreboard => {
-                let stats = sqlx::query_as::<_, MemberStat>(
-                    r#"
-                    SELECT tgUserId, ls
-                    FROM Member
-                    LEFT JOIN Stat
-                           ON Member.id = Stat.memberId
-                    "#,
-                )
-                .fetch_all(db())
-                .await
-                .into_diagnostic()?;
+            Self::ViewScoreboard(view) => {
+                respond!(scoreboard(&bot, msg.chat.id, view).await?);
+            }
+            Self::Chart => {
+                let settings = settings::get(msg.chat.id).await?;
+                let stats = decay::scoreboard(msg.chat.id, &settings, award_types::L).await?;
 
                 if stats.is_empty() {
                     respond!("Scoreboard is empty\\!");
                 }
 
-                let mut response = String::new();
-                for (i, stat) in stats.iter().enumerate() {
-                    response.push_str(&format!(
-                        "__{}__ — *{}* Ls",
-                        bot.get_chat_member(
-                            msg.chat.id,
-                            UserId(stat.tg_user_id.parse().into_diagnostic()?)
-                        )
-                        .await
-                        .into_diagnostic()?
-                        .user
-                        .first_name,
-                        stat.ls
-                    ));
-                    if i < stats.len() - 1 {
-                        response.push_str("\n");
+                let given_at = StatsRepo::new(db()).given_at(msg.chat.id).await?;
+                let totals = chart::running_totals(&given_at, settings.timezone, unix_time());
+
+                let mut charts = vec![("scoreboard.png", chart::scoreboard(&stats))];
+                // Scores from before awards were recorded have no dates
+                if !totals.is_empty() {
+                    charts.push(("ls-over-time.png", chart::over_time(&totals)));
+                }
+                for (file_name, png) in charts {
+                    let mut request =
+                        bot.send_photo(msg.chat.id, InputFile::memory(png).file_name(file_name));
+                    if settings.reply_style == ReplyStyle::Quote {
+                        request = request.reply_to_message_id(msg.id);
                     }
+                    let sent = request.await.into_diagnostic()?;
+                    cleanup::schedule(&settings, msg.chat.id, sent.id, Chatter::Confirmation)
+                        .await?;
                 }
-
-                respond!(response);
             }
-            Self::GiveL => {
-                let Some(awardee) = msg
-                    .reply_to_message()
-                    .and_then(|m| m.from()) else {
-                        info!("Message is not a reply");
-                        return Ok(None)
-                    };
+            Self::MyStats => {
+                respond!(stats_of(msg.chat.id, author.id, &author.first_name).await?);
+            }
+            Self::Stats(args) => {
+                let (user_id, name) = stats_target(&msg, author, args).await?;
+                respond!(stats_of(msg.chat.id, user_id, &name).await?);
+            }
+            Self::GiveL(reason) => {
+                let award_type = award_types::lookup(msg.chat.id, award_types::L).await?;
+                give(&bot, &msg, author, &award_type, reason).await?;
+            }
+            Self::GiveW(reason) => {
+                let award_type = award_types::lookup(msg.chat.id, award_types::W).await?;
+                give(&bot, &msg, author, &award_type, reason).await?;
+            }
+            Self::Give(args) => {
+                let (code, reason) = first_word(args);
+                if code.is_empty() {
+                    return Err(BotError::user(
+                        "Use /give <type>, optionally followed by why. See /awardtypes for the types",
+                    ));
+                }
+                let award_type = award_types::lookup(msg.chat.id, code).await?;
+                give(&bot, &msg, author, &award_type, reason).await?;
+            }
+            Self::AwardTypes(args) => {
+                let (action, args) = first_word(args);
+                if action.is_empty() {
+                    respond!(award_types::render(&award_types::all(msg.chat.id).await?));
+                }
+                if !["set", "remove"].contains(&action) {
+                    return Err(BotError::user(
+                        "Use /awardtypes set or /awardtypes remove to change award types",
+                    ));
+                }
+                if !permissions::has_role(&bot, &msg.chat, author.id, Role::ChatAdmin).await? {
+                    return Err(BotError::user("Only chat admins can change award types"));
+                }
 
-                let member = sqlx::query_as::<_, MemberStat>(
-                    r#"
-                    SELECT tgUserId, ls
-                    FROM Member
-                    LEFT JOIN Stat
-                           ON Member.id = Stat.memberId
-                    WHERE id = ?
-                    "#,
-                )
-                .bind(awardee.id.to_string())
-                .fetch_optional(db())
-                .await
-                .into_diagnostic()?;
+                let repo = AwardTypeRepo::new(db());
+                if action == "set" {
+                    let award_type = award_types::parse(msg.chat.id, args)?;
+                    repo.set(&award_type).await?;
+                    respond!(markdown::escape(&format!(
+                        "{} {} can be given with /give {}",
+                        award_type.emoji,
+                        award_type.plural(),
+                        award_type.code
+                    )));
+                }
 
-                match member {
-                    Some(member) => {
-                        sqlx::query(
-                            r#"
-                            UPDATE Stat SET ls = ls + 1
-                            WHERE memberId = ?
-                            "#,
-                        )
-                        .bind(member.tg_user_id)
-                        .execute(db())
-                        .await
-                        .into_diagnostic()?;
-                    }
-                    None => {
-                        sqlx::query(
-                            r#"
-                            INSERT INTO Member (
-                                id,
-                                tgUserId
-                            ) VALUES (?1, ?2);
-                            
-                            INSERT INTO Stat (
-                                memberId,
-                                ls
-                            ) VALUES (?1, ?3);
-                            "#,
-                        )
-                        .bind(awardee.id.to_string())
-                        .bind(awardee.id.to_string())
-                        .bind(1)
-                        .execute(db())
-                        .await
-                        .into_diagnostic()?;
-                    }
+                let award_type = award_types::lookup(msg.chat.id, args).await?;
+                if !repo.remove(msg.chat.id, &award_type.code).await? {
+                    return Err(BotError::user(format!(
+                        "{} are built in, so they can only be changed",
+                        award_type.plural()
+                    )));
+                }
+                let restored = award_types::find(msg.chat.id, &award_type.code).await?;
+                respond!(markdown::escape(&match restored {
+                    Some(built_in) => format!("{} are back to how they were", built_in.plural()),
+                    None => format!("{} can't be given anymore", award_type.plural()),
+                }));
+            }
+            Self::Poll(args) => {
+                let parts = args.split('|').map(str::trim).collect::<Vec<_>>();
+                let (question, answers) = parts.split_first().unwrap_or((&"", &[]));
+                let valid = (1..=MAX_POLL_QUESTION_CHARS).contains(&question.chars().count())
+                    && (2..=MAX_POLL_ANSWERS).contains(&answers.len())
+                    && answers.iter().all(|answer| {
+                        (1..=MAX_POLL_ANSWER_CHARS).contains(&answer.chars().count())
+                    });
+                if !valid {
+                    return Err(BotError::user(format!(
+                        "Use /poll question | answer | answer, with 2 to {} answers",
+                        MAX_POLL_ANSWERS
+                    )));
                 }
 
-                bot.send_message(msg.chat.id, "L has been awarded")
-                    .parse_mode(ParseMode::MarkdownV2)
-                    .reply_to_message_id(msg.id)
+                // Telegram only tells bots who voted in their own polls, and
+                // only if they aren't anonymous
+                let sent = bot
+                    .send_poll(
+                        msg.chat.id,
+                        *question,
+                        answers.iter().map(|answer| answer.to_string()),
+                    )
+                    .is_anonymous(false)
                     .await
                     .into_diagnostic()?;
-
-                // TODO: Have bot delete message after x time has passed
-                // bot.delete_message(msg.chat.id, sent.id)
-                //     .await
-                //     .into_diagnostic()
-                //     .unwrap();
+                let Some(poll) = sent.poll() else {
+                    return Err(miette!("Sent poll came back without one").into());
+                };
+                PollRepo::new(db())
+                    .add(msg.chat.id, sent.id, &poll.id)
+                    .await?;
             }
             Self::Learn(body) => {
                 let args = body.split(" | ").collect::<Vec<&str>>();
                 let phrase = {
-                    let Some(phrase) = args.get(0) else {
+                    let Some(phrase) = args.first() else {
                         respond!("input not specified");
                     };
                     if phrase.is_empty() {
@@ -199,62 +366,150 @@ impl Command {
                     respond!("response not specified");
                 };
 
-                let nphrase = text::normalize(&phrase);
+                let nphrase = text::normalize(phrase);
 
-                let existing_phrase = sqlx::query_as::<_, Phrase>(
-                    r#"
-                    SELECT id, authorId, content
-                    FROM Phrase
-                    WHERE content = ?
-                    "#,
-                )
-                .bind(phrase)
-                .fetch_optional(db())
-                .await
-                .into_diagnostic()?;
+                PhraseRepo::new(db())
+                    .learn(author.id, &nphrase, response)
+                    .await?;
 
-                if let Some(phrase) = existing_phrase {
-                    sqlx::query(
-                        r#"
-                        INSERT INTO Response (phraseId, content) VALUES (?, ?);
-                        "#,
-                    )
-                    .bind(phrase.id)
-                    .bind(&response)
-                    .execute(db())
-                    .await
-                    .into_diagnostic()?;
-                } else {
-                    sqlx::query(
-                        r#"
-                        INSERT INTO Member (id, tgUserId)
-                        SELECT ?1, ?1 WHERE NOT EXISTS (
-                            SELECT 1 FROM Member
-                            WHERE id = ?1
-                              AND tgUserId = ?1
-                        );
-                        "#,
-                    )
-                    .bind(author.id.to_string())
-                    .execute(db())
-                    .await
-                    .into_diagnostic()?;
+                respond!("learnt");
+            }
+            Self::Settings(args) => {
+                let mut args = args.split_whitespace();
+                let Some(name) = args.next() else {
+                    let settings = settings::get(msg.chat.id).await?;
+                    let mut request = reply(&bot, &msg, &settings, settings::render(&settings));
+                    if permissions::has_role(&bot, &msg.chat, author.id, Role::ChatAdmin).await? {
+                        request = request.reply_markup(settings::keyboard(&settings));
+                    }
+                    request.await.into_diagnostic()?;
+                    return Ok(None);
+                };
 
-                    sqlx::query(
-                        r#"
-                        INSERT INTO Phrase   (authorId, content) VALUES (?, ?);
-                        INSERT INTO Response (phraseId, content) VALUES (last_insert_rowid(), ?);
-                        "#,
-                    )
-                    .bind(author.id.to_string())
-                    .bind(nphrase)
-                    .bind(&response)
-                    .execute(db())
-                    .await
-                    .into_diagnostic()?;
+                let Some(setting) = Setting::from_name(name) else {
+                    return Err(BotError::user(format!(
+                        "There is no setting called {}",
+                        name
+                    )));
+                };
+                let Some(value) = args.next() else {
+                    return Err(BotError::user(format!(
+                        "Use /settings {} <value> to change it",
+                        name
+                    )));
+                };
+                if !permissions::has_role(&bot, &msg.chat, author.id, Role::ChatAdmin).await? {
+                    return Err(BotError::user("Only chat admins can change settings"));
                 }
 
-                respond!("learnt");
+                let settings = settings::set(msg.chat.id, setting, value).await?;
+
+                respond!(markdown::escape(&format!(
+                    "{} is now {}",
+                    setting.name(),
+                    setting.value(&settings)
+                )));
+            }
+            Self::Digest(args) => {
+                if args.trim() == "now" {
+                    digest::preview(&bot, msg.chat.id).await?;
+                    return Ok(None);
+                }
+                if !args.trim().is_empty() {
+                    return Err(BotError::user("Use /digest now to see the digest so far"));
+                }
+
+                let settings = settings::get(msg.chat.id).await?;
+                let schedule = match settings.digest {
+                    DigestFrequency::Off => {
+                        respond!(markdown::escape(
+                            "Digests are off. Chat admins can turn them on with /settings digest daily or weekly."
+                        ));
+                    }
+                    DigestFrequency::Daily => "every day",
+                    DigestFrequency::Weekly => "every Sunday",
+                };
+                respond!(markdown::escape(&format!(
+                    "The digest is posted {} at {} ({}). Use /digest now to see it so far.",
+                    schedule,
+                    settings.digest_time.format("%H:%M"),
+                    settings.timezone.name()
+                )));
+            }
+            Self::ResetScores => {
+                let awards = AwardRepo::new(db());
+                let scoreboard = awards.scoreboard(msg.chat.id).await?;
+                awards.reset(msg.chat.id).await?;
+                if let Err(err) = badges::crown_champions(&bot, msg.chat.id, &scoreboard).await {
+                    warn!("Failed to crown season champions: {:?}", err);
+                }
+
+                respond!("Scoreboard has been reset");
+            }
+            Self::SetRole(role) => {
+                let role = match role.trim() {
+                    "admin" => Some(Role::ChatAdmin),
+                    "member" => Some(Role::Member),
+                    "default" => None,
+                    _ => {
+                        return Err(BotError::user(
+                            "Reply to someone with /setrole admin, member or default",
+                        ))
+                    }
+                };
+                let Some(target) = msg.reply_to_message().and_then(|m| m.from()) else {
+                    return Err(BotError::user(
+                        "Reply to someone with /setrole admin, member or default",
+                    ));
+                };
+                remember_member(target).await?;
+
+                RoleRepo::new(db())
+                    .set_chat_role(msg.chat.id, target.id, role)
+                    .await?;
+
+                respond!("Role updated");
+            }
+            Self::Forget(phrase) => {
+                if phrase.trim().is_empty() {
+                    respond!("input not specified");
+                }
+
+                if !PhraseRepo::new(db())
+                    .forget(&text::normalize(phrase))
+                    .await?
+                {
+                    return Err(BotError::user("I never learnt that"));
+                }
+
+                respond!("forgotten");
+            }
+            Self::Backup => {
+                if !backup::is_supported(db()) {
+                    return Err(BotError::user(
+                        "Backups are only available for SQLite databases",
+                    ));
+                }
+
+                let path = env::temp_dir().join(format!("{}-{}.sqlite3", PROGRAM_NAME, nanoid!()));
+                backup::snapshot(db(), &path).await?;
+                let sent = bot
+                    .send_document(
+                        author.id,
+                        InputFile::file(&path).file_name(format!("{}.sqlite3", PROGRAM_NAME)),
+                    )
+                    .await;
+                fs::remove_file(&path).ok();
+
+                if let Err(err) = sent {
+                    debug!("Failed to send backup privately: {:?}", err);
+                    return Err(BotError::user(
+                        "I can't message you, start a private chat with me first",
+                    ));
+                }
+                if msg.chat.id != ChatId::from(author.id) {
+                    respond!("Backup sent to you privately");
+                }
             }
         }
 
@@ -262,63 +517,1110 @@ impl Command {
     }
 }
 
+/// Finds whose stats `/stats` asks for: someone mentioned in `args`, the
+/// author of the message replied to, or else the author.
+async fn stats_target(msg: &Message, author: &User, args: &str) -> HandlerResult<(UserId, String)> {
+    let (members, rest) = mentioned(msg, args).await?;
+    if !rest.is_empty() {
+        return Err(BotError::user(
+            "Use /stats @username, or reply to someone with /stats",
+        ));
+    }
+    if let Some(member) = members.into_iter().next() {
+        return Ok(member);
+    }
+
+    let user = msg
+        .reply_to_message()
+        .and_then(|m| m.from())
+        .unwrap_or(author);
+    remember_member(user).await?;
+    Ok((user.id, user.first_name.clone()))
+}
+
+/// Renders the stats of `user_id`, called `name`, in `chat_id`.
+async fn stats_of(chat_id: ChatId, user_id: UserId, name: &str) -> Result<String> {
+    let settings = settings::get(chat_id).await?;
+    let profile = stats::collect(chat_id, user_id, &settings, unix_time()).await?;
+
+    Ok(stats::render(name, &profile, settings.timezone))
+}
+
+/// Splits the first word off `args`, returning it and the rest.
+fn first_word(args: &str) -> (&str, &str) {
+    let args = args.trim();
+    match args.split_once(char::is_whitespace) {
+        Some((word, rest)) => (word, rest.trim()),
+        None => (args, ""),
+    }
+}
+
+/// Resolves the members mentioned first thing in `args`, what was written
+/// after the command in `msg`. Members without a username are mentioned by
+/// name, linking to them, and the rest by @username, which must be known.
+/// Returns their ids and names along with the rest of `args`.
+async fn mentioned<'a>(
+    msg: &Message,
+    args: &'a str,
+) -> HandlerResult<(Vec<(UserId, String)>, &'a str)> {
+    let text_mentions = msg
+        .parse_entities()
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|entity| match entity.kind() {
+            MessageEntityKind::TextMention { user } if !entity.text().is_empty() => {
+                Some((entity.text().to_owned(), user.clone()))
+            }
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    let mut mentioned = Vec::new();
+    let mut rest = args.trim();
+    loop {
+        if let Some((name, user)) = text_mentions
+            .iter()
+            .find(|(name, _)| rest.starts_with(name.as_str()))
+        {
+            remember_member(user).await?;
+            mentioned.push((user.id, user.first_name.clone()));
+            rest = rest[name.len()..].trim_start();
+            continue;
+        }
+
+        let (word, after) = first_word(rest);
+        let Some(username) = word
+            .trim_end_matches(',')
+            .strip_prefix('@')
+            .filter(|username| !username.is_empty())
+        else {
+            break;
+        };
+        let Some(member) = MemberRepo::new(db()).by_username(username).await? else {
+            return Err(BotError::user(format!("I haven't seen @{} yet", username)));
+        };
+        let name = member
+            .first_name
+            .unwrap_or_else(|| format!("@{}", username));
+        mentioned.push((UserId(member.id as u64), name));
+        rest = after;
+    }
+
+    Ok((mentioned, rest))
+}
+
+/// Finds who a command giving an award of `award_type` is for, from `args`,
+/// what was written after the command: the members mentioned first thing,
+/// see [`mentioned`], everyone who voted in the poll `msg` replies to with `all-who-voted`, or
+/// else the author of the message it replies to. Returns their ids and
+/// names along with the rest of `args`, the reason.
+async fn recipients<'a>(
+    msg: &Message,
+    author: &User,
+    award_type: &AwardType,
+    args: &'a str,
+) -> HandlerResult<(Vec<(UserId, String)>, &'a str)> {
+    let (mut recipients, rest) = mentioned(msg, args).await?;
+    if !recipients.is_empty() {
+        return Ok((recipients, rest));
+    }
+
+    let Some(replied) = msg.reply_to_message() else {
+        return Err(BotError::user(format!(
+            "Reply to someone's message or mention them to award them {}",
+            award_type.one()
+        )));
+    };
+    let (word, after) = first_word(rest);
+    if word == ALL_WHO_VOTED {
+        let Some(voters) = PollRepo::new(db()).voters(msg.chat.id, replied.id).await? else {
+            return Err(BotError::user(format!(
+                "Reply to a poll started with /poll to award everyone who voted {}",
+                award_type.one()
+            )));
+        };
+        let members = MemberRepo::new(db());
+        // Voting doesn't stop anyone from giving awards to the others
+        for voter in voters.into_iter().filter(|voter| *voter != author.id) {
+            let name = members
+                .get(voter)
+                .await?
+                .and_then(|member| member.first_name)
+                .unwrap_or_else(|| String::from("(left)"));
+            recipients.push((voter, name));
+        }
+        if recipients.is_empty() {
+            return Err(BotError::user("Nobody else has voted in that poll yet"));
+        }
+        return Ok((recipients, after));
+    }
+
+    let Some(awardee) = replied.from() else {
+        return Err(BotError::user(format!(
+            "Reply to someone's message or mention them to award them {}",
+            award_type.one()
+        )));
+    };
+    remember_member(awardee).await?;
+    Ok((vec![(awardee.id, awardee.first_name.clone())], rest))
+}
+
+/// Gives an award of `award_type` from `author` to whoever `args` names, see
+/// [`recipients`], given for the reason written after them, if any. In jury
+/// mode, puts it to the chat's vote instead.
+async fn give(
+    bot: &Bot,
+    msg: &Message,
+    author: &User,
+    award_type: &AwardType,
+    args: &str,
+) -> HandlerResult {
+    let (mut recipients, reason) = recipients(msg, author, award_type, args).await?;
+    let reason = awards::reason(reason)?;
+    let mut seen = HashSet::new();
+    recipients.retain(|(id, _)| seen.insert(*id));
+    if recipients.len() > 1 {
+        return give_many(bot, msg, author, award_type, &recipients, reason).await;
+    }
+    let awardee = recipients[0].0;
+
+    let settings = settings::get(msg.chat.id).await?;
+    awards::check(msg.chat.id, award_type, author.id, awardee, &settings).await?;
+
+    if settings.jury {
+        let closes_at = i64::try_from(settings.jury_time.as_secs())
+            .ok()
+            .and_then(|secs| unix_time().checked_add(secs))
+            .ok_or_else(|| miette!("Jury time {:?} is out of range", settings.jury_time))?;
+        let jury = JuryRepo::new(db());
+        let pending_id = jury
+            .propose(
+                msg.chat.id,
+                &award_type.code,
+                author.id,
+                awardee,
+                reason,
+                closes_at,
+            )
+            .await?;
+        let sent = reply(
+            bot,
+            msg,
+            &settings,
+            jury::text(
+                award_type,
+                &JuryTally::default(),
+                settings.jury_votes,
+                closes_at,
+            ),
+        )
+        .reply_markup(jury::keyboard(pending_id))
+        .await
+        .into_diagnostic()?;
+        jury.set_message(pending_id, sent.id).await?;
+
+        return Ok(());
+    }
+
+    let awarded = AwardRepo::new(db())
+        .award(msg.chat.id, &award_type.code, author.id, awardee, reason)
+        .await?;
+    metrics::AWARDS_GIVEN
+        .with_label_values(&[&award_type.code])
+        .inc();
+
+    let sent = reply(bot, msg, &settings, awards::announcement(award_type, 1))
+        .reply_markup(awards::keyboard(awarded.id, award_type))
+        .await
+        .into_diagnostic()?;
+    cleanup::schedule(&settings, msg.chat.id, sent.id, Chatter::Confirmation).await?;
+    grant_badges(bot, msg.chat.id, author.id, awardee).await;
+
+    Ok(())
+}
+
+/// Gives an award of `award_type` from `author` to each of `recipients`,
+/// given by their ids and names, and announces them in one message. The
+/// awards are checked and recorded together, so either all are given or
+/// none is.
+async fn give_many(
+    bot: &Bot,
+    msg: &Message,
+    author: &User,
+    award_type: &AwardType,
+    recipients: &[(UserId, String)],
+    reason: Option<&str>,
+) -> HandlerResult {
+    if recipients.len() > awards::MAX_RECIPIENTS {
+        return Err(BotError::user(format!(
+            "{} can be given to up to {} members at once",
+            award_type.plural(),
+            awards::MAX_RECIPIENTS
+        )));
+    }
+    let settings = settings::get(msg.chat.id).await?;
+    if settings.jury {
+        return Err(BotError::user(format!(
+            "In jury mode, {} are given to one member at a time",
+            award_type.plural()
+        )));
+    }
+    for (receiver, _) in recipients {
+        awards::check(msg.chat.id, award_type, author.id, *receiver, &settings).await?;
+    }
+
+    let receivers = recipients.iter().map(|(id, _)| *id).collect::<Vec<_>>();
+    let awarded = AwardRepo::new(db())
+        .award_many(msg.chat.id, &award_type.code, author.id, &receivers, reason)
+        .await?;
+    metrics::AWARDS_GIVEN
+        .with_label_values(&[&award_type.code])
+        .inc_by(awarded.len() as u64);
+
+    let names = recipients
+        .iter()
+        .map(|(_, name)| name.clone())
+        .collect::<Vec<_>>();
+    let sent = reply(
+        bot,
+        msg,
+        &settings,
+        awards::bulk_announcement(award_type, &names),
+    )
+    .await
+    .into_diagnostic()?;
+    cleanup::schedule(&settings, msg.chat.id, sent.id, Chatter::Confirmation).await?;
+    for receiver in receivers {
+        grant_badges(bot, msg.chat.id, author.id, receiver).await;
+    }
+
+    Ok(())
+}
+
+/// Renders the scoreboard of `chat_id` in MarkdownV2. `view` is as for
+/// [`standings`].
+async fn scoreboard(bot: &Bot, chat_id: ChatId, view: &str) -> HandlerResult<String> {
+    let settings = settings::get(chat_id).await?;
+    let standings = standings(chat_id, &settings, view).await?;
+    if standings.members.is_empty() {
+        return Ok(String::from("Scoreboard is empty\\!"));
+    }
+
+    let lines = scoreboard_lines(bot, chat_id, &standings.members, &standings.rows).await?;
+    Ok(with_decay_note(lines, &settings))
+}
+
+/// Members on a chat's scoreboard, best first.
+struct Standings {
+    /// Each member's Telegram id and last known name
+    members: Vec<(String, Option<String>)>,
+    /// Each member's scores in MarkdownV2
+    rows: Vec<String>,
+    /// Each member's score in plain text
+    summaries: Vec<String>,
+}
+
+/// Ranks the members of `chat_id`, with scores decayed as its `settings`
+/// say. `view` is `net` for everyone's net score, or the code of an award
+/// type to rank by. Without one, the net scores are shown once more than one
+/// type was given.
+async fn standings(
+    chat_id: ChatId,
+    settings: &ChatSettings,
+    view: &str,
+) -> HandlerResult<Standings> {
+    let view = view.trim().to_lowercase();
+    let types = award_types::all(chat_id).await?;
+    let scores = decay::chat_scores(chat_id, settings).await?;
+    // Scores of types the chat removed are left out
+    let given = types
+        .iter()
+        .filter(|award_type| {
+            scores
+                .iter()
+                .any(|score| score.award_type == award_type.code)
+        })
+        .cloned()
+        .collect::<Vec<_>>();
+
+    let net = match view.as_str() {
+        "net" => true,
+        "" => given.len() > 1,
+        _ => false,
+    };
+    if !net {
+        let award_type = match view.as_str() {
+            "" => given.first().unwrap_or(&types[0]).clone(),
+            code => award_types::lookup(chat_id, code).await?,
+        };
+        let stats = decay::scoreboard(chat_id, settings, &award_type.code).await?;
+
+        return Ok(Standings {
+            members: stats
+                .iter()
+                .map(|stat| (stat.tg_user_id.clone(), stat.first_name.clone()))
+                .collect(),
+            rows: stats
+                .iter()
+                .map(|stat| {
+                    format!(
+                        "*{}* {}",
+                        stat.amount,
+                        markdown::escape(&award_type.plural())
+                    )
+                })
+                .collect(),
+            summaries: stats.iter().map(|stat| stat.amount.to_string()).collect(),
+        });
+    }
+
+    let members = award_types::net_scores(&given, &scores);
+    let rows = members
+        .iter()
+        .map(|member| {
+            let mut columns = given
+                .iter()
+                .zip(&member.amounts)
+                .map(|(award_type, amount)| format!("{} *{}*", award_type.emoji, amount))
+                .collect::<Vec<_>>();
+            columns.push(format!(
+                "net *{}*",
+                markdown::escape(&member.net.to_string())
+            ));
+            columns.join(" · ")
+        })
+        .collect();
+    let summaries = members
+        .iter()
+        .map(|member| format!("net {}", member.net))
+        .collect();
+
+    Ok(Standings {
+        members: members
+            .into_iter()
+            .map(|member| (member.tg_user_id, member.first_name))
+            .collect(),
+        rows,
+        summaries,
+    })
+}
+
+/// Adds how scores decay under `settings`, if they do, below the
+/// scoreboard `lines`.
+fn with_decay_note(lines: String, settings: &ChatSettings) -> String {
+    match decay::note(settings) {
+        Some(note) => format!("{}\n\n{}", lines, note),
+        None => lines,
+    }
+}
+
+/// Lines of a scoreboard, each with a member of `members`, identified by
+/// their Telegram id and last known name, their badges and their `rows`.
+async fn scoreboard_lines(
+    bot: &Bot,
+    chat_id: ChatId,
+    members: &[(String, Option<String>)],
+    rows: &[String],
+) -> Result<String> {
+    let names = display_names(bot, chat_id, members).await;
+    let earned = BadgeRepo::new(db()).earned_in_chat(chat_id).await?;
+
+    let lines = members
+        .iter()
+        .zip(names)
+        .zip(rows)
+        .map(|(((tg_user_id, _), name), row)| {
+            let emojis = badges::emojis(
+                earned
+                    .iter()
+                    .filter(|badge| badge.member_id.to_string() == *tg_user_id),
+            );
+            format!(
+                "__{}__{}{} — {}",
+                markdown::escape(&name),
+                if emojis.is_empty() { "" } else { " " },
+                emojis,
+                row
+            )
+        })
+        .collect::<Vec<_>>();
+
+    Ok(lines.join("\n"))
+}
+
+/// Maximum number of `get_chat_member` lookups in flight at once
+const MEMBER_LOOKUP_CONCURRENCY: usize = 5;
+
+/// Looks up the current display name of each of `members`, given by their
+/// Telegram id and last known name. Members who can't be found in the chat
+/// anymore are shown with their last known name.
+async fn display_names(
+    bot: &Bot,
+    chat_id: ChatId,
+    members: &[(String, Option<String>)],
+) -> Vec<String> {
+    let lookups = members
+        .iter()
+        .map(|(tg_user_id, first_name)| {
+            display_name(bot.clone(), chat_id, tg_user_id.clone(), first_name.clone())
+        })
+        .collect::<Vec<_>>();
+
+    stream::iter(lookups)
+        .buffered(MEMBER_LOOKUP_CONCURRENCY)
+        .collect()
+        .await
+}
+
+async fn display_name(
+    bot: Bot,
+    chat_id: ChatId,
+    tg_user_id: String,
+    last_known_name: Option<String>,
+) -> String {
+    let member = match tg_user_id.parse() {
+        Ok(user_id) => bot.get_chat_member(chat_id, UserId(user_id)).await.ok(),
+        Err(_) => None,
+    };
+
+    match member {
+        Some(member) if member.is_present() => {
+            if let Err(err) = remember_member(&member.user).await {
+                warn!("Failed to update member name: {:?}", err);
+            }
+            member.user.first_name
+        }
+        _ => last_known_name.unwrap_or_else(|| String::from("(left)")),
+    }
+}
+
+/// Remembers who sent `msg`, if the chat awards Ls by reacting to messages.
+async fn remember_author(msg: &Message) -> Result<()> {
+    let Some(author) = msg.from().filter(|author| !author.is_bot) else {
+        return Ok(());
+    };
+    if settings::get(msg.chat.id).await?.reaction.is_none() {
+        return Ok(());
+    }
+
+    ReactionRepo::new(db())
+        .remember_author(msg.chat.id, msg.id, author.id)
+        .await
+}
+
+/// Grants the badges the giver and receiver of an L earned with it. The L
+/// counts even if this fails.
+async fn grant_badges(bot: &Bot, chat_id: ChatId, giver: UserId, receiver: UserId) {
+    if let Err(err) = badges::grant_earned(bot, chat_id, &[giver, receiver]).await {
+        warn!("Failed to grant badges: {:?}", err);
+    }
+}
+
+/// Records `user` as a member, refreshing their last known name and username.
+async fn remember_member(user: &User) -> Result<()> {
+    MemberRepo::new(db())
+        .remember(user.id, &user.first_name, user.username.as_deref())
+        .await
+}
+
 async fn command_handler(
     cmd: Command,
     bot: Bot,
     me: teloxide::types::Me,
     msg: Message,
 ) -> Result<()> {
-    if let Some(response) = Command::handle(&cmd, bot.clone(), me, msg.clone()).await? {
-        bot.send_message(msg.chat.id, response)
-            .parse_mode(ParseMode::MarkdownV2)
-            .reply_to_message_id(msg.id)
+    metrics::COMMANDS_HANDLED
+        .with_label_values(&[cmd.name()])
+        .inc();
+
+    let result: HandlerResult = async {
+        let settings = settings::get(msg.chat.id).await?;
+        cleanup::schedule(&settings, msg.chat.id, msg.id, Chatter::Command).await?;
+
+        if let Some(response) = Command::handle(&cmd, bot.clone(), me, msg.clone()).await? {
+            // Commands may have changed the settings
+            let settings = settings::get(msg.chat.id).await?;
+            let sent = reply(&bot, &msg, &settings, response)
+                .parse_mode(ParseMode::MarkdownV2)
+                .await
+                .into_diagnostic()?;
+            cleanup::schedule(&settings, msg.chat.id, sent.id, Chatter::Confirmation).await?;
+        }
+
+        Ok(())
+    }
+    .await;
+
+    BotError::report(result, "command", &bot, &msg).await
+}
+
+async fn fallback_handler(bot: Bot, msg: Message) -> Result<()> {
+    let result: HandlerResult = async {
+        let Some(content) = msg.text() else {
+            return Err(BotError::NoMatch("Message content not found"));
+        };
+        let settings = settings::get(msg.chat.id).await?;
+        if !settings.fallback_replies {
+            return Err(BotError::NoMatch("Fallback replies are turned off"));
+        }
+        let ncontent = text::normalize(content);
+
+        let turns = PhraseRepo::new(db()).responses(&ncontent).await?;
+
+        if turns.is_empty() {
+            metrics::FALLBACK_MISSES.inc();
+            return Err(BotError::NoMatch("No dialog matched"));
+        }
+        metrics::FALLBACK_MATCHES.inc();
+
+        let Some(turn) = turns.choose(&mut rand::thread_rng()) else {
+            return Err(miette!("Failed to choose random dialog turn").into());
+        };
+
+        let sent = reply(&bot, &msg, &settings, &turn.response)
             .await
             .into_diagnostic()?;
+        cleanup::schedule(&settings, msg.chat.id, sent.id, Chatter::Reply).await?;
+
+        Ok(())
+    }
+    .await;
+
+    BotError::report(result, "fallback", &bot, &msg).await
+}
+
+async fn callback_handler(bot: Bot, query: CallbackQuery) -> Result<()> {
+    let result: HandlerResult = async {
+        let Some(data) = query.data.as_deref() else {
+            return Err(BotError::NoMatch("Callback query has no data"));
+        };
+
+        if let Some(name) = data.strip_prefix(settings::CALLBACK_PREFIX) {
+            return toggle_setting(&bot, &query, name).await;
+        }
+        if let Some(data) = data.strip_prefix(awards::CALLBACK_PREFIX) {
+            let Some((action, award_id)) = Action::parse(data) else {
+                return Err(BotError::NoMatch("Malformed award callback data"));
+            };
+            return award_button(&bot, &query, action, award_id).await;
+        }
+        if let Some(data) = data.strip_prefix(jury::CALLBACK_PREFIX) {
+            let Some((approve, pending_id)) = jury::parse_vote(data) else {
+                return Err(BotError::NoMatch("Malformed jury callback data"));
+            };
+            return jury_vote(&bot, &query, approve, pending_id).await;
+        }
+        if let Some(data) = data.strip_prefix(awards::DISPUTE_CALLBACK_PREFIX) {
+            let Some((keep, award_id)) = awards::parse_vote(data) else {
+                return Err(BotError::NoMatch("Malformed dispute callback data"));
+            };
+            return dispute_vote(&bot, &query, keep, award_id).await;
+        }
+
+        Err(BotError::NoMatch("Unknown callback data"))
     }
+    .await;
+
+    BotError::report_callback(result, "callback", &bot, &query).await
+}
+
+/// Flips a setting from a button of the `/settings` keyboard.
+async fn toggle_setting(bot: &Bot, query: &CallbackQuery, name: &str) -> HandlerResult {
+    let Some(msg) = &query.message else {
+        return Err(BotError::NoMatch("Callback query has no message"));
+    };
+    let Some(setting) = Setting::from_name(name) else {
+        return Err(BotError::NoMatch("Unknown setting"));
+    };
+    if !permissions::has_role(bot, &msg.chat, query.from.id, Role::ChatAdmin).await? {
+        return Err(BotError::user("Only chat admins can change settings"));
+    }
+
+    let Some(value) = setting.toggled(&settings::get(msg.chat.id).await?) else {
+        return Err(BotError::NoMatch("Setting has no toggle"));
+    };
+    let settings = settings::set(msg.chat.id, setting, value).await?;
+
+    bot.edit_message_text(msg.chat.id, msg.id, settings::render(&settings))
+        .reply_markup(settings::keyboard(&settings))
+        .await
+        .into_diagnostic()?;
+    bot.answer_callback_query(&query.id)
+        .text(format!("{} is now {}", setting.name(), value))
+        .await
+        .into_diagnostic()?;
 
     Ok(())
 }
 
-async fn fallback_handler(bot: Bot, msg: Message) -> Result<()> {
-    let Some(content) = msg.text() else {
-        bail!("Message content not found");
+/// Gives or takes back an L when a member adds or removes the chat's award
+/// reaction on a message.
+async fn reaction_handler(bot: Bot, reaction: MessageReactionUpdated) -> Result<()> {
+    let chat_id = reaction.chat.id;
+    let result: HandlerResult = async {
+        let Some(reactor) = &reaction.user else {
+            return Err(BotError::NoMatch("Reaction is anonymous"));
+        };
+        let settings = settings::get(chat_id).await?;
+        let Some(emoji) = &settings.reaction else {
+            return Err(BotError::NoMatch("Chat doesn't award Ls by reaction"));
+        };
+        let Some(change) = reaction.change(emoji) else {
+            return Err(BotError::NoMatch("Reaction doesn't award an L"));
+        };
+        let reactions = ReactionRepo::new(db());
+
+        match change {
+            Change::Added => {
+                if settings.jury {
+                    return Err(BotError::NoMatch("Reactions don't award Ls in jury mode"));
+                }
+                let Some(author) = reactions.author(chat_id, reaction.message_id()).await? else {
+                    return Err(BotError::NoMatch("Author of the message is unknown"));
+                };
+                remember_member(reactor).await?;
+                let award_type = award_types::get(chat_id, award_types::L).await?;
+                awards::check(chat_id, &award_type, reactor.id, author, &settings).await?;
+
+                if reactions
+                    .award(chat_id, reaction.message_id(), reactor.id, author)
+                    .await?
+                    .is_some()
+                {
+                    metrics::AWARDS_GIVEN
+                        .with_label_values(&[&award_type.code])
+                        .inc();
+                    grant_badges(&bot, chat_id, reactor.id, author).await;
+                }
+            }
+            Change::Removed => {
+                let Some(award_id) = reactions
+                    .award_id(chat_id, reaction.message_id(), reactor.id)
+                    .await?
+                else {
+                    return Err(BotError::NoMatch("Reaction didn't award an L"));
+                };
+                let awards = AwardRepo::new(db());
+                if let Some(award) = awards.get(award_id).await? {
+                    awards.withdraw(&award).await?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+    .await;
+
+    BotError::report_to(result, "reaction", &bot, chat_id, reaction.message_id()).await
+}
+
+/// Keeps track of who has a vote in the polls the bot sent.
+async fn poll_answer_handler(answer: PollAnswer) -> Result<()> {
+    remember_member(&answer.user).await?;
+    PollRepo::new(db())
+        .vote(
+            &answer.poll_id,
+            answer.user.id,
+            !answer.option_ids.is_empty(),
+        )
+        .await
+}
+
+/// Answers an inline query with the scoreboards of the querying member's
+/// chats, or with the responses learned for a phrase.
+async fn inline_handler(bot: Bot, query: InlineQuery) -> Result<()> {
+    remember_member(&query.from).await?;
+
+    let results = match inline::parse(&query.query) {
+        inline::Query::Scores => {
+            let chat_ids = AwardRepo::new(db()).chats_of(query.from.id).await?;
+            let lookups = chat_ids
+                .into_iter()
+                .map(|chat_id| member_chat(bot.clone(), chat_id, query.from.id))
+                .collect::<Vec<_>>();
+            // Chats the member left are skipped before counting towards the
+            // most results
+            let chats = stream::iter(lookups)
+                .buffered(MEMBER_LOOKUP_CONCURRENCY)
+                .filter_map(future::ready)
+                .take(inline::MAX_RESULTS)
+                .collect::<Vec<_>>()
+                .await;
+
+            let mut results = Vec::new();
+            for (chat_id, title) in chats {
+                let settings = settings::get(chat_id).await?;
+                let standings = standings(chat_id, &settings, "").await?;
+                let rows = standings
+                    .members
+                    .into_iter()
+                    .zip(standings.rows)
+                    .zip(standings.summaries)
+                    .map(|(((_, first_name), scores), summary)| inline::CardRow {
+                        name: first_name.unwrap_or_else(|| String::from("(left)")),
+                        scores,
+                        summary,
+                    })
+                    .collect::<Vec<_>>();
+                results.push(inline::scoreboard_article(
+                    chat_id,
+                    &title,
+                    &rows,
+                    decay::note(&settings),
+                ));
+            }
+            results
+        }
+        inline::Query::Phrase(phrase) => {
+            let turns = PhraseRepo::new(db())
+                .responses(&text::normalize(phrase))
+                .await?;
+            inline::phrase_articles(&turns)
+        }
+        inline::Query::Unknown => Vec::new(),
     };
-    let ncontent = text::normalize(content);
-
-    let turns = sqlx::query_as::<_, DialogTurn>(
-        r#"
-        SELECT
-            Phrase.content   AS phrase,
-            Response.content AS response
-        FROM Phrase
-        LEFT JOIN Response
-               ON Response.phraseId = Phrase.id
-        WHERE phrase = ?1
-        "#,
-    )
-    .bind(ncontent)
-    .fetch_all(db())
-    .await
-    .into_diagnostic()?;
 
-    if turns.is_empty() {
-        bail!("No dialog matched");
+    // Scoreboards differ between members
+    bot.answer_inline_query(query.id, results)
+        .is_personal(true)
+        .cache_time(inline::CACHE_SECS)
+        .await
+        .into_diagnostic()?;
+
+    Ok(())
+}
+
+/// The title of `chat_id`, if `user_id` is still a member there, so its
+/// scoreboard can't be seen from outside.
+async fn member_chat(bot: Bot, chat_id: ChatId, user_id: UserId) -> Option<(ChatId, String)> {
+    let member = bot.get_chat_member(chat_id, user_id).await.ok()?;
+    if !member.is_present() {
+        return None;
     }
+    let chat = bot.get_chat(chat_id).await.ok()?;
 
-    let Some(turn) = turns.choose(&mut rand::thread_rng()) else {
-        bail!("Failed to choose random dialog turn");
+    Some((chat_id, chat.title().unwrap_or("a private chat").to_owned()))
+}
+
+/// Handles a button under an award. `award_id` is the award the message
+/// announced, which later presses of "+1 L" are added to.
+async fn award_button(
+    bot: &Bot,
+    query: &CallbackQuery,
+    action: Action,
+    award_id: i64,
+) -> HandlerResult {
+    let Some(msg) = &query.message else {
+        return Err(BotError::NoMatch("Callback query has no message"));
+    };
+    let awards = AwardRepo::new(db());
+    let Some(award) = awards
+        .get(award_id)
+        .await?
+        .filter(|award| award.chat_id == msg.chat.id.0)
+    else {
+        return Err(BotError::user("This L has been taken back"));
     };
+    let award_type = award_types::get(msg.chat.id, &award.award_type).await?;
+    let presser = query.from.id;
+    remember_member(&query.from).await?;
+
+    // The award heading the group, which changes if its giver takes it back
+    let mut root_id = award.id;
+    let answer = match action {
+        Action::PlusOne => {
+            if awards.given_in_group(award.id, presser).await?.is_some() {
+                return Err(BotError::user(format!(
+                    "You already gave this {}",
+                    award_type.name
+                )));
+            }
+            let settings = settings::get(msg.chat.id).await?;
+            let receiver = UserId(award.receiver_id as u64);
+            awards::check(msg.chat.id, &award_type, presser, receiver, &settings).await?;
 
-    bot.send_message(msg.chat.id, &turn.response)
-        .reply_to_message_id(msg.id)
+            awards.pile_on(&award, presser).await?;
+            metrics::AWARDS_GIVEN
+                .with_label_values(&[&award_type.code])
+                .inc();
+            grant_badges(bot, msg.chat.id, presser, receiver).await;
+            format!("+1 {} given", award_type.name)
+        }
+        Action::Undo => {
+            match awards.given_in_group(award.id, presser).await? {
+                // Givers only take back their own award, so the others keep
+                // counting
+                Some(given) => {
+                    root_id = awards.withdraw(&given).await?.unwrap_or(award.id);
+                }
+                // Chat admins can take back anyone's L, along with the Ls
+                // added to it
+                None if permissions::has_role(bot, &msg.chat, presser, Role::ChatAdmin).await? => {
+                    awards.revoke(&award).await?;
+                }
+                None => {
+                    return Err(BotError::user(format!(
+                        "Only its givers and chat admins can undo this {}",
+                        award_type.name
+                    )))
+                }
+            }
+            format!("{} taken back", award_type.name)
+        }
+        Action::Dispute => {
+            if presser.0 as i64 == award.giver_id {
+                return Err(BotError::user(format!(
+                    "You can't dispute {} you gave",
+                    award_type.one()
+                )));
+            }
+            if !DisputeRepo::new(db()).open(award.id, presser).await? {
+                return Err(BotError::user(format!(
+                    "This {} is already disputed",
+                    award_type.name
+                )));
+            }
+
+            let settings = settings::get(msg.chat.id).await?;
+            bot.send_message(
+                msg.chat.id,
+                awards::dispute_text(
+                    &award_type,
+                    &DisputeTally::default(),
+                    settings.dispute_votes,
+                ),
+            )
+            .reply_to_message_id(msg.id)
+            .reply_markup(awards::dispute_keyboard(award.id))
+            .await
+            .into_diagnostic()?;
+            String::from("Vote opened")
+        }
+    };
+
+    match awards.group_size(root_id).await? {
+        0 => {
+            bot.edit_message_text(
+                msg.chat.id,
+                msg.id,
+                format!("{} has been taken back", award_type.name),
+            )
+            .await
+            .into_diagnostic()?;
+        }
+        count => {
+            bot.edit_message_text(
+                msg.chat.id,
+                msg.id,
+                awards::announcement(&award_type, count),
+            )
+            .reply_markup(awards::keyboard(root_id, &award_type))
+            .await
+            .into_diagnostic()?;
+        }
+    }
+    bot.answer_callback_query(&query.id)
+        .text(answer)
+        .await
+        .into_diagnostic()?;
+
+    Ok(())
+}
+
+/// Counts a vote on a disputed L, settling the dispute once enough members
+/// agree.
+async fn dispute_vote(
+    bot: &Bot,
+    query: &CallbackQuery,
+    keep: bool,
+    award_id: i64,
+) -> HandlerResult {
+    let Some(msg) = &query.message else {
+        return Err(BotError::NoMatch("Callback query has no message"));
+    };
+    let awards = AwardRepo::new(db());
+    let Some(award) = awards
+        .get(award_id)
+        .await?
+        .filter(|award| award.chat_id == msg.chat.id.0)
+    else {
+        return Err(BotError::user("This L has been taken back"));
+    };
+    let disputes = DisputeRepo::new(db());
+    let Some(dispute) = disputes.get(award.id).await? else {
+        return Err(BotError::NoMatch("Award is not disputed"));
+    };
+    if dispute.outcome.is_some() {
+        return Err(BotError::user("This vote is over"));
+    }
+    let award_type = award_types::get(msg.chat.id, &award.award_type).await?;
+    let voter = query.from.id;
+    if [award.giver_id, award.receiver_id].contains(&(voter.0 as i64)) {
+        return Err(BotError::user(format!(
+            "You can't vote on {} you gave or received",
+            award_type.one()
+        )));
+    }
+    remember_member(&query.from).await?;
+
+    let settings = settings::get(msg.chat.id).await?;
+    let needed = i64::from(settings.dispute_votes);
+    let tally = disputes.vote(award.id, voter, keep).await?;
+
+    let edit = if tally.drop_votes >= needed {
+        awards.revoke(&award).await?;
+        bot.edit_message_text(
+            msg.chat.id,
+            msg.id,
+            format!("The {} was dropped after a vote", award_type.name),
+        )
+    } else if tally.keep_votes >= needed {
+        disputes.keep(award.id).await?;
+        bot.edit_message_text(
+            msg.chat.id,
+            msg.id,
+            format!("The {} stands after a vote", award_type.name),
+        )
+    } else {
+        bot.edit_message_text(
+            msg.chat.id,
+            msg.id,
+            awards::dispute_text(&award_type, &tally, needed as u32),
+        )
+        .reply_markup(awards::dispute_keyboard(award.id))
+    };
+    edit.await.into_diagnostic()?;
+    bot.answer_callback_query(&query.id)
+        .text("Vote counted")
+        .await
+        .into_diagnostic()?;
+
+    Ok(())
+}
+
+/// Counts a vote on an L waiting for the chat's approval, awarding or
+/// discarding it once enough members agree.
+async fn jury_vote(
+    bot: &Bot,
+    query: &CallbackQuery,
+    approve: bool,
+    pending_id: i64,
+) -> HandlerResult {
+    let Some(msg) = &query.message else {
+        return Err(BotError::NoMatch("Callback query has no message"));
+    };
+    let jury = JuryRepo::new(db());
+    let Some(pending) = jury
+        .get(pending_id)
+        .await?
+        .filter(|pending| pending.chat_id == msg.chat.id.0 && pending.closes_at > unix_time())
+    else {
+        return Err(BotError::user("This vote is over"));
+    };
+    let award_type = award_types::get(msg.chat.id, &pending.award_type).await?;
+    let voter = query.from.id;
+    if [pending.giver_id, pending.receiver_id].contains(&(voter.0 as i64)) {
+        return Err(BotError::user(format!(
+            "You can't vote on {} you gave or received",
+            award_type.one()
+        )));
+    }
+    remember_member(&query.from).await?;
+
+    let settings = settings::get(msg.chat.id).await?;
+    let needed = i64::from(settings.jury_votes);
+    let tally = jury.vote(pending.id, voter, approve).await?;
+
+    if tally.approvals >= needed {
+        let Some(awarded) = jury.confirm(&pending).await? else {
+            return Err(BotError::user("This vote is over"));
+        };
+        metrics::AWARDS_GIVEN
+            .with_label_values(&[&award_type.code])
+            .inc();
+        grant_badges(
+            bot,
+            msg.chat.id,
+            UserId(pending.giver_id as u64),
+            UserId(pending.receiver_id as u64),
+        )
+        .await;
+        bot.edit_message_text(
+            msg.chat.id,
+            msg.id,
+            format!("The jury approved this {}, so it counts", award_type.name),
+        )
+        .reply_markup(awards::keyboard(awarded.id, &award_type))
+        .await
+        .into_diagnostic()?;
+    } else if tally.rejections >= needed {
+        if !jury.discard(pending.id).await? {
+            return Err(BotError::user("This vote is over"));
+        }
+        bot.edit_message_text(
+            msg.chat.id,
+            msg.id,
+            format!("The jury rejected this {}", award_type.name),
+        )
+        .await
+        .into_diagnostic()?;
+    } else {
+        bot.edit_message_text(
+            msg.chat.id,
+            msg.id,
+            jury::text(&award_type, &tally, settings.jury_votes, pending.closes_at),
+        )
+        .reply_markup(jury::keyboard(pending.id))
+        .await
+        .into_diagnostic()?;
+    }
+    bot.answer_callback_query(&query.id)
+        .text("Vote counted")
         .await
         .into_diagnostic()?;
 
     Ok(())
 }
 
-fn schema() -> UpdateHandler<miette::Error> {
+/// Starts a message to the chat of `msg`, quoting `msg` if the chat's reply
+/// style asks for that.
+fn reply(
+    bot: &Bot,
+    msg: &Message,
+    settings: &ChatSettings,
+    text: impl Into<String>,
+) -> JsonRequest<SendMessage> {
+    let request = bot.send_message(msg.chat.id, text);
+    match settings.reply_style {
+        ReplyStyle::Quote => request.reply_to_message_id(msg.id),
+        ReplyStyle::Plain => request,
+    }
+}
+
+pub fn schema() -> UpdateHandler<miette::Error> {
+    dptree::entry()
+        .branch(Update::filter_callback_query().endpoint(callback_handler))
+        .branch(
+            dptree::filter_map(|update: Update| MessageReactionUpdated::from_update(&update))
+                .endpoint(reaction_handler),
+        )
+        .branch(Update::filter_poll_answer().endpoint(poll_answer_handler))
+        .branch(Update::filter_inline_query().endpoint(inline_handler))
+        .branch(message_handler())
+}
+
+fn message_handler() -> UpdateHandler<miette::Error> {
     Update::filter_message()
+        .inspect_async(|msg: Message| async move {
+            if let Some(author) = msg.from() {
+                if let Err(err) = remember_member(author).await {
+                    warn!("Failed to remember message author: {:?}", err);
+                }
+            }
+            if let Err(err) = remember_author(&msg).await {
+                warn!("Failed to remember who sent a message: {:?}", err);
+            }
+        })
         // You can use branching to define multiple ways in which an update will be handled. If the
         // first branch fails, an update will be passed to the second branch, and so on.
         .branch(
@@ -330,10 +1632,19 @@ fn schema() -> UpdateHandler<miette::Error> {
             dptree::filter_map_async(|msg: Message, me: teloxide::types::Me| async move {
                 debug!("Incoming text message: {:#?}", msg);
 
-                let mut input;
-                let Some(text) = msg.text() else {
-                    return None
+                let settings = match settings::get(msg.chat.id).await {
+                    Ok(settings) => settings,
+                    Err(err) => {
+                        warn!("Failed to read chat settings: {:?}", err);
+                        return None;
+                    }
                 };
+                if !settings.nlu {
+                    return None;
+                }
+
+                let mut input;
+                let text = msg.text()?;
                 input = text.to_string();
                 if text.is_empty() {
                     // handle reply
@@ -345,24 +1656,35 @@ fn schema() -> UpdateHandler<miette::Error> {
                         _ => unimplemented!(),
                     }
                 }
-                Command::from_phrase(input, me).await
+                Command::from_phrase(input, settings.language, me).await
             })
             .endpoint(command_handler),
         )
         .branch(Message::filter_text().endpoint(fallback_handler))
 }
 
-pub async fn run_bot() -> Result<()> {
+pub fn new_bot() -> Result<Bot> {
     let token = env::var("TELEGRAM_API_TOKEN")
         .into_diagnostic()
         .wrap_err("TELEGRAM_API_TOKEN not found in environment")?;
-    let bot = Bot::new(token);
 
+    Ok(Bot::new(token))
+}
+
+pub async fn set_commands(bot: &Bot) -> Result<()> {
     bot.set_my_commands(Command::bot_commands())
         .await
         .into_diagnostic()?;
 
-    Dispatcher::builder(bot, schema())
+    Ok(())
+}
+
+pub async fn run_bot(config: &Config) -> Result<()> {
+    let bot = new_bot()?;
+
+    set_commands(&bot).await?;
+
+    let mut dispatcher = Dispatcher::builder(bot.clone(), schema())
         .dependencies(dptree::deps![])
         .default_handler(|update| async move {
             warn!("Unhandled update: {:?}", update);
@@ -371,9 +1693,73 @@ pub async fn run_bot() -> Result<()> {
             "An error has occurred in the dispatcher",
         ))
         .enable_ctrlc_handler()
-        .build()
-        .dispatch()
-        .await;
+        .build();
+
+    let (stop_health, health_stopped) = oneshot::channel::<()>();
+    let health_server = config.metrics_address.map(|address| {
+        tokio::spawn(webhook::serve(health::router(), address, async {
+            health_stopped.await.ok();
+        }))
+    });
+
+    let mut scheduler = Scheduler::new(bot.clone())
+        .every("jury-expiry", jury::EXPIRY_INTERVAL, jury::run_expiry)
+        .every("cleanup", cleanup::SWEEP_INTERVAL, cleanup::run_sweep)
+        .every("digest", digest::CHECK_INTERVAL, digest::run_posting);
+    match &config.backup {
+        Some(_) if !backup::is_supported(db()) => {
+            warn!("BACKUP_DIR is ignored, scheduled backups are only taken of SQLite databases");
+        }
+        Some(backup_config) => {
+            let backup_config = backup_config.clone();
+            scheduler = scheduler.every("backup", backup_config.interval, move |_| {
+                let backup_config = backup_config.clone();
+                async move { backup::run_scheduled(&backup_config).await }
+            });
+        }
+        None => {}
+    }
+    let scheduler = scheduler.start().await?;
+
+    permissions::set_owners(config.owners.iter().copied());
+
+    health::set_dispatcher_alive(true);
+    match &config.update_mode {
+        UpdateMode::Polling => {
+            let listener = update_listeners::polling_default(bot.clone()).await;
+            updates::subscribe(&bot).await?;
+
+            dispatcher
+                .dispatch_with_listener(
+                    KeepAllowedUpdates(listener),
+                    LoggingErrorHandler::with_custom_text("An error from the update listener"),
+                )
+                .await;
+        }
+        UpdateMode::Webhook(webhook_config) => {
+            let options = webhook::options(webhook_config);
+            webhook::set_webhook(&bot, &options).await?;
+
+            let (listener, stopped, router) = webhook::listener(options);
+            let server = tokio::spawn(webhook::serve(router, webhook_config.address, stopped));
+
+            dispatcher
+                .dispatch_with_listener(
+                    listener,
+                    LoggingErrorHandler::with_custom_text("An error from the update listener"),
+                )
+                .await;
+
+            server.await.into_diagnostic()??;
+        }
+    }
+    health::set_dispatcher_alive(false);
+
+    scheduler.shutdown().await;
+    stop_health.send(()).ok();
+    if let Some(health_server) = health_server {
+        health_server.await.into_diagnostic()??;
+    }
 
     Ok(())
 }
@@ -0,0 +1,144 @@
+//! An RGB image that shapes and text can be drawn on, and saved as a PNG.
+
+use flate2::{write::ZlibEncoder, Compression};
+use std::io::Write;
+
+use super::font::{self, GLYPH_HEIGHT, GLYPH_WIDTH};
+
+pub type Color = [u8; 3];
+
+pub struct Canvas {
+    width: u32,
+    height: u32,
+    pixels: Vec<Color>,
+}
+
+impl Canvas {
+    /// A `width` by `height` image filled with `background`.
+    pub fn new(width: u32, height: u32, background: Color) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![background; (width * height) as usize],
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Colors a pixel, ignoring pixels outside the image.
+    pub fn set(&mut self, x: i64, y: i64, color: Color) {
+        if (0..self.width as i64).contains(&x) && (0..self.height as i64).contains(&y) {
+            self.pixels[(y as u32 * self.width + x as u32) as usize] = color;
+        }
+    }
+
+    pub fn fill_rect(&mut self, x: i64, y: i64, width: u32, height: u32, color: Color) {
+        for y in y..y + height as i64 {
+            for x in x..x + width as i64 {
+                self.set(x, y, color);
+            }
+        }
+    }
+
+    /// Draws a line `thickness` pixels wide from one point to another.
+    pub fn line(&mut self, from: (i64, i64), to: (i64, i64), thickness: u32, color: Color) {
+        let (mut x, mut y) = from;
+        let dx = (to.0 - x).abs();
+        let dy = -(to.1 - y).abs();
+        let step_x = if x < to.0 { 1 } else { -1 };
+        let step_y = if y < to.1 { 1 } else { -1 };
+        let offset = thickness as i64 / 2;
+        let mut error = dx + dy;
+
+        loop {
+            self.fill_rect(x - offset, y - offset, thickness, thickness, color);
+            if (x, y) == to {
+                break;
+            }
+            let doubled = 2 * error;
+            if doubled >= dy {
+                error += dy;
+                x += step_x;
+            }
+            if doubled <= dx {
+                error += dx;
+                y += step_y;
+            }
+        }
+    }
+
+    /// Writes `text` with its top left corner at `(x, y)`, each font pixel
+    /// drawn `scale` pixels wide.
+    pub fn text(&mut self, x: i64, y: i64, text: &str, scale: u32, color: Color) {
+        let scale = scale as i64;
+        for (i, c) in text.chars().enumerate() {
+            let left = x + i as i64 * (GLYPH_WIDTH as i64 + 1) * scale;
+            for (column, bits) in font::glyph(c).iter().enumerate() {
+                for row in 0..GLYPH_HEIGHT as i64 {
+                    if bits & (1 << row) != 0 {
+                        self.fill_rect(
+                            left + column as i64 * scale,
+                            y + row * scale,
+                            scale as u32,
+                            scale as u32,
+                            color,
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    /// How many pixels wide `text` is when written at `scale`.
+    pub fn text_width(text: &str, scale: u32) -> u32 {
+        match text.chars().count() as u32 {
+            0 => 0,
+            chars => (chars * (GLYPH_WIDTH + 1) - 1) * scale,
+        }
+    }
+
+    /// Encodes the image as a PNG.
+    pub fn to_png(&self) -> Vec<u8> {
+        // Each row starts with the filter type, 0 for none
+        let mut raw = Vec::with_capacity(((self.width * 3 + 1) * self.height) as usize);
+        for row in self.pixels.chunks(self.width as usize) {
+            raw.push(0);
+            raw.extend(row.iter().flatten());
+        }
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder
+            .write_all(&raw)
+            .expect("Writing to memory can't fail");
+        let data = encoder.finish().expect("Writing to memory can't fail");
+
+        let mut header = Vec::with_capacity(13);
+        header.extend(self.width.to_be_bytes());
+        header.extend(self.height.to_be_bytes());
+        // 8 bits per channel, RGB, default compression, filtering and no
+        // interlacing
+        header.extend([8, 2, 0, 0, 0]);
+
+        let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+        chunk(&mut png, b"IHDR", &header);
+        chunk(&mut png, b"IDAT", &data);
+        chunk(&mut png, b"IEND", &[]);
+        png
+    }
+}
+
+fn chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend((data.len() as u32).to_be_bytes());
+    png.extend(kind);
+    png.extend(data);
+
+    let mut crc = crc32fast::Hasher::new();
+    crc.update(kind);
+    crc.update(data);
+    png.extend(crc.finalize().to_be_bytes());
+}
@@ -0,0 +1,243 @@
+//! Charts of the Ls in a chat, drawn as PNG images.
+
+mod canvas;
+mod font;
+
+use chrono::{NaiveDate, TimeZone, Utc};
+use chrono_tz::Tz;
+use unicode_normalization::{char::is_combining_mark, UnicodeNormalization};
+
+use crate::db::models::MemberStat;
+use canvas::{Canvas, Color};
+use font::GLYPH_HEIGHT;
+
+/// Most members the scoreboard chart shows bars for
+pub const MAX_BARS: usize = 30;
+
+/// Names longer than this many characters are cut short
+const MAX_NAME_CHARS: usize = 16;
+
+const BACKGROUND: Color = [0xff, 0xff, 0xff];
+const INK: Color = [0x22, 0x22, 0x22];
+const FAINT: Color = [0xdd, 0xdd, 0xdd];
+const ACCENT: Color = [0xe0, 0x4e, 0x39];
+
+const PADDING: u32 = 20;
+const TITLE_SCALE: u32 = 3;
+const LABEL_SCALE: u32 = 2;
+const ROW_HEIGHT: u32 = 28;
+const BAR_HEIGHT: u32 = 20;
+const BAR_AREA_WIDTH: u32 = 480;
+const GAP: u32 = 12;
+
+const LINE_CHART_WIDTH: u32 = 800;
+const LINE_CHART_HEIGHT: u32 = 420;
+
+fn label_height(scale: u32) -> u32 {
+    GLYPH_HEIGHT * scale
+}
+
+/// Fits a member's name to the chart. Accents are dropped, since the font
+/// only covers ASCII.
+fn short_name(first_name: &Option<String>) -> String {
+    let name = first_name
+        .as_deref()
+        .unwrap_or("(left)")
+        .nfd()
+        .filter(|c| !is_combining_mark(*c))
+        .collect::<String>();
+    if name.chars().count() > MAX_NAME_CHARS {
+        let mut name = name.chars().take(MAX_NAME_CHARS - 1).collect::<String>();
+        name.push('.');
+        name
+    } else {
+        name
+    }
+}
+
+/// Draws `stats`, most Ls first, as a PNG bar chart with a bar per member.
+/// Members past [`MAX_BARS`] are only counted.
+pub fn scoreboard(stats: &[MemberStat]) -> Vec<u8> {
+    let shown = &stats[..stats.len().min(MAX_BARS)];
+    let names = shown
+        .iter()
+        .map(|stat| short_name(&stat.first_name))
+        .collect::<Vec<_>>();
+    let max = shown
+        .iter()
+        .map(|stat| stat.amount)
+        .max()
+        .unwrap_or(0)
+        .max(1);
+    let rest = stats.len() - shown.len();
+
+    let name_width = names
+        .iter()
+        .map(|name| Canvas::text_width(name, LABEL_SCALE))
+        .max()
+        .unwrap_or(0);
+    let value_width = Canvas::text_width(&max.to_string(), LABEL_SCALE);
+    let title_height = label_height(TITLE_SCALE) + 2 * GAP;
+    let footer_height = if rest > 0 { ROW_HEIGHT } else { 0 };
+    let width = 2 * PADDING + name_width + GAP + BAR_AREA_WIDTH + GAP + value_width;
+    let height = 2 * PADDING + title_height + shown.len() as u32 * ROW_HEIGHT + footer_height;
+
+    let mut canvas = Canvas::new(width, height, BACKGROUND);
+    canvas.text(
+        PADDING as i64,
+        PADDING as i64,
+        "L scoreboard",
+        TITLE_SCALE,
+        INK,
+    );
+
+    let bars_left = (PADDING + name_width + GAP) as i64;
+    let label_offset = (ROW_HEIGHT - label_height(LABEL_SCALE)) as i64 / 2;
+    for (i, (stat, name)) in shown.iter().zip(&names).enumerate() {
+        let top = (PADDING + title_height + i as u32 * ROW_HEIGHT) as i64;
+        // Names are aligned to the bars
+        let name_left = bars_left - (GAP + Canvas::text_width(name, LABEL_SCALE)) as i64;
+        canvas.text(name_left, top + label_offset, name, LABEL_SCALE, INK);
+
+        let bar_width = (BAR_AREA_WIDTH as i64 * stat.amount.max(0) as i64 / max as i64) as u32;
+        canvas.fill_rect(
+            bars_left,
+            top + (ROW_HEIGHT - BAR_HEIGHT) as i64 / 2,
+            bar_width.max(1),
+            BAR_HEIGHT,
+            ACCENT,
+        );
+        canvas.text(
+            bars_left + (bar_width + GAP) as i64,
+            top + label_offset,
+            &stat.amount.to_string(),
+            LABEL_SCALE,
+            INK,
+        );
+    }
+    if rest > 0 {
+        let top = (PADDING + title_height + shown.len() as u32 * ROW_HEIGHT) as i64;
+        canvas.text(
+            bars_left,
+            top + label_offset,
+            &format!("and {} more", rest),
+            LABEL_SCALE,
+            INK,
+        );
+    }
+
+    canvas.to_png()
+}
+
+/// How many Ls had been given in total by the end of each day, in
+/// `timezone`, from the day of the first of `times` up to `now`. `times` are
+/// in Unix seconds and in order.
+pub fn running_totals(times: &[i64], timezone: Tz, now: i64) -> Vec<(NaiveDate, i64)> {
+    let local_date = |time: i64| {
+        Utc.timestamp_opt(time, 0)
+            .single()
+            .map(|time| time.with_timezone(&timezone).date_naive())
+    };
+    let (Some(mut day), Some(today)) = (
+        times.first().and_then(|time| local_date(*time)),
+        local_date(now),
+    ) else {
+        return Vec::new();
+    };
+
+    let mut days = times.iter().filter_map(|time| local_date(*time)).peekable();
+    let mut totals = Vec::new();
+    let mut total = 0;
+    while day <= today {
+        while days.next_if(|given| *given <= day).is_some() {
+            total += 1;
+        }
+        totals.push((day, total));
+        let Some(next) = day.succ_opt() else {
+            break;
+        };
+        day = next;
+    }
+
+    totals
+}
+
+/// Draws `totals` as a PNG line chart of the Ls given over time.
+pub fn over_time(totals: &[(NaiveDate, i64)]) -> Vec<u8> {
+    let mut canvas = Canvas::new(LINE_CHART_WIDTH, LINE_CHART_HEIGHT, BACKGROUND);
+    canvas.text(
+        PADDING as i64,
+        PADDING as i64,
+        "Ls over time",
+        TITLE_SCALE,
+        INK,
+    );
+
+    let max = totals
+        .iter()
+        .map(|(_, total)| *total)
+        .max()
+        .unwrap_or(0)
+        .max(1);
+    let axis_width = Canvas::text_width(&max.to_string(), LABEL_SCALE) + GAP;
+    let left = (PADDING + axis_width) as i64;
+    let right = (canvas.width() - PADDING) as i64;
+    let top = (PADDING + label_height(TITLE_SCALE) + 2 * GAP) as i64;
+    let bottom = (canvas.height() - PADDING - label_height(LABEL_SCALE) - GAP) as i64;
+
+    // Grid lines at zero, half way and the highest total
+    for value in [0, max / 2, max] {
+        let y = bottom - (bottom - top) * value / max;
+        canvas.line((left, y), (right, y), 1, FAINT);
+        let label = value.to_string();
+        canvas.text(
+            left - (GAP + Canvas::text_width(&label, LABEL_SCALE)) as i64,
+            y - label_height(LABEL_SCALE) as i64 / 2,
+            &label,
+            LABEL_SCALE,
+            INK,
+        );
+    }
+
+    let points = totals
+        .iter()
+        .enumerate()
+        .map(|(i, (_, total))| {
+            let x = match totals.len() {
+                1 => (left + right) / 2,
+                days => left + (right - left) * i as i64 / (days as i64 - 1),
+            };
+            (x, bottom - (bottom - top) * total / max)
+        })
+        .collect::<Vec<_>>();
+    for pair in points.windows(2) {
+        canvas.line(pair[0], pair[1], 3, ACCENT);
+    }
+    if let [point] = points[..] {
+        canvas.fill_rect(point.0 - 3, point.1 - 3, 7, 7, ACCENT);
+    }
+
+    // The first and last day mark the ends of the time axis
+    let label_top = bottom + GAP as i64;
+    if let Some((first, _)) = totals.first() {
+        canvas.text(
+            left,
+            label_top,
+            &first.format("%Y-%m-%d").to_string(),
+            LABEL_SCALE,
+            INK,
+        );
+    }
+    if let Some((last, _)) = totals.last().filter(|_| totals.len() > 1) {
+        let label = last.format("%Y-%m-%d").to_string();
+        canvas.text(
+            right - Canvas::text_width(&label, LABEL_SCALE) as i64,
+            label_top,
+            &label,
+            LABEL_SCALE,
+            INK,
+        );
+    }
+
+    canvas.to_png()
+}
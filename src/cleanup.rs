@@ -0,0 +1,109 @@
+//! Deletes the bot's chatter, and the commands that caused it, once the
+//! delays set for the chat have passed. Scheduled deletions are stored, so
+//! they survive restarts.
+
+use miette::{miette, Result};
+use std::time::Duration;
+use teloxide::{prelude::*, types::MessageId, RequestError};
+use tracing::{debug, info, warn};
+
+use crate::{
+    common::time::unix_time,
+    db::{pool::db, repo::DeletionRepo},
+    settings::ChatSettings,
+};
+
+/// How often messages due for deletion are looked for
+pub const SWEEP_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Most messages deleted in one sweep, so a backlog is worked off gradually
+const SWEEP_BATCH: i64 = 100;
+
+/// A kind of message that can be deleted after a delay set per chat.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Chatter {
+    /// The bot's answer to a command, such as "L has been awarded"
+    Confirmation,
+    /// A command sent to the bot
+    Command,
+    /// The bot's answer with a learned phrase
+    Reply,
+}
+
+impl Chatter {
+    fn delay(self, settings: &ChatSettings) -> Duration {
+        match self {
+            Self::Confirmation => settings.delete_confirmations,
+            Self::Command => settings.delete_commands,
+            Self::Reply => settings.delete_replies,
+        }
+    }
+}
+
+/// Schedules `message_id` for deletion if `settings` set a delay for this
+/// kind of message.
+pub async fn schedule(
+    settings: &ChatSettings,
+    chat_id: ChatId,
+    message_id: MessageId,
+    chatter: Chatter,
+) -> Result<()> {
+    let delay = chatter.delay(settings);
+    if delay.is_zero() {
+        return Ok(());
+    }
+
+    let delete_at = i64::try_from(delay.as_secs())
+        .ok()
+        .and_then(|secs| unix_time().checked_add(secs))
+        .ok_or_else(|| miette!("Deletion delay {:?} is out of range", delay))?;
+    DeletionRepo::new(db())
+        .schedule(chat_id, message_id, delete_at)
+        .await
+}
+
+/// Deletes the messages due for deletion at `now`, in Unix seconds. Returns
+/// how many were deleted.
+///
+/// Messages Telegram refuses to delete, e.g. because they are gone already,
+/// are given up on. Those that failed for other reasons, such as a network
+/// error, are tried again on the next sweep.
+pub async fn sweep(bot: &Bot, now: i64) -> Result<usize> {
+    let deletions = DeletionRepo::new(db());
+    let mut deleted = 0;
+    for due in deletions.due(now, SWEEP_BATCH).await? {
+        let chat_id = ChatId(due.chat_id);
+        let message_id = MessageId(due.message_id);
+
+        match bot.delete_message(chat_id, message_id).await {
+            Ok(_) => deleted += 1,
+            Err(RequestError::Api(err)) => {
+                debug!(
+                    "Failed to delete message {} in {}: {:?}",
+                    message_id.0, chat_id, err
+                );
+            }
+            Err(err) => {
+                warn!(
+                    "Failed to delete message {} in {}: {:?}",
+                    message_id.0, chat_id, err
+                );
+                continue;
+            }
+        }
+        deletions.remove(chat_id, message_id).await?;
+    }
+
+    Ok(deleted)
+}
+
+/// Deletes the messages that are due, run every [`SWEEP_INTERVAL`] by the
+/// scheduler.
+pub async fn run_sweep(bot: Bot) -> Result<()> {
+    let deleted = sweep(&bot, unix_time()).await?;
+    if deleted > 0 {
+        info!("Deleted {} messages", deleted);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,155 @@
+use clap::{Parser, Subcommand};
+use miette::{bail, IntoDiagnostic, Result, WrapErr};
+use std::{fs, io, path::PathBuf};
+use teloxide::types::{ChatId, UserId};
+
+use crate::{
+    bot::{new_bot, run_bot, set_commands},
+    config::Config,
+    db::{
+        backup, dump, pool,
+        repo::{AwardRepo, MemberRepo},
+        sqlite,
+    },
+};
+
+#[derive(Parser)]
+#[command(version, about)]
+pub struct Cli {
+    #[command(subcommand)]
+    command: Option<Action>,
+}
+
+#[derive(Subcommand)]
+enum Action {
+    /// Start the bot (default)
+    Run,
+    /// Apply pending database migrations
+    Migrate,
+    /// Inspect the database
+    #[command(subcommand)]
+    Db(DbAction),
+    /// Write all stored data as JSON
+    Export {
+        /// File to write to, defaults to stdout
+        path: Option<PathBuf>,
+    },
+    /// Load data written by `export`, replacing rows that already exist
+    Import {
+        /// File to read from
+        path: PathBuf,
+    },
+    /// Replace the SQLite database with a backup, after checking its integrity.
+    /// Stop the bot first.
+    Restore {
+        /// Backup file to restore
+        path: PathBuf,
+    },
+    /// Make a Telegram user a bot owner, allowed to run every command
+    GrantAdmin {
+        /// Telegram user ID
+        user_id: u64,
+    },
+    /// Remove every score recorded in a chat
+    ResetScores {
+        /// Telegram chat ID
+        #[arg(long, allow_negative_numbers = true)]
+        chat: i64,
+    },
+    /// Register the command list with Telegram
+    SetCommands,
+}
+
+#[derive(Subcommand)]
+enum DbAction {
+    /// Print the location of the SQLite database file
+    ShellPath,
+    /// Move the scores recorded before scoreboards were kept per chat into a
+    /// chat, adding them to any it already has
+    AssignLegacy {
+        /// Telegram chat ID
+        #[arg(long, allow_negative_numbers = true)]
+        chat: i64,
+    },
+}
+
+impl Cli {
+    pub async fn run(self) -> Result<()> {
+        let action = self.command.unwrap_or(Action::Run);
+
+        match action {
+            Action::Db(DbAction::ShellPath) => {
+                println!("{}", sqlite::path().display());
+                return Ok(());
+            }
+            Action::SetCommands => {
+                set_commands(&new_bot()?).await?;
+                println!("Commands registered");
+                return Ok(());
+            }
+            Action::Restore { path } => {
+                let Some(target) = sqlite::file_path(&pool::url()) else {
+                    bail!("Only SQLite database files can be restored");
+                };
+                let previous = backup::restore(&path, &target).await?;
+                println!("Restored {} to {}", path.display(), target.display());
+                if let Some(previous) = previous {
+                    println!("The replaced database was kept at {}", previous.display());
+                }
+                return Ok(());
+            }
+            _ => {}
+        }
+
+        pool::init()
+            .await
+            .wrap_err("Failed to connect to the database")?;
+        let applied = pool::migrate()
+            .await
+            .wrap_err("Failed to migrate the database")?;
+
+        match action {
+            Action::Run => run_bot(&Config::from_env()?).await?,
+            Action::Migrate => println!("Applied {} migration(s)", applied),
+            Action::Export { path } => {
+                let dump = dump::export().await?;
+                match path {
+                    Some(path) => {
+                        let file = fs::File::create(&path).into_diagnostic()?;
+                        serde_json::to_writer_pretty(file, &dump).into_diagnostic()?;
+                    }
+                    None => serde_json::to_writer_pretty(io::stdout(), &dump).into_diagnostic()?,
+                }
+            }
+            Action::Import { path } => {
+                let file = fs::File::open(&path)
+                    .into_diagnostic()
+                    .wrap_err_with(|| format!("Failed to open {}", path.display()))?;
+                let dump = serde_json::from_reader(file).into_diagnostic()?;
+                dump::import(&dump).await?;
+                println!("Imported {}", path.display());
+            }
+            Action::GrantAdmin { user_id } => {
+                MemberRepo::new(pool::db())
+                    .grant_admin(UserId(user_id))
+                    .await?;
+                println!("{} is now an admin", user_id);
+            }
+            Action::Db(DbAction::AssignLegacy { chat }) => {
+                let moved = AwardRepo::new(pool::db())
+                    .assign_legacy(ChatId(chat))
+                    .await?;
+                println!("Moved {} score(s) into chat {}", moved, chat);
+            }
+            Action::ResetScores { chat } => {
+                let reset = AwardRepo::new(pool::db()).reset(ChatId(chat)).await?;
+                println!("Reset {} score(s) in chat {}", reset, chat);
+            }
+            Action::Db(DbAction::ShellPath) | Action::SetCommands | Action::Restore { .. } => {
+                unreachable!()
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -4,7 +4,7 @@
 ///
 /// ### Usage
 ///
-/// ```
+/// ```ignore
 /// respond!("deez nuts")
 /// ```
 macro_rules! respond {
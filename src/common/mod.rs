@@ -1,3 +1,4 @@
 pub mod bot;
 pub mod constants;
 pub mod text;
+pub mod time;
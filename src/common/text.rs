@@ -2,8 +2,17 @@ use natural::tokenize::tokenize;
 use unicode_normalization::UnicodeNormalization;
 
 pub fn normalize(text: &str) -> String {
-    let toks = tokenize(&text);
+    let toks = tokenize(text);
     let mut text = toks.join(" ");
     text = text.nfc().collect::<String>();
     text.to_lowercase()
 }
+
+/// `count` Ls, as in "1 L" or "3 Ls".
+pub fn ls(count: i64) -> String {
+    if count == 1 {
+        String::from("1 L")
+    } else {
+        format!("{} Ls", count)
+    }
+}
@@ -0,0 +1,12 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Seconds in a day
+pub const DAY_SECS: i64 = 24 * 60 * 60;
+
+/// Seconds since the Unix epoch, as stored in the database.
+pub fn unix_time() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs() as i64
+}
@@ -0,0 +1,151 @@
+use miette::{bail, miette, IntoDiagnostic, Result, WrapErr};
+use std::{env, net::SocketAddr, path::PathBuf, time::Duration};
+use teloxide::types::UserId;
+use url::Url;
+
+const DEFAULT_WEBHOOK_ADDRESS: &str = "0.0.0.0:8080";
+const DEFAULT_BACKUP_INTERVAL_HOURS: u64 = 24;
+const DEFAULT_BACKUP_KEEP: usize = 7;
+const DEFAULT_BACKUP_MAX_AGE_DAYS: u64 = 30;
+
+/// Runtime configuration, read from the environment.
+#[derive(Clone, Debug)]
+pub struct Config {
+    pub update_mode: UpdateMode,
+    /// Address to serve `/healthz` and `/metrics` on, disabled when unset
+    pub metrics_address: Option<SocketAddr>,
+    /// Scheduled SQLite backups, disabled when unset
+    pub backup: Option<BackupConfig>,
+    /// Users allowed to run every command in every chat
+    pub owners: Vec<UserId>,
+}
+
+/// How updates are received from Telegram.
+#[derive(Clone, Debug)]
+pub enum UpdateMode {
+    /// Long polling through `getUpdates`
+    Polling,
+    /// Telegram pushes updates to a local HTTP listener
+    Webhook(WebhookConfig),
+}
+
+#[derive(Clone, Debug)]
+pub struct WebhookConfig {
+    /// Public URL Telegram sends updates to. Its path is also the path the
+    /// local listener accepts updates on.
+    pub url: Url,
+    /// Address the local listener binds to
+    pub address: SocketAddr,
+    /// Expected value of the `X-Telegram-Bot-Api-Secret-Token` header. A
+    /// random token is generated on startup when this is not set.
+    pub secret_token: Option<String>,
+}
+
+#[derive(Clone, Debug)]
+pub struct BackupConfig {
+    /// Directory backups are written to
+    pub dir: PathBuf,
+    /// Time between backups
+    pub interval: Duration,
+    /// Number of backups kept
+    pub keep: usize,
+    /// Backups older than this are removed, apart from the newest one
+    pub max_age: Duration,
+}
+
+impl Config {
+    pub fn from_env() -> Result<Self> {
+        let update_mode = match var("UPDATE_MODE").as_deref() {
+            None | Some("polling") => UpdateMode::Polling,
+            Some("webhook") => UpdateMode::Webhook(WebhookConfig::from_env()?),
+            Some(mode) => bail!(
+                "Unknown UPDATE_MODE `{}`, expected `polling` or `webhook`",
+                mode
+            ),
+        };
+
+        let metrics_address = var("METRICS_ADDRESS")
+            .map(|address| address.parse())
+            .transpose()
+            .into_diagnostic()
+            .wrap_err("METRICS_ADDRESS is not a valid socket address")?;
+
+        let backup = var("BACKUP_DIR")
+            .map(|dir| BackupConfig::from_env(dir.into()))
+            .transpose()?;
+
+        let owners = var("BOT_OWNERS")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|id| !id.is_empty())
+            .map(|id| {
+                id.parse().map(UserId).map_err(|_| {
+                    miette!("BOT_OWNERS must be a comma separated list of Telegram user IDs")
+                })
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Self {
+            update_mode,
+            metrics_address,
+            backup,
+            owners,
+        })
+    }
+}
+
+impl BackupConfig {
+    fn from_env(dir: PathBuf) -> Result<Self> {
+        let hours = number("BACKUP_INTERVAL_HOURS", DEFAULT_BACKUP_INTERVAL_HOURS)?;
+        if hours == 0 {
+            bail!("BACKUP_INTERVAL_HOURS must be at least 1");
+        }
+
+        Ok(Self {
+            dir,
+            interval: Duration::from_secs(hours * 60 * 60),
+            keep: number("BACKUP_KEEP", DEFAULT_BACKUP_KEEP)?,
+            max_age: Duration::from_secs(
+                number("BACKUP_MAX_AGE_DAYS", DEFAULT_BACKUP_MAX_AGE_DAYS)? * 24 * 60 * 60,
+            ),
+        })
+    }
+}
+
+impl WebhookConfig {
+    fn from_env() -> Result<Self> {
+        let Some(url) = var("WEBHOOK_URL") else {
+            bail!("WEBHOOK_URL must be set when UPDATE_MODE is `webhook`");
+        };
+
+        Ok(Self {
+            url: url
+                .parse()
+                .into_diagnostic()
+                .wrap_err("WEBHOOK_URL is not a valid URL")?,
+            address: var("WEBHOOK_ADDRESS")
+                .as_deref()
+                .unwrap_or(DEFAULT_WEBHOOK_ADDRESS)
+                .parse()
+                .into_diagnostic()
+                .wrap_err("WEBHOOK_ADDRESS is not a valid socket address")?,
+            secret_token: var("WEBHOOK_SECRET_TOKEN"),
+        })
+    }
+}
+
+/// Reads an environment variable, treating empty values as unset.
+fn var(key: &str) -> Option<String> {
+    env::var(key).ok().filter(|value| !value.is_empty())
+}
+
+/// Reads a numeric environment variable, falling back to `default` when unset.
+fn number<T: std::str::FromStr>(key: &str, default: T) -> Result<T> {
+    match var(key) {
+        Some(value) => value
+            .parse()
+            .map_err(|_| miette!("{} must be a whole number, got `{}`", key, value)),
+        None => Ok(default),
+    }
+}
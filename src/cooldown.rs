@@ -0,0 +1,30 @@
+//! Time members have to wait between giving Ls, as set per chat.
+
+use miette::Result;
+use std::time::Duration;
+use teloxide::types::{ChatId, UserId};
+
+use crate::{
+    common::time::unix_time,
+    db::{pool::db, repo::AwardRepo},
+};
+
+/// Time `user_id` still has to wait before giving another L in `chat_id`, if
+/// any. Counted from the last award they gave that still stands.
+pub async fn remaining(
+    chat_id: ChatId,
+    user_id: UserId,
+    cooldown: Duration,
+) -> Result<Option<Duration>> {
+    if cooldown.is_zero() {
+        return Ok(None);
+    }
+    let Some(last_given_at) = AwardRepo::new(db()).last_given_at(chat_id, user_id).await? else {
+        return Ok(None);
+    };
+
+    let elapsed = Duration::from_secs(unix_time().saturating_sub(last_given_at).max(0) as u64);
+    Ok(cooldown
+        .checked_sub(elapsed)
+        .filter(|remaining| !remaining.is_zero()))
+}
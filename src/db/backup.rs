@@ -0,0 +1,200 @@
+use miette::{bail, IntoDiagnostic, Result, WrapErr};
+use sqlx::any::AnyKind;
+use std::{
+    cmp::Reverse,
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use tracing::{info, warn};
+
+use super::{
+    pool::{self, db, Pool},
+    sqlite,
+};
+use crate::{common::time::unix_time, config::BackupConfig};
+
+const FILE_PREFIX: &str = "db-";
+const FILE_SUFFIX: &str = ".sqlite3";
+
+/// Whether backups can be taken of the database behind `pool`. Only SQLite
+/// databases are backed up here; Postgres has `pg_dump` for that.
+pub fn is_supported(pool: &Pool) -> bool {
+    matches!(pool.any_kind(), AnyKind::Sqlite)
+}
+
+/// Writes a consistent copy of the database to `path` without taking it
+/// offline.
+pub async fn snapshot(pool: &Pool, path: &Path) -> Result<()> {
+    if !is_supported(pool) {
+        bail!("Backups are only supported for SQLite databases");
+    }
+
+    sqlx::query("VACUUM INTO $1")
+        .bind(path.to_string_lossy().into_owned())
+        .execute(pool)
+        .await
+        .into_diagnostic()
+        .wrap_err_with(|| format!("Failed to back up the database to {}", path.display()))?;
+
+    Ok(())
+}
+
+/// Takes a backup into `dir`, named after the time it was taken. Backups
+/// taken within the same second are numbered after the first.
+pub async fn backup(pool: &Pool, dir: &Path) -> Result<PathBuf> {
+    fs::create_dir_all(dir).into_diagnostic()?;
+
+    let taken_at = unix_time();
+    let mut path = dir.join(file_name(taken_at, 0));
+    for number in 1.. {
+        if !path.exists() {
+            break;
+        }
+        path = dir.join(file_name(taken_at, number));
+    }
+    snapshot(pool, &path).await?;
+
+    Ok(path)
+}
+
+/// The name of the backup taken at `taken_at`, in Unix seconds, with
+/// `number` backups taken before it within that second.
+fn file_name(taken_at: i64, number: u32) -> String {
+    match number {
+        0 => format!("{}{}{}", FILE_PREFIX, taken_at, FILE_SUFFIX),
+        number => format!("{}{}-{}{}", FILE_PREFIX, taken_at, number, FILE_SUFFIX),
+    }
+}
+
+/// When the backup called `name` was taken, and how many backups were taken
+/// before it within that second, if it is a backup.
+fn parse_file_name(name: &str) -> Option<(i64, u32)> {
+    let stem = name.strip_prefix(FILE_PREFIX)?.strip_suffix(FILE_SUFFIX)?;
+    match stem.split_once('-') {
+        Some((taken_at, number)) => Some((taken_at.parse().ok()?, number.parse().ok()?)),
+        None => Some((stem.parse().ok()?, 0)),
+    }
+}
+
+/// Removes backups in `dir` beyond the newest `keep`, and those older than
+/// `max_age`. The newest backup is always kept. Returns how many were removed.
+pub fn rotate(dir: &Path, keep: usize, max_age: Duration) -> Result<usize> {
+    let mut backups = Vec::new();
+    for entry in fs::read_dir(dir).into_diagnostic()? {
+        let path = entry.into_diagnostic()?.path();
+        let taken_at = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(parse_file_name);
+        if let Some(taken_at) = taken_at {
+            backups.push((taken_at, path));
+        }
+    }
+    backups.sort_by_key(|(taken_at, _)| Reverse(*taken_at));
+
+    let now = unix_time();
+    let mut removed = 0;
+    for (i, ((taken_at, _), path)) in backups.iter().enumerate() {
+        let expired = now.saturating_sub(*taken_at) > max_age.as_secs() as i64;
+        if i > 0 && (i >= keep || expired) {
+            fs::remove_file(path).into_diagnostic()?;
+            removed += 1;
+        }
+    }
+
+    Ok(removed)
+}
+
+/// Takes a backup of the global database and rotates the backups, run every
+/// `config.interval` by the scheduler.
+pub async fn run_scheduled(config: &BackupConfig) -> Result<()> {
+    let path = backup(db(), &config.dir).await?;
+    info!("Backed up the database to {}", path.display());
+
+    if let Err(err) = rotate(&config.dir, config.keep, config.max_age) {
+        warn!("Failed to rotate backups: {:?}", err);
+    }
+
+    Ok(())
+}
+
+/// Replaces the database file at `target` with `backup`, once `backup` has
+/// passed an integrity check. The bot must not be running. The replaced file
+/// is kept next to `target`, and its path is returned. Fails rather than
+/// replace a file kept by an earlier restore.
+pub async fn restore(backup: &Path, target: &Path) -> Result<Option<PathBuf>> {
+    verify(backup)
+        .await
+        .wrap_err_with(|| format!("{} can't be restored", backup.display()))?;
+
+    let previous = with_suffix(target, ".before-restore");
+    if target.exists() && previous.exists() {
+        bail!(
+            "{} is left from an earlier restore, move it away first",
+            previous.display()
+        );
+    }
+
+    let staged = with_suffix(target, ".restoring");
+    fs::copy(backup, &staged).into_diagnostic()?;
+
+    let previous = if target.exists() {
+        // Fold the write-ahead log into the file, so nothing is left behind
+        // that would be replayed onto the restored database
+        let pool = pool::connect(&format!("sqlite://{}", target.display())).await?;
+        sqlx::query("PRAGMA wal_checkpoint(TRUNCATE)")
+            .execute(&pool)
+            .await
+            .into_diagnostic()?;
+        pool.close().await;
+
+        fs::rename(target, &previous).into_diagnostic()?;
+        for suffix in ["-wal", "-shm"] {
+            fs::remove_file(with_suffix(target, suffix)).ok();
+        }
+        Some(previous)
+    } else {
+        None
+    };
+
+    fs::rename(&staged, target).into_diagnostic()?;
+
+    Ok(previous)
+}
+
+/// Checks that `path` is an intact SQLite database with a schema this build
+/// knows how to migrate.
+async fn verify(path: &Path) -> Result<()> {
+    if !path.is_file() {
+        bail!("{} is not a file", path.display());
+    }
+
+    let pool = pool::connect(&format!("sqlite://{}?mode=ro", path.display())).await?;
+    let problems = sqlx::query_scalar::<_, String>("PRAGMA integrity_check")
+        .fetch_all(&pool)
+        .await
+        .into_diagnostic()
+        .wrap_err("Integrity check failed")?;
+    if problems != ["ok"] {
+        bail!("Integrity check failed: {}", problems.join("; "));
+    }
+
+    let version = sqlite::version(&pool).await?;
+    pool.close().await;
+    if version as usize > sqlite::MIGRATIONS.len() {
+        bail!(
+            "Schema version {} is newer than this build supports ({})",
+            version,
+            sqlite::MIGRATIONS.len()
+        );
+    }
+
+    Ok(())
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(suffix);
+    path.into()
+}
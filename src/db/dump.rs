@@ -0,0 +1,256 @@
+use miette::{IntoDiagnostic, Result};
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "postgres")]
+use sqlx::any::AnyKind;
+
+#[cfg(feature = "postgres")]
+use super::postgres;
+use super::{models::*, pool::db};
+
+/// A portable snapshot of everything stored in the database, apart from
+/// votes still in progress on pending and disputed Ls, and messages waiting
+/// to be deleted.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Dump {
+    pub members: Vec<Member>,
+    pub stats: Vec<Stat>,
+    pub phrases: Vec<Phrase>,
+    pub responses: Vec<Response>,
+    pub admins: Vec<Admin>,
+    #[serde(default)]
+    pub chat_roles: Vec<ChatRole>,
+    #[serde(default)]
+    pub chat_settings: Vec<ChatSetting>,
+    #[serde(default)]
+    pub awards: Vec<Award>,
+    #[serde(default)]
+    pub badges: Vec<EarnedBadge>,
+    #[serde(default)]
+    pub award_types: Vec<AwardType>,
+}
+
+pub async fn export() -> Result<Dump> {
+    Ok(Dump {
+        members: sqlx::query_as("SELECT id, tg_user_id, first_name, username FROM Member")
+            .fetch_all(db())
+            .await
+            .into_diagnostic()?,
+        stats: sqlx::query_as("SELECT chat_id, member_id, award_type, amount FROM Stat")
+            .fetch_all(db())
+            .await
+            .into_diagnostic()?,
+        phrases: sqlx::query_as("SELECT id, author_id, content FROM Phrase")
+            .fetch_all(db())
+            .await
+            .into_diagnostic()?,
+        responses: sqlx::query_as("SELECT id, phrase_id, content, learned_at FROM Response")
+            .fetch_all(db())
+            .await
+            .into_diagnostic()?,
+        admins: sqlx::query_as("SELECT member_id FROM Admin")
+            .fetch_all(db())
+            .await
+            .into_diagnostic()?,
+        chat_roles: sqlx::query_as("SELECT chat_id, member_id, role FROM ChatRole")
+            .fetch_all(db())
+            .await
+            .into_diagnostic()?,
+        chat_settings: sqlx::query_as("SELECT chat_id, name, value FROM ChatSettings")
+            .fetch_all(db())
+            .await
+            .into_diagnostic()?,
+        // Awards come before the awards added to them
+        awards: sqlx::query_as(
+            r#"
+            SELECT id, chat_id, giver_id, receiver_id, award_type, parent_id, created_at, reason
+            FROM Award
+            ORDER BY id
+            "#,
+        )
+        .fetch_all(db())
+        .await
+        .into_diagnostic()?,
+        badges: sqlx::query_as("SELECT chat_id, member_id, badge, granted_at FROM Badge")
+            .fetch_all(db())
+            .await
+            .into_diagnostic()?,
+        award_types: sqlx::query_as("SELECT chat_id, code, name, emoji, weight FROM AwardType")
+            .fetch_all(db())
+            .await
+            .into_diagnostic()?,
+    })
+}
+
+/// Writes a dump into the database. Rows that already exist are replaced,
+/// everything else is left untouched.
+pub async fn import(dump: &Dump) -> Result<()> {
+    let mut tx = db().begin().await.into_diagnostic()?;
+
+    for member in &dump.members {
+        sqlx::query(
+            r#"
+            INSERT INTO Member (id, tg_user_id, first_name, username) VALUES ($1, $2, $3, $4)
+            ON CONFLICT (id) DO UPDATE SET tg_user_id = excluded.tg_user_id,
+                                           first_name = excluded.first_name,
+                                           username = excluded.username
+            "#,
+        )
+        .bind(member.id)
+        .bind(&member.tg_user_id)
+        .bind(&member.first_name)
+        .bind(&member.username)
+        .execute(&mut tx)
+        .await
+        .into_diagnostic()?;
+    }
+    for stat in &dump.stats {
+        sqlx::query(
+            r#"
+            INSERT INTO Stat (chat_id, member_id, award_type, amount) VALUES ($1, $2, $3, $4)
+            ON CONFLICT (chat_id, member_id, award_type) DO UPDATE SET amount = excluded.amount
+            "#,
+        )
+        .bind(stat.chat_id)
+        .bind(stat.member_id)
+        .bind(&stat.award_type)
+        .bind(stat.amount)
+        .execute(&mut tx)
+        .await
+        .into_diagnostic()?;
+    }
+    for phrase in &dump.phrases {
+        sqlx::query(
+            r#"
+            INSERT INTO Phrase (id, author_id, content) VALUES ($1, $2, $3)
+            ON CONFLICT (id) DO UPDATE SET author_id = excluded.author_id, content = excluded.content
+            "#,
+        )
+        .bind(phrase.id)
+        .bind(phrase.author_id)
+        .bind(&phrase.content)
+        .execute(&mut tx)
+        .await
+        .into_diagnostic()?;
+    }
+    for response in &dump.responses {
+        sqlx::query(
+            r#"
+            INSERT INTO Response (id, phrase_id, content, learned_at) VALUES ($1, $2, $3, $4)
+            ON CONFLICT (id) DO UPDATE SET phrase_id = excluded.phrase_id,
+                                           content = excluded.content,
+                                           learned_at = excluded.learned_at
+            "#,
+        )
+        .bind(response.id)
+        .bind(response.phrase_id)
+        .bind(&response.content)
+        .bind(response.learned_at)
+        .execute(&mut tx)
+        .await
+        .into_diagnostic()?;
+    }
+    for admin in &dump.admins {
+        sqlx::query("INSERT INTO Admin (member_id) VALUES ($1) ON CONFLICT DO NOTHING")
+            .bind(admin.member_id)
+            .execute(&mut tx)
+            .await
+            .into_diagnostic()?;
+    }
+    for chat_role in &dump.chat_roles {
+        sqlx::query(
+            r#"
+            INSERT INTO ChatRole (chat_id, member_id, role) VALUES ($1, $2, $3)
+            ON CONFLICT (chat_id, member_id) DO UPDATE SET role = excluded.role
+            "#,
+        )
+        .bind(chat_role.chat_id)
+        .bind(chat_role.member_id)
+        .bind(&chat_role.role)
+        .execute(&mut tx)
+        .await
+        .into_diagnostic()?;
+    }
+    for setting in &dump.chat_settings {
+        sqlx::query(
+            r#"
+            INSERT INTO ChatSettings (chat_id, name, value) VALUES ($1, $2, $3)
+            ON CONFLICT (chat_id, name) DO UPDATE SET value = excluded.value
+            "#,
+        )
+        .bind(setting.chat_id)
+        .bind(&setting.name)
+        .bind(&setting.value)
+        .execute(&mut tx)
+        .await
+        .into_diagnostic()?;
+    }
+    for award in &dump.awards {
+        sqlx::query(
+            r#"
+            INSERT INTO Award
+              (id, chat_id, giver_id, receiver_id, parent_id, created_at, reason, award_type)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            ON CONFLICT (id) DO UPDATE SET chat_id = excluded.chat_id,
+                                           giver_id = excluded.giver_id,
+                                           receiver_id = excluded.receiver_id,
+                                           award_type = excluded.award_type,
+                                           parent_id = excluded.parent_id,
+                                           created_at = excluded.created_at,
+                                           reason = excluded.reason
+            "#,
+        )
+        .bind(award.id)
+        .bind(award.chat_id)
+        .bind(award.giver_id)
+        .bind(award.receiver_id)
+        .bind(award.parent_id)
+        .bind(award.created_at)
+        .bind(&award.reason)
+        .bind(&award.award_type)
+        .execute(&mut tx)
+        .await
+        .into_diagnostic()?;
+    }
+    for badge in &dump.badges {
+        sqlx::query(
+            r#"
+            INSERT INTO Badge (chat_id, member_id, badge, granted_at) VALUES ($1, $2, $3, $4)
+            ON CONFLICT (chat_id, member_id, badge) DO UPDATE SET granted_at = excluded.granted_at
+            "#,
+        )
+        .bind(badge.chat_id)
+        .bind(badge.member_id)
+        .bind(&badge.badge)
+        .bind(badge.granted_at)
+        .execute(&mut tx)
+        .await
+        .into_diagnostic()?;
+    }
+    for award_type in &dump.award_types {
+        sqlx::query(
+            r#"
+            INSERT INTO AwardType (chat_id, code, name, emoji, weight) VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (chat_id, code) DO UPDATE SET name = excluded.name,
+                                                      emoji = excluded.emoji,
+                                                      weight = excluded.weight
+            "#,
+        )
+        .bind(award_type.chat_id)
+        .bind(&award_type.code)
+        .bind(&award_type.name)
+        .bind(&award_type.emoji)
+        .bind(award_type.weight)
+        .execute(&mut tx)
+        .await
+        .into_diagnostic()?;
+    }
+
+    #[cfg(feature = "postgres")]
+    if let AnyKind::Postgres = db().any_kind() {
+        postgres::reset_sequences(&mut tx).await?;
+    }
+
+    tx.commit().await.into_diagnostic()?;
+
+    Ok(())
+}
@@ -0,0 +1,29 @@
+CREATE TABLE Member (
+  id BIGINT PRIMARY KEY,
+  tg_user_id TEXT,
+  first_name TEXT
+);
+
+CREATE TABLE Stat (
+  chat_id BIGINT NOT NULL,
+  member_id BIGINT NOT NULL REFERENCES Member(id),
+  ls INTEGER NOT NULL,
+
+  PRIMARY KEY(chat_id, member_id)
+);
+
+CREATE TABLE Phrase (
+  id BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+  author_id BIGINT NOT NULL REFERENCES Member(id),
+  content TEXT NOT NULL UNIQUE
+);
+
+CREATE TABLE Response (
+  id BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+  phrase_id BIGINT NOT NULL REFERENCES Phrase(id),
+  content TEXT NOT NULL
+);
+
+CREATE TABLE Admin (
+  member_id BIGINT PRIMARY KEY REFERENCES Member(id)
+);
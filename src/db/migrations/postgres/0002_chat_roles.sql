@@ -0,0 +1,9 @@
+-- Roles granted or taken away in a single chat, taking precedence over the
+-- member's Telegram admin status there
+CREATE TABLE ChatRole (
+  chat_id BIGINT NOT NULL,
+  member_id BIGINT NOT NULL REFERENCES Member(id),
+  role TEXT NOT NULL,
+
+  PRIMARY KEY(chat_id, member_id)
+);
@@ -0,0 +1,8 @@
+-- Settings changed from their defaults, one row per chat and setting
+CREATE TABLE ChatSettings (
+  chat_id BIGINT NOT NULL,
+  name TEXT NOT NULL,
+  value TEXT NOT NULL,
+
+  PRIMARY KEY(chat_id, name)
+);
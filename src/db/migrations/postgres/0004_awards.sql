@@ -0,0 +1,29 @@
+-- Every L given, so it can be traced back to its giver and taken back
+CREATE TABLE Award (
+  id BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+  chat_id BIGINT NOT NULL,
+  giver_id BIGINT NOT NULL REFERENCES Member(id),
+  receiver_id BIGINT NOT NULL REFERENCES Member(id),
+  -- The award this one was added to with "+1 L"
+  parent_id BIGINT REFERENCES Award(id) ON DELETE CASCADE,
+  -- Unix time in seconds
+  created_at BIGINT NOT NULL
+);
+
+CREATE INDEX Award_chat_giver ON Award(chat_id, giver_id, created_at);
+CREATE INDEX Award_parent ON Award(parent_id);
+
+-- Awards put to a vote, and how the vote ended once it has
+CREATE TABLE Dispute (
+  award_id BIGINT PRIMARY KEY REFERENCES Award(id) ON DELETE CASCADE,
+  opened_by BIGINT NOT NULL REFERENCES Member(id),
+  outcome TEXT
+);
+
+CREATE TABLE DisputeVote (
+  award_id BIGINT NOT NULL REFERENCES Dispute(award_id) ON DELETE CASCADE,
+  voter_id BIGINT NOT NULL REFERENCES Member(id),
+  keep BOOLEAN NOT NULL,
+
+  PRIMARY KEY(award_id, voter_id)
+);
@@ -0,0 +1,24 @@
+-- Ls waiting for the chat's approval before they count, in chats with jury
+-- mode on. Confirmed ones move to Award, the rest are removed.
+CREATE TABLE PendingAward (
+  id BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+  chat_id BIGINT NOT NULL,
+  giver_id BIGINT NOT NULL REFERENCES Member(id),
+  receiver_id BIGINT NOT NULL REFERENCES Member(id),
+  -- The message members vote under, once it has been sent
+  message_id INTEGER,
+  -- Unix times in seconds
+  created_at BIGINT NOT NULL,
+  closes_at BIGINT NOT NULL
+);
+
+CREATE INDEX PendingAward_closes_at ON PendingAward(closes_at);
+CREATE INDEX PendingAward_chat_giver ON PendingAward(chat_id, giver_id, created_at);
+
+CREATE TABLE JuryVote (
+  pending_id BIGINT NOT NULL REFERENCES PendingAward(id) ON DELETE CASCADE,
+  voter_id BIGINT NOT NULL REFERENCES Member(id),
+  approve BOOLEAN NOT NULL,
+
+  PRIMARY KEY(pending_id, voter_id)
+);
@@ -0,0 +1,24 @@
+-- Authors of recent messages in chats that award Ls by reaction, since
+-- reaction updates don't say whose message was reacted to
+CREATE TABLE MessageAuthor (
+  chat_id BIGINT NOT NULL,
+  message_id INTEGER NOT NULL,
+  author_id BIGINT NOT NULL,
+  -- Unix time in seconds
+  sent_at BIGINT NOT NULL,
+
+  PRIMARY KEY(chat_id, message_id)
+);
+
+CREATE INDEX MessageAuthor_sent_at ON MessageAuthor(chat_id, sent_at);
+
+-- Awards given by reacting to a message, so removing the reaction takes
+-- them back
+CREATE TABLE ReactionAward (
+  award_id BIGINT PRIMARY KEY REFERENCES Award(id) ON DELETE CASCADE,
+  chat_id BIGINT NOT NULL,
+  message_id INTEGER NOT NULL,
+  reactor_id BIGINT NOT NULL,
+
+  UNIQUE(chat_id, message_id, reactor_id)
+);
@@ -0,0 +1,11 @@
+-- Recurring background jobs, shared by every bot running against the database
+-- so each run happens once
+CREATE TABLE Job (
+  name TEXT PRIMARY KEY,
+  -- Unix time in seconds the last run started
+  last_run_at BIGINT,
+  last_error TEXT,
+  -- The bot running the job, if any, and until when it may
+  locked_by TEXT,
+  locked_until BIGINT
+);
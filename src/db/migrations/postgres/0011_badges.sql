@@ -0,0 +1,11 @@
+-- Badges members earned in a chat. `badge` is the id of its definition.
+CREATE TABLE Badge (
+  chat_id BIGINT NOT NULL,
+  member_id BIGINT NOT NULL,
+  badge TEXT NOT NULL,
+  -- Unix time in seconds
+  granted_at BIGINT NOT NULL,
+
+  PRIMARY KEY(chat_id, member_id, badge),
+  FOREIGN KEY(member_id) REFERENCES Member(id)
+);
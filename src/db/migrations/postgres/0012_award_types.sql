@@ -0,0 +1,22 @@
+-- Awards come in types a chat can configure, such as Ls and Ws, and scores
+-- are kept per type. Everything recorded before this migration is an L.
+ALTER TABLE Stat RENAME COLUMN ls TO amount;
+ALTER TABLE Stat ADD COLUMN award_type TEXT NOT NULL DEFAULT 'l';
+ALTER TABLE Stat DROP CONSTRAINT stat_pkey;
+ALTER TABLE Stat ADD PRIMARY KEY (chat_id, member_id, award_type);
+
+ALTER TABLE Award ADD COLUMN award_type TEXT NOT NULL DEFAULT 'l';
+ALTER TABLE PendingAward ADD COLUMN award_type TEXT NOT NULL DEFAULT 'l';
+
+-- Award types a chat added, or built-in ones it changed. `code` is what
+-- members type after /give.
+CREATE TABLE AwardType (
+  chat_id BIGINT NOT NULL,
+  code TEXT NOT NULL,
+  name TEXT NOT NULL,
+  emoji TEXT NOT NULL,
+  -- How much one award adds to the net score, negative for Ls
+  weight INTEGER NOT NULL,
+
+  PRIMARY KEY(chat_id, code)
+);
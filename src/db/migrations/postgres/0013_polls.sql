@@ -0,0 +1,17 @@
+-- Polls the bot sent, the only ones Telegram reports the voters of
+CREATE TABLE Poll (
+  -- Telegram's id for the poll
+  id TEXT PRIMARY KEY,
+  chat_id BIGINT NOT NULL,
+  message_id INTEGER NOT NULL,
+
+  UNIQUE(chat_id, message_id)
+);
+
+-- Who voted in each poll, as long as they keep their vote
+CREATE TABLE PollVote (
+  poll_id TEXT NOT NULL REFERENCES Poll(id) ON DELETE CASCADE,
+  voter_id BIGINT NOT NULL REFERENCES Member(id),
+
+  PRIMARY KEY(poll_id, voter_id)
+);
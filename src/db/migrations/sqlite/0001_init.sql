@@ -1,5 +1,3 @@
-PRAGMA user_version = 1;
-
 CREATE TABLE IF NOT EXISTS Member (
   id INTEGER PRIMARY KEY,
   tgUserId TEXT
@@ -0,0 +1,24 @@
+-- Scores are now kept per chat. Rows recorded before this migration have no
+-- chat attached, so they are kept under chat 0 until `db assign-legacy`
+-- moves them into a chat.
+ALTER TABLE Stat RENAME TO LegacyStat;
+
+CREATE TABLE Stat (
+  chatId INTEGER NOT NULL,
+  memberId INTEGER NOT NULL,
+  ls INTEGER NOT NULL,
+
+  PRIMARY KEY(chatId, memberId),
+  FOREIGN KEY(memberId) REFERENCES Member(id)
+);
+
+INSERT INTO Stat (chatId, memberId, ls)
+SELECT 0, memberId, ls FROM LegacyStat;
+
+DROP TABLE LegacyStat;
+
+CREATE TABLE Admin (
+  memberId INTEGER PRIMARY KEY,
+
+  FOREIGN KEY(memberId) REFERENCES Member(id)
+);
@@ -0,0 +1,2 @@
+-- Last known display name, refreshed whenever the member sends a message
+ALTER TABLE Member ADD COLUMN firstName TEXT;
@@ -0,0 +1,9 @@
+-- Postgres folds unquoted identifiers to lowercase, so columns are named in
+-- snake_case to read back under the same names on either backend.
+ALTER TABLE Member RENAME COLUMN tgUserId TO tg_user_id;
+ALTER TABLE Member RENAME COLUMN firstName TO first_name;
+ALTER TABLE Stat RENAME COLUMN chatId TO chat_id;
+ALTER TABLE Stat RENAME COLUMN memberId TO member_id;
+ALTER TABLE Phrase RENAME COLUMN authorId TO author_id;
+ALTER TABLE Response RENAME COLUMN phraseId TO phrase_id;
+ALTER TABLE Admin RENAME COLUMN memberId TO member_id;
@@ -0,0 +1,10 @@
+-- Roles granted or taken away in a single chat, taking precedence over the
+-- member's Telegram admin status there
+CREATE TABLE ChatRole (
+  chat_id INTEGER NOT NULL,
+  member_id INTEGER NOT NULL,
+  role TEXT NOT NULL,
+
+  PRIMARY KEY(chat_id, member_id),
+  FOREIGN KEY(member_id) REFERENCES Member(id)
+);
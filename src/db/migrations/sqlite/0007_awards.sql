@@ -0,0 +1,40 @@
+-- Every L given, so it can be traced back to its giver and taken back.
+-- AUTOINCREMENT keeps the ids of removed awards from being reused, since
+-- buttons in old messages still refer to them.
+CREATE TABLE Award (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  chat_id INTEGER NOT NULL,
+  giver_id INTEGER NOT NULL,
+  receiver_id INTEGER NOT NULL,
+  -- The award this one was added to with "+1 L"
+  parent_id INTEGER,
+  -- Unix time in seconds
+  created_at INTEGER NOT NULL,
+
+  FOREIGN KEY(giver_id) REFERENCES Member(id),
+  FOREIGN KEY(receiver_id) REFERENCES Member(id),
+  FOREIGN KEY(parent_id) REFERENCES Award(id) ON DELETE CASCADE
+);
+
+CREATE INDEX Award_chat_giver ON Award(chat_id, giver_id, created_at);
+CREATE INDEX Award_parent ON Award(parent_id);
+
+-- Awards put to a vote, and how the vote ended once it has
+CREATE TABLE Dispute (
+  award_id INTEGER PRIMARY KEY,
+  opened_by INTEGER NOT NULL,
+  outcome TEXT,
+
+  FOREIGN KEY(award_id) REFERENCES Award(id) ON DELETE CASCADE,
+  FOREIGN KEY(opened_by) REFERENCES Member(id)
+);
+
+CREATE TABLE DisputeVote (
+  award_id INTEGER NOT NULL,
+  voter_id INTEGER NOT NULL,
+  keep BOOLEAN NOT NULL,
+
+  PRIMARY KEY(award_id, voter_id),
+  FOREIGN KEY(award_id) REFERENCES Dispute(award_id) ON DELETE CASCADE,
+  FOREIGN KEY(voter_id) REFERENCES Member(id)
+);
@@ -0,0 +1,29 @@
+-- Ls waiting for the chat's approval before they count, in chats with jury
+-- mode on. Confirmed ones move to Award, the rest are removed.
+CREATE TABLE PendingAward (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  chat_id INTEGER NOT NULL,
+  giver_id INTEGER NOT NULL,
+  receiver_id INTEGER NOT NULL,
+  -- The message members vote under, once it has been sent
+  message_id INTEGER,
+  -- Unix times in seconds
+  created_at INTEGER NOT NULL,
+  closes_at INTEGER NOT NULL,
+
+  FOREIGN KEY(giver_id) REFERENCES Member(id),
+  FOREIGN KEY(receiver_id) REFERENCES Member(id)
+);
+
+CREATE INDEX PendingAward_closes_at ON PendingAward(closes_at);
+CREATE INDEX PendingAward_chat_giver ON PendingAward(chat_id, giver_id, created_at);
+
+CREATE TABLE JuryVote (
+  pending_id INTEGER NOT NULL,
+  voter_id INTEGER NOT NULL,
+  approve BOOLEAN NOT NULL,
+
+  PRIMARY KEY(pending_id, voter_id),
+  FOREIGN KEY(pending_id) REFERENCES PendingAward(id) ON DELETE CASCADE,
+  FOREIGN KEY(voter_id) REFERENCES Member(id)
+);
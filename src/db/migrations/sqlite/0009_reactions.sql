@@ -0,0 +1,25 @@
+-- Authors of recent messages in chats that award Ls by reaction, since
+-- reaction updates don't say whose message was reacted to
+CREATE TABLE MessageAuthor (
+  chat_id INTEGER NOT NULL,
+  message_id INTEGER NOT NULL,
+  author_id INTEGER NOT NULL,
+  -- Unix time in seconds
+  sent_at INTEGER NOT NULL,
+
+  PRIMARY KEY(chat_id, message_id)
+);
+
+CREATE INDEX MessageAuthor_sent_at ON MessageAuthor(chat_id, sent_at);
+
+-- Awards given by reacting to a message, so removing the reaction takes
+-- them back
+CREATE TABLE ReactionAward (
+  award_id INTEGER PRIMARY KEY,
+  chat_id INTEGER NOT NULL,
+  message_id INTEGER NOT NULL,
+  reactor_id INTEGER NOT NULL,
+
+  UNIQUE(chat_id, message_id, reactor_id),
+  FOREIGN KEY(award_id) REFERENCES Award(id) ON DELETE CASCADE
+);
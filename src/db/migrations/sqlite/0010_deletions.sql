@@ -0,0 +1,11 @@
+-- Messages the bot deletes once their chat's delay for them has passed
+CREATE TABLE ScheduledDeletion (
+  chat_id INTEGER NOT NULL,
+  message_id INTEGER NOT NULL,
+  -- Unix time in seconds
+  delete_at INTEGER NOT NULL,
+
+  PRIMARY KEY(chat_id, message_id)
+);
+
+CREATE INDEX ScheduledDeletion_delete_at ON ScheduledDeletion(delete_at);
@@ -0,0 +1,15 @@
+-- Why an L was given, as written after /givel
+ALTER TABLE Award ADD COLUMN reason TEXT;
+ALTER TABLE PendingAward ADD COLUMN reason TEXT;
+
+-- Unix time in seconds, unknown for responses learned before it was recorded
+ALTER TABLE Response ADD COLUMN learned_at INTEGER;
+
+CREATE INDEX Award_chat_created_at ON Award(chat_id, created_at);
+
+-- When each chat's digest was last posted
+CREATE TABLE Digest (
+  chat_id INTEGER PRIMARY KEY,
+  -- Unix time in seconds the digest was due
+  posted_at INTEGER NOT NULL
+);
@@ -0,0 +1,5 @@
+-- Telegram username, so members can be looked up by @mention
+ALTER TABLE Member ADD COLUMN username TEXT;
+
+CREATE INDEX Member_username ON Member(LOWER(username));
+CREATE INDEX Award_chat_receiver ON Award(chat_id, receiver_id, created_at);
@@ -0,0 +1,33 @@
+-- Awards come in types a chat can configure, such as Ls and Ws, and scores
+-- are kept per type. Everything recorded before this migration is an L.
+CREATE TABLE TypedStat (
+  chat_id INTEGER NOT NULL,
+  member_id INTEGER NOT NULL,
+  award_type TEXT NOT NULL DEFAULT 'l',
+  amount INTEGER NOT NULL,
+
+  PRIMARY KEY(chat_id, member_id, award_type),
+  FOREIGN KEY(member_id) REFERENCES Member(id)
+);
+
+INSERT INTO TypedStat (chat_id, member_id, award_type, amount)
+SELECT chat_id, member_id, 'l', ls FROM Stat;
+
+DROP TABLE Stat;
+ALTER TABLE TypedStat RENAME TO Stat;
+
+ALTER TABLE Award ADD COLUMN award_type TEXT NOT NULL DEFAULT 'l';
+ALTER TABLE PendingAward ADD COLUMN award_type TEXT NOT NULL DEFAULT 'l';
+
+-- Award types a chat added, or built-in ones it changed. `code` is what
+-- members type after /give.
+CREATE TABLE AwardType (
+  chat_id INTEGER NOT NULL,
+  code TEXT NOT NULL,
+  name TEXT NOT NULL,
+  emoji TEXT NOT NULL,
+  -- How much one award adds to the net score, negative for Ls
+  weight INTEGER NOT NULL,
+
+  PRIMARY KEY(chat_id, code)
+);
@@ -0,0 +1,19 @@
+-- Polls the bot sent, the only ones Telegram reports the voters of
+CREATE TABLE Poll (
+  -- Telegram's id for the poll
+  id TEXT PRIMARY KEY,
+  chat_id INTEGER NOT NULL,
+  message_id INTEGER NOT NULL,
+
+  UNIQUE(chat_id, message_id)
+);
+
+-- Who voted in each poll, as long as they keep their vote
+CREATE TABLE PollVote (
+  poll_id TEXT NOT NULL,
+  voter_id INTEGER NOT NULL,
+
+  PRIMARY KEY(poll_id, voter_id),
+  FOREIGN KEY(poll_id) REFERENCES Poll(id) ON DELETE CASCADE,
+  FOREIGN KEY(voter_id) REFERENCES Member(id)
+);
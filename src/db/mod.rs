@@ -1,2 +1,8 @@
+pub mod backup;
+pub mod dump;
 pub mod models;
+pub mod pool;
+#[cfg(feature = "postgres")]
+pub mod postgres;
+pub mod repo;
 pub mod sqlite;
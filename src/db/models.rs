@@ -1,35 +1,271 @@
+use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 
-#[derive(FromRow, Debug)]
-#[sqlx(rename_all = "camelCase")]
+#[derive(FromRow, Serialize, Deserialize, Debug)]
 pub struct Member {
+    pub id: i64,
     pub tg_user_id: String,
+    #[serde(default)]
+    pub first_name: Option<String>,
+    /// Telegram username, without the @
+    #[serde(default)]
+    pub username: Option<String>,
 }
 
-#[derive(FromRow, Debug)]
-#[sqlx(rename_all = "camelCase")]
+#[derive(FromRow, Serialize, Deserialize, Debug)]
 pub struct Stat {
-    pub ls: i32,
+    pub chat_id: i64,
+    pub member_id: i64,
+    /// Code of the award type the score is for. Dumps from before award
+    /// types only had Ls.
+    #[serde(default = "default_award_type")]
+    pub award_type: String,
+    #[serde(alias = "ls")]
+    pub amount: i32,
+}
+
+fn default_award_type() -> String {
+    String::from("l")
 }
 
+/// A member's score of one award type.
 #[derive(FromRow, Debug)]
-#[sqlx(rename_all = "camelCase")]
 pub struct MemberStat {
     pub tg_user_id: String,
-    pub ls: i32,
+    pub first_name: Option<String>,
+    pub amount: i32,
+}
+
+/// A member's score of `award_type`, as one row of the net scoreboard.
+#[derive(FromRow, Debug)]
+pub struct TypedMemberStat {
+    pub tg_user_id: String,
+    pub first_name: Option<String>,
+    pub award_type: String,
+    pub amount: i32,
 }
 
+/// An award a member received, with when, so it can be weighed by its age.
 #[derive(FromRow, Debug)]
-#[sqlx(rename_all = "camelCase")]
+pub struct TimedAward {
+    pub tg_user_id: String,
+    pub first_name: Option<String>,
+    pub award_type: String,
+    /// Unix time in seconds
+    pub created_at: i64,
+}
+
+/// An award type a chat added, or a built-in one it changed.
+#[derive(FromRow, Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+pub struct AwardType {
+    pub chat_id: i64,
+    /// What members type after /give
+    pub code: String,
+    pub name: String,
+    pub emoji: String,
+    /// How much one award adds to the net score
+    pub weight: i32,
+}
+
+#[derive(FromRow, Serialize, Deserialize, Debug)]
 pub struct Phrase {
-    pub id: i32,
-    pub author_id: i32,
+    pub id: i64,
+    pub author_id: i64,
     pub content: String,
 }
 
+#[derive(FromRow, Serialize, Deserialize, Debug)]
+pub struct Response {
+    pub id: i64,
+    pub phrase_id: i64,
+    pub content: String,
+    /// Unix time in seconds, unknown for responses learned before it was
+    /// recorded
+    #[serde(default)]
+    pub learned_at: Option<i64>,
+}
+
+#[derive(FromRow, Serialize, Deserialize, Debug)]
+pub struct Admin {
+    pub member_id: i64,
+}
+
 #[derive(FromRow, Debug)]
-#[sqlx(rename_all = "camelCase")]
 pub struct DialogTurn {
     pub phrase: String,
     pub response: String,
 }
+
+#[derive(FromRow, Serialize, Deserialize, Debug)]
+pub struct ChatRole {
+    pub chat_id: i64,
+    pub member_id: i64,
+    pub role: String,
+}
+
+#[derive(FromRow, Serialize, Deserialize, Debug)]
+pub struct ChatSetting {
+    pub chat_id: i64,
+    pub name: String,
+    pub value: String,
+}
+
+#[derive(FromRow, Serialize, Deserialize, Clone, Debug)]
+pub struct Award {
+    pub id: i64,
+    pub chat_id: i64,
+    pub giver_id: i64,
+    pub receiver_id: i64,
+    /// Code of the award type
+    #[serde(default = "default_award_type")]
+    pub award_type: String,
+    /// The award this one was added to with "+1 L"
+    #[serde(default)]
+    pub parent_id: Option<i64>,
+    /// Unix time in seconds
+    pub created_at: i64,
+    /// Why the L was given
+    #[serde(default)]
+    pub reason: Option<String>,
+}
+
+impl Award {
+    /// The award at the head of the group this one belongs to: either this
+    /// one, or the award it was added to.
+    pub fn root_id(&self) -> i64 {
+        self.parent_id.unwrap_or(self.id)
+    }
+}
+
+#[derive(FromRow, Debug)]
+pub struct Dispute {
+    pub award_id: i64,
+    pub opened_by: i64,
+    /// `kept` once the vote has been decided in the award's favour
+    pub outcome: Option<String>,
+}
+
+/// Votes cast on a dispute so far.
+#[derive(FromRow, Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub struct DisputeTally {
+    pub keep_votes: i64,
+    pub drop_votes: i64,
+}
+
+#[derive(FromRow, Debug)]
+pub struct PendingAward {
+    pub id: i64,
+    pub chat_id: i64,
+    pub giver_id: i64,
+    pub receiver_id: i64,
+    pub award_type: String,
+    pub message_id: Option<i32>,
+    /// Unix time in seconds
+    pub created_at: i64,
+    /// Unix time in seconds after which the award is discarded
+    pub closes_at: i64,
+    pub reason: Option<String>,
+}
+
+/// Votes cast on a pending award so far.
+#[derive(FromRow, Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub struct JuryTally {
+    pub approvals: i64,
+    pub rejections: i64,
+}
+
+/// Ls a member received over some period.
+#[derive(FromRow, Debug)]
+pub struct MemberCount {
+    pub member_id: i64,
+    pub first_name: Option<String>,
+    pub ls: i64,
+}
+
+/// An award given with a reason, and the Ls added to it since.
+#[derive(FromRow, Debug)]
+pub struct ReasonedAward {
+    pub reason: String,
+    /// Name of the member who received it
+    pub first_name: Option<String>,
+    pub ls: i64,
+}
+
+/// An L a member received, with who gave it.
+#[derive(FromRow, Clone, Debug)]
+pub struct ReceivedAward {
+    pub giver_id: i64,
+    pub giver_first_name: Option<String>,
+    pub reason: Option<String>,
+    /// Unix time in seconds
+    pub created_at: i64,
+}
+
+/// A badge a member earned in a chat.
+#[derive(FromRow, Serialize, Deserialize, Debug)]
+pub struct EarnedBadge {
+    pub chat_id: i64,
+    pub member_id: i64,
+    /// Id of the badge's definition
+    pub badge: String,
+    /// Unix time in seconds
+    pub granted_at: i64,
+}
+
+#[derive(FromRow, Debug)]
+pub struct ScheduledDeletion {
+    pub chat_id: i64,
+    pub message_id: i32,
+    /// Unix time in seconds
+    pub delete_at: i64,
+}
+
+#[derive(FromRow, Debug)]
+pub struct Job {
+    pub name: String,
+    /// Unix time in seconds the last run started
+    pub last_run_at: Option<i64>,
+    pub last_error: Option<String>,
+    /// The bot running the job, if any
+    pub locked_by: Option<String>,
+    /// Unix time in seconds after which another bot may take over the job
+    pub locked_until: Option<i64>,
+}
+
+/// What a member may do, from least to most privileged.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum Role {
+    Member,
+    /// Administers the chat, either on Telegram or through a role override
+    ChatAdmin,
+    /// Runs the bot, set in the configuration or with `grant-admin`
+    Owner,
+}
+
+impl Role {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Member => "member",
+            Self::ChatAdmin => "admin",
+            Self::Owner => "owner",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "member" => Some(Self::Member),
+            "admin" => Some(Self::ChatAdmin),
+            "owner" => Some(Self::Owner),
+            _ => None,
+        }
+    }
+
+    /// Who holds the role, e.g. to say who may run a command.
+    pub fn holders(self) -> &'static str {
+        match self {
+            Self::Member => "members",
+            Self::ChatAdmin => "chat admins",
+            Self::Owner => "bot owners",
+        }
+    }
+}
@@ -0,0 +1,99 @@
+use miette::{bail, IntoDiagnostic, Result};
+use once_cell::sync::OnceCell;
+use sqlx::{
+    any::{AnyKind, AnyPoolOptions},
+    AnyConnection, AnyPool, Executor,
+};
+use std::env;
+use tracing::info;
+
+#[cfg(feature = "postgres")]
+use super::postgres;
+use super::sqlite;
+
+/// A pool of connections to whichever backend the connection URL names.
+pub type Pool = AnyPool;
+
+static INSTANCE: OnceCell<Pool> = OnceCell::new();
+
+/// Connection URL from `DATABASE_URL`, falling back to the SQLite file at
+/// [`sqlite::path`].
+pub fn url() -> String {
+    env::var("DATABASE_URL")
+        .ok()
+        .filter(|url| !url.is_empty())
+        .unwrap_or_else(sqlite::default_url)
+}
+
+pub async fn init() -> Result<()> {
+    init_with_url(&url()).await
+}
+
+/// Connects to the database at `url` instead of the configured one, e.g.
+/// `sqlite::memory:` in tests.
+pub async fn init_with_url(url: &str) -> Result<()> {
+    if INSTANCE.get().is_none() {
+        let pool = connect(url).await?;
+        if INSTANCE.set(pool).is_err() {
+            bail!("Unable to set database pool instance");
+        }
+    }
+
+    Ok(())
+}
+
+/// Opens a pool separate from the global instance.
+pub async fn connect(url: &str) -> Result<Pool> {
+    if cfg!(not(feature = "postgres")) && url.starts_with("postgres") {
+        bail!("Postgres support was not compiled in, rebuild with `--features postgres`");
+    }
+
+    AnyPoolOptions::new()
+        .max_connections(5)
+        .connect(url)
+        .await
+        .into_diagnostic()
+}
+
+pub fn db<'a>() -> &'a Pool {
+    INSTANCE
+        .get()
+        .expect("Database pool instance was never initialized!")
+}
+
+/// Brings the schema up to date, returning the number of migrations applied.
+pub async fn migrate() -> Result<usize> {
+    migrate_pool(db()).await
+}
+
+/// Like [`migrate`], for a pool other than the global instance.
+pub async fn migrate_pool(pool: &Pool) -> Result<usize> {
+    let kind = pool.any_kind();
+    let (migrations, version) = match kind {
+        AnyKind::Sqlite => (sqlite::MIGRATIONS, sqlite::version(pool).await?),
+        #[cfg(feature = "postgres")]
+        AnyKind::Postgres => (postgres::MIGRATIONS, postgres::version(pool).await?),
+    };
+
+    let mut applied = 0;
+    for (i, migration) in migrations.iter().enumerate().skip(version as usize) {
+        let mut tx = pool.begin().await.into_diagnostic()?;
+        // Executed without bind parameters so files may hold several statements
+        tx.execute(*migration).await.into_diagnostic()?;
+        set_version(&mut tx, kind, i as i64 + 1).await?;
+        tx.commit().await.into_diagnostic()?;
+
+        info!("Applied migration {}", i + 1);
+        applied += 1;
+    }
+
+    Ok(applied)
+}
+
+async fn set_version(conn: &mut AnyConnection, kind: AnyKind, version: i64) -> Result<()> {
+    match kind {
+        AnyKind::Sqlite => sqlite::set_version(conn, version).await,
+        #[cfg(feature = "postgres")]
+        AnyKind::Postgres => postgres::set_version(conn, version).await,
+    }
+}
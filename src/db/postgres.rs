@@ -0,0 +1,58 @@
+use miette::{IntoDiagnostic, Result};
+use sqlx::{AnyConnection, Executor};
+
+use super::pool::Pool;
+
+/// Schema migrations, applied in order. Each applied migration is recorded in
+/// `SchemaVersion` by its index plus one.
+pub(super) const MIGRATIONS: &[&str] = &[
+    include_str!("migrations/postgres/0001_init.sql"),
+    include_str!("migrations/postgres/0002_chat_roles.sql"),
+    include_str!("migrations/postgres/0003_chat_settings.sql"),
+    include_str!("migrations/postgres/0004_awards.sql"),
+    include_str!("migrations/postgres/0005_jury.sql"),
+    include_str!("migrations/postgres/0006_reactions.sql"),
+    include_str!("migrations/postgres/0007_deletions.sql"),
+    include_str!("migrations/postgres/0008_jobs.sql"),
+    include_str!("migrations/postgres/0009_digests.sql"),
+    include_str!("migrations/postgres/0010_stats.sql"),
+    include_str!("migrations/postgres/0011_badges.sql"),
+    include_str!("migrations/postgres/0012_award_types.sql"),
+    include_str!("migrations/postgres/0013_polls.sql"),
+];
+
+pub(super) async fn version(pool: &Pool) -> Result<i64> {
+    pool.execute("CREATE TABLE IF NOT EXISTS SchemaVersion (version BIGINT NOT NULL)")
+        .await
+        .into_diagnostic()?;
+
+    sqlx::query_scalar("SELECT COALESCE(MAX(version), 0) FROM SchemaVersion")
+        .fetch_one(pool)
+        .await
+        .into_diagnostic()
+}
+
+pub(super) async fn set_version(conn: &mut AnyConnection, version: i64) -> Result<()> {
+    sqlx::query("INSERT INTO SchemaVersion (version) VALUES ($1)")
+        .bind(version)
+        .execute(conn)
+        .await
+        .into_diagnostic()?;
+
+    Ok(())
+}
+
+/// Moves identity sequences past ids written explicitly, as an import does,
+/// so rows inserted afterwards don't collide with them.
+pub(super) async fn reset_sequences(conn: &mut AnyConnection) -> Result<()> {
+    for table in ["Phrase", "Response", "Award", "PendingAward"] {
+        let statement = format!(
+            "SELECT setval(pg_get_serial_sequence('{0}', 'id'), \
+             (SELECT COALESCE(MAX(id), 0) + 1 FROM {0}), false)",
+            table
+        );
+        conn.execute(statement.as_str()).await.into_diagnostic()?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,486 @@
+use miette::{IntoDiagnostic, Result};
+use sqlx::AnyConnection;
+use teloxide::types::{ChatId, UserId};
+
+use super::member;
+use crate::{
+    award_types,
+    common::time::unix_time,
+    db::{
+        models::{Award, MemberStat, TimedAward, TypedMemberStat},
+        pool::Pool,
+    },
+};
+
+/// An award that was just recorded.
+#[derive(Clone, Copy, Debug)]
+pub struct Awarded {
+    pub id: i64,
+    /// The receiver's new total of the award type in the chat
+    pub total: i32,
+}
+
+#[derive(Clone, Copy)]
+pub struct AwardRepo<'a> {
+    pool: &'a Pool,
+}
+
+impl<'a> AwardRepo<'a> {
+    pub fn new(pool: &'a Pool) -> Self {
+        Self { pool }
+    }
+
+    /// Records an award of `award_type` from `giver_id` to `receiver_id` in
+    /// `chat_id`, given for `reason` if one was written.
+    ///
+    /// The award and the receiver's score are written in one transaction,
+    /// and the score is incremented by a single upsert, so concurrent awards
+    /// neither conflict nor overwrite each other.
+    pub async fn award(
+        &self,
+        chat_id: ChatId,
+        award_type: &str,
+        giver_id: UserId,
+        receiver_id: UserId,
+        reason: Option<&str>,
+    ) -> Result<Awarded> {
+        let mut tx = self.pool.begin().await.into_diagnostic()?;
+        let awarded = record(
+            &mut tx,
+            chat_id,
+            award_type,
+            giver_id,
+            receiver_id,
+            None,
+            reason,
+        )
+        .await?;
+        tx.commit().await.into_diagnostic()?;
+
+        Ok(awarded)
+    }
+
+    /// Records an award of `award_type` from `giver_id` to each of
+    /// `receiver_ids` in `chat_id`, all given for `reason` if one was
+    /// written. Either every award is recorded or none is.
+    pub async fn award_many(
+        &self,
+        chat_id: ChatId,
+        award_type: &str,
+        giver_id: UserId,
+        receiver_ids: &[UserId],
+        reason: Option<&str>,
+    ) -> Result<Vec<Awarded>> {
+        let mut tx = self.pool.begin().await.into_diagnostic()?;
+        let mut awarded = Vec::with_capacity(receiver_ids.len());
+        for receiver_id in receiver_ids {
+            awarded.push(
+                record(
+                    &mut tx,
+                    chat_id,
+                    award_type,
+                    giver_id,
+                    *receiver_id,
+                    None,
+                    reason,
+                )
+                .await?,
+            );
+        }
+        tx.commit().await.into_diagnostic()?;
+
+        Ok(awarded)
+    }
+
+    /// Adds an award from `giver_id` to the group of awards headed by `root`.
+    pub async fn pile_on(&self, root: &Award, giver_id: UserId) -> Result<Awarded> {
+        let mut tx = self.pool.begin().await.into_diagnostic()?;
+        let awarded = record(
+            &mut tx,
+            ChatId(root.chat_id),
+            &root.award_type,
+            giver_id,
+            UserId(root.receiver_id as u64),
+            Some(root.id),
+            None,
+        )
+        .await?;
+        tx.commit().await.into_diagnostic()?;
+
+        Ok(awarded)
+    }
+
+    pub async fn get(&self, id: i64) -> Result<Option<Award>> {
+        sqlx::query_as(
+            r#"
+            SELECT id, chat_id, giver_id, receiver_id, award_type, parent_id, created_at, reason
+            FROM Award
+            WHERE id = $1
+            "#,
+        )
+        .bind(id)
+        .fetch_optional(self.pool)
+        .await
+        .into_diagnostic()
+    }
+
+    /// The award `giver_id` contributed to the group headed by `root_id`,
+    /// either the award itself or one added to it.
+    pub async fn given_in_group(&self, root_id: i64, giver_id: UserId) -> Result<Option<Award>> {
+        sqlx::query_as(
+            r#"
+            SELECT id, chat_id, giver_id, receiver_id, award_type, parent_id, created_at, reason
+            FROM Award
+            WHERE (id = $1 OR parent_id = $1) AND giver_id = $2
+            "#,
+        )
+        .bind(root_id)
+        .bind(giver_id.0 as i64)
+        .fetch_optional(self.pool)
+        .await
+        .into_diagnostic()
+    }
+
+    /// How many awards the group headed by `root_id` adds up to.
+    pub async fn group_size(&self, root_id: i64) -> Result<i64> {
+        sqlx::query_scalar("SELECT COUNT(*) FROM Award WHERE id = $1 OR parent_id = $1")
+            .bind(root_id)
+            .fetch_one(self.pool)
+            .await
+            .into_diagnostic()
+    }
+
+    /// When `giver_id` last gave an award of any type in `chat_id`, in Unix
+    /// seconds. Awards still waiting for the chat's approval count as given.
+    pub async fn last_given_at(&self, chat_id: ChatId, giver_id: UserId) -> Result<Option<i64>> {
+        sqlx::query_scalar(
+            r#"
+            SELECT MAX(created_at)
+            FROM (
+              SELECT created_at FROM Award WHERE chat_id = $1 AND giver_id = $2
+              UNION ALL
+              SELECT created_at FROM PendingAward WHERE chat_id = $1 AND giver_id = $2
+            ) AS given
+            "#,
+        )
+        .bind(chat_id.0)
+        .bind(giver_id.0 as i64)
+        .fetch_one(self.pool)
+        .await
+        .into_diagnostic()
+    }
+
+    /// Takes back an award along with any added to it, returning how many
+    /// awards were taken back.
+    pub async fn revoke(&self, award: &Award) -> Result<i64> {
+        let mut tx = self.pool.begin().await.into_diagnostic()?;
+
+        // Awards added to this one go with it. They are counted beforehand,
+        // since rows removed by a cascade don't count as affected.
+        let revoked = sqlx::query_scalar::<_, i64>(
+            "SELECT COUNT(*) FROM Award WHERE id = $1 OR parent_id = $1",
+        )
+        .bind(award.id)
+        .fetch_one(&mut tx)
+        .await
+        .into_diagnostic()?;
+        sqlx::query("DELETE FROM Award WHERE id = $1 OR parent_id = $1")
+            .bind(award.id)
+            .execute(&mut tx)
+            .await
+            .into_diagnostic()?;
+        if revoked > 0 {
+            add(
+                &mut tx,
+                award.chat_id,
+                award.receiver_id,
+                &award.award_type,
+                -revoked,
+            )
+            .await?;
+        }
+
+        tx.commit().await.into_diagnostic()?;
+
+        Ok(revoked)
+    }
+
+    /// Takes back just `award`, leaving any awards added to it counted. If it
+    /// headed a group, the oldest award added to it heads the rest.
+    ///
+    /// Returns the id of the award now heading the group, or `None` if
+    /// nothing is left of it.
+    pub async fn withdraw(&self, award: &Award) -> Result<Option<i64>> {
+        let mut tx = self.pool.begin().await.into_diagnostic()?;
+
+        let root_id = match award.parent_id {
+            Some(parent_id) => Some(parent_id),
+            None => {
+                let heir = sqlx::query_scalar::<_, Option<i64>>(
+                    "SELECT MIN(id) FROM Award WHERE parent_id = $1",
+                )
+                .bind(award.id)
+                .fetch_one(&mut tx)
+                .await
+                .into_diagnostic()?;
+                if let Some(heir) = heir {
+                    sqlx::query("UPDATE Award SET parent_id = NULL WHERE id = $1")
+                        .bind(heir)
+                        .execute(&mut tx)
+                        .await
+                        .into_diagnostic()?;
+                    sqlx::query("UPDATE Award SET parent_id = $1 WHERE parent_id = $2")
+                        .bind(heir)
+                        .bind(award.id)
+                        .execute(&mut tx)
+                        .await
+                        .into_diagnostic()?;
+                }
+                heir
+            }
+        };
+        let result = sqlx::query("DELETE FROM Award WHERE id = $1")
+            .bind(award.id)
+            .execute(&mut tx)
+            .await
+            .into_diagnostic()?;
+        if result.rows_affected() > 0 {
+            add(
+                &mut tx,
+                award.chat_id,
+                award.receiver_id,
+                &award.award_type,
+                -1,
+            )
+            .await?;
+        }
+
+        tx.commit().await.into_diagnostic()?;
+
+        Ok(root_id)
+    }
+
+    /// Everyone with Ls in `chat_id`, most Ls first.
+    pub async fn scoreboard(&self, chat_id: ChatId) -> Result<Vec<MemberStat>> {
+        self.scoreboard_of(chat_id, award_types::L).await
+    }
+
+    /// Everyone with awards of `award_type` in `chat_id`, most first.
+    pub async fn scoreboard_of(
+        &self,
+        chat_id: ChatId,
+        award_type: &str,
+    ) -> Result<Vec<MemberStat>> {
+        sqlx::query_as::<_, MemberStat>(
+            r#"
+            SELECT tg_user_id, first_name, amount
+            FROM Member
+            JOIN Stat
+              ON Member.id = Stat.member_id
+            WHERE Stat.chat_id = $1 AND award_type = $2 AND amount > 0
+            ORDER BY amount DESC
+            "#,
+        )
+        .bind(chat_id.0)
+        .bind(award_type)
+        .fetch_all(self.pool)
+        .await
+        .into_diagnostic()
+    }
+
+    /// Every member's score of every award type they have in `chat_id`.
+    pub async fn scores(&self, chat_id: ChatId) -> Result<Vec<TypedMemberStat>> {
+        sqlx::query_as::<_, TypedMemberStat>(
+            r#"
+            SELECT tg_user_id, first_name, award_type, amount
+            FROM Member
+            JOIN Stat
+              ON Member.id = Stat.member_id
+            WHERE Stat.chat_id = $1 AND amount > 0
+            ORDER BY Member.id, award_type
+            "#,
+        )
+        .bind(chat_id.0)
+        .fetch_all(self.pool)
+        .await
+        .into_diagnostic()
+    }
+
+    /// Every award received in `chat_id`, with when it was given, grouped by
+    /// member and award type.
+    pub async fn received_at(&self, chat_id: ChatId) -> Result<Vec<TimedAward>> {
+        sqlx::query_as::<_, TimedAward>(
+            r#"
+            SELECT tg_user_id, first_name, award_type, Award.created_at
+            FROM Award
+            JOIN Member
+              ON Member.id = Award.receiver_id
+            WHERE Award.chat_id = $1
+            ORDER BY Member.id, award_type, Award.created_at
+            "#,
+        )
+        .bind(chat_id.0)
+        .fetch_all(self.pool)
+        .await
+        .into_diagnostic()
+    }
+
+    /// The part of every member's scores in `chat_id` that no recorded award
+    /// accounts for, such as Ls given before awards were recorded one by
+    /// one. These have no date.
+    pub async fn undated(&self, chat_id: ChatId) -> Result<Vec<TypedMemberStat>> {
+        sqlx::query_as::<_, TypedMemberStat>(
+            r#"
+            SELECT tg_user_id, first_name, Stat.award_type,
+                   CAST(Stat.amount - COUNT(Award.id) AS INTEGER) AS amount
+            FROM Stat
+            JOIN Member
+              ON Member.id = Stat.member_id
+            LEFT JOIN Award
+              ON Award.chat_id = Stat.chat_id
+              AND Award.receiver_id = Stat.member_id
+              AND Award.award_type = Stat.award_type
+            WHERE Stat.chat_id = $1
+            GROUP BY Member.id, tg_user_id, first_name, Stat.award_type, Stat.amount
+            HAVING Stat.amount > COUNT(Award.id)
+            ORDER BY Member.id, Stat.award_type
+            "#,
+        )
+        .bind(chat_id.0)
+        .fetch_all(self.pool)
+        .await
+        .into_diagnostic()
+    }
+
+    /// Every chat `member_id` has a score in or gave an award in.
+    pub async fn chats_of(&self, member_id: UserId) -> Result<Vec<ChatId>> {
+        let chat_ids = sqlx::query_scalar::<_, i64>(
+            r#"
+            SELECT chat_id FROM Stat WHERE member_id = $1
+            UNION
+            SELECT chat_id FROM Award WHERE giver_id = $1
+            ORDER BY chat_id
+            "#,
+        )
+        .bind(member_id.0 as i64)
+        .fetch_all(self.pool)
+        .await
+        .into_diagnostic()?;
+
+        Ok(chat_ids.into_iter().map(ChatId).collect())
+    }
+
+    /// Moves the scores kept under chat 0, recorded before scoreboards were
+    /// kept per chat, into `chat_id`, adding them to any scores it already
+    /// has. Returns how many scores were moved.
+    pub async fn assign_legacy(&self, chat_id: ChatId) -> Result<u64> {
+        let mut tx = self.pool.begin().await.into_diagnostic()?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO Stat (chat_id, member_id, award_type, amount)
+            SELECT $1, member_id, award_type, amount FROM Stat WHERE chat_id = 0
+            ON CONFLICT (chat_id, member_id, award_type)
+            DO UPDATE SET amount = Stat.amount + excluded.amount
+            "#,
+        )
+        .bind(chat_id.0)
+        .execute(&mut tx)
+        .await
+        .into_diagnostic()?;
+        let result = sqlx::query("DELETE FROM Stat WHERE chat_id = 0")
+            .execute(&mut tx)
+            .await
+            .into_diagnostic()?;
+
+        tx.commit().await.into_diagnostic()?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Removes every score and award, pending or not, in `chat_id`, returning how many scores
+    /// were removed.
+    pub async fn reset(&self, chat_id: ChatId) -> Result<u64> {
+        let mut tx = self.pool.begin().await.into_diagnostic()?;
+
+        for table in ["Award", "PendingAward"] {
+            sqlx::query(&format!("DELETE FROM {} WHERE chat_id = $1", table))
+                .bind(chat_id.0)
+                .execute(&mut tx)
+                .await
+                .into_diagnostic()?;
+        }
+        let result = sqlx::query("DELETE FROM Stat WHERE chat_id = $1")
+            .bind(chat_id.0)
+            .execute(&mut tx)
+            .await
+            .into_diagnostic()?;
+
+        tx.commit().await.into_diagnostic()?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+/// Records an award and adds it to the receiver's score. Meant to run in a
+/// transaction.
+pub(super) async fn record(
+    conn: &mut AnyConnection,
+    chat_id: ChatId,
+    award_type: &str,
+    giver_id: UserId,
+    receiver_id: UserId,
+    parent_id: Option<i64>,
+    reason: Option<&str>,
+) -> Result<Awarded> {
+    member::ensure(conn, giver_id).await?;
+    member::ensure(conn, receiver_id).await?;
+
+    let id = sqlx::query_scalar::<_, i64>(
+        r#"
+        INSERT INTO Award (chat_id, giver_id, receiver_id, award_type, parent_id, created_at, reason)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        RETURNING id
+        "#,
+    )
+    .bind(chat_id.0)
+    .bind(giver_id.0 as i64)
+    .bind(receiver_id.0 as i64)
+    .bind(award_type)
+    .bind(parent_id)
+    .bind(unix_time())
+    .bind(reason)
+    .fetch_one(&mut *conn)
+    .await
+    .into_diagnostic()?;
+
+    let total = add(conn, chat_id.0, receiver_id.0 as i64, award_type, 1).await?;
+
+    Ok(Awarded { id, total })
+}
+
+/// Adds `amount`, which may be negative, to a member's score of
+/// `award_type`, returning the new total.
+async fn add(
+    conn: &mut AnyConnection,
+    chat_id: i64,
+    member_id: i64,
+    award_type: &str,
+    amount: i64,
+) -> Result<i32> {
+    sqlx::query_scalar::<_, i32>(
+        r#"
+        INSERT INTO Stat (chat_id, member_id, award_type, amount) VALUES ($1, $2, $3, $4)
+        ON CONFLICT (chat_id, member_id, award_type)
+        DO UPDATE SET amount = Stat.amount + excluded.amount
+        RETURNING amount
+        "#,
+    )
+    .bind(chat_id)
+    .bind(member_id)
+    .bind(award_type)
+    .bind(amount as i32)
+    .fetch_one(conn)
+    .await
+    .into_diagnostic()
+}
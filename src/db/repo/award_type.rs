@@ -0,0 +1,67 @@
+use miette::{IntoDiagnostic, Result};
+use teloxide::types::ChatId;
+
+use crate::db::{models::AwardType, pool::Pool};
+
+#[derive(Clone, Copy)]
+pub struct AwardTypeRepo<'a> {
+    pool: &'a Pool,
+}
+
+impl<'a> AwardTypeRepo<'a> {
+    pub fn new(pool: &'a Pool) -> Self {
+        Self { pool }
+    }
+
+    /// The award types `chat_id` added or changed, by code.
+    pub async fn stored(&self, chat_id: ChatId) -> Result<Vec<AwardType>> {
+        sqlx::query_as(
+            r#"
+            SELECT chat_id, code, name, emoji, weight
+            FROM AwardType
+            WHERE chat_id = $1
+            ORDER BY code
+            "#,
+        )
+        .bind(chat_id.0)
+        .fetch_all(self.pool)
+        .await
+        .into_diagnostic()
+    }
+
+    /// Adds `award_type` to its chat, replacing the type with the same code.
+    pub async fn set(&self, award_type: &AwardType) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO AwardType (chat_id, code, name, emoji, weight) VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (chat_id, code) DO UPDATE SET name = excluded.name,
+                                                      emoji = excluded.emoji,
+                                                      weight = excluded.weight
+            "#,
+        )
+        .bind(award_type.chat_id)
+        .bind(&award_type.code)
+        .bind(&award_type.name)
+        .bind(&award_type.emoji)
+        .bind(award_type.weight)
+        .execute(self.pool)
+        .await
+        .into_diagnostic()?;
+
+        Ok(())
+    }
+
+    /// Removes the award type called `code` from `chat_id`. Returns `false`
+    /// if the chat never stored one.
+    pub async fn remove(&self, chat_id: ChatId, code: &str) -> Result<bool> {
+        let removed = sqlx::query("DELETE FROM AwardType WHERE chat_id = $1 AND code = $2")
+            .bind(chat_id.0)
+            .bind(code)
+            .execute(self.pool)
+            .await
+            .into_diagnostic()?
+            .rows_affected();
+
+        Ok(removed > 0)
+    }
+}
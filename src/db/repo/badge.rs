@@ -0,0 +1,78 @@
+use miette::{IntoDiagnostic, Result};
+use teloxide::types::{ChatId, UserId};
+
+use super::member;
+use crate::{
+    common::time::unix_time,
+    db::{models::EarnedBadge, pool::Pool},
+};
+
+#[derive(Clone, Copy)]
+pub struct BadgeRepo<'a> {
+    pool: &'a Pool,
+}
+
+impl<'a> BadgeRepo<'a> {
+    pub fn new(pool: &'a Pool) -> Self {
+        Self { pool }
+    }
+
+    /// Grants `badge` to `member_id` in `chat_id`. Returns whether they
+    /// didn't have it yet.
+    pub async fn grant(&self, chat_id: ChatId, member_id: UserId, badge: &str) -> Result<bool> {
+        let mut tx = self.pool.begin().await.into_diagnostic()?;
+
+        member::ensure(&mut tx, member_id).await?;
+        let granted = sqlx::query(
+            r#"
+            INSERT INTO Badge (chat_id, member_id, badge, granted_at) VALUES ($1, $2, $3, $4)
+            ON CONFLICT DO NOTHING
+            "#,
+        )
+        .bind(chat_id.0)
+        .bind(member_id.0 as i64)
+        .bind(badge)
+        .bind(unix_time())
+        .execute(&mut tx)
+        .await
+        .into_diagnostic()?
+        .rows_affected();
+
+        tx.commit().await.into_diagnostic()?;
+
+        Ok(granted > 0)
+    }
+
+    /// The badges `member_id` earned in `chat_id`, oldest first.
+    pub async fn earned(&self, chat_id: ChatId, member_id: UserId) -> Result<Vec<EarnedBadge>> {
+        sqlx::query_as(
+            r#"
+            SELECT chat_id, member_id, badge, granted_at
+            FROM Badge
+            WHERE chat_id = $1 AND member_id = $2
+            ORDER BY granted_at, badge
+            "#,
+        )
+        .bind(chat_id.0)
+        .bind(member_id.0 as i64)
+        .fetch_all(self.pool)
+        .await
+        .into_diagnostic()
+    }
+
+    /// Every badge earned in `chat_id`, oldest first.
+    pub async fn earned_in_chat(&self, chat_id: ChatId) -> Result<Vec<EarnedBadge>> {
+        sqlx::query_as(
+            r#"
+            SELECT chat_id, member_id, badge, granted_at
+            FROM Badge
+            WHERE chat_id = $1
+            ORDER BY granted_at, badge
+            "#,
+        )
+        .bind(chat_id.0)
+        .fetch_all(self.pool)
+        .await
+        .into_diagnostic()
+    }
+}
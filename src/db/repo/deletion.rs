@@ -0,0 +1,69 @@
+use miette::{IntoDiagnostic, Result};
+use teloxide::types::{ChatId, MessageId};
+
+use crate::db::{models::ScheduledDeletion, pool::Pool};
+
+#[derive(Clone, Copy)]
+pub struct DeletionRepo<'a> {
+    pool: &'a Pool,
+}
+
+impl<'a> DeletionRepo<'a> {
+    pub fn new(pool: &'a Pool) -> Self {
+        Self { pool }
+    }
+
+    /// Schedules `message_id` to be deleted at `delete_at`. A message that is
+    /// already scheduled keeps its earlier time.
+    pub async fn schedule(
+        &self,
+        chat_id: ChatId,
+        message_id: MessageId,
+        delete_at: i64,
+    ) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO ScheduledDeletion (chat_id, message_id, delete_at) VALUES ($1, $2, $3)
+            ON CONFLICT (chat_id, message_id) DO NOTHING
+            "#,
+        )
+        .bind(chat_id.0)
+        .bind(message_id.0)
+        .bind(delete_at)
+        .execute(self.pool)
+        .await
+        .into_diagnostic()?;
+
+        Ok(())
+    }
+
+    /// Up to `limit` messages due for deletion at `now`, the longest overdue
+    /// first.
+    pub async fn due(&self, now: i64, limit: i64) -> Result<Vec<ScheduledDeletion>> {
+        sqlx::query_as(
+            r#"
+            SELECT chat_id, message_id, delete_at
+            FROM ScheduledDeletion
+            WHERE delete_at <= $1
+            ORDER BY delete_at
+            LIMIT $2
+            "#,
+        )
+        .bind(now)
+        .bind(limit)
+        .fetch_all(self.pool)
+        .await
+        .into_diagnostic()
+    }
+
+    pub async fn remove(&self, chat_id: ChatId, message_id: MessageId) -> Result<()> {
+        sqlx::query("DELETE FROM ScheduledDeletion WHERE chat_id = $1 AND message_id = $2")
+            .bind(chat_id.0)
+            .bind(message_id.0)
+            .execute(self.pool)
+            .await
+            .into_diagnostic()?;
+
+        Ok(())
+    }
+}
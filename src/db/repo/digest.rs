@@ -0,0 +1,113 @@
+use miette::{IntoDiagnostic, Result};
+use teloxide::types::ChatId;
+
+use crate::{
+    award_types,
+    db::{
+        models::{MemberCount, ReasonedAward},
+        pool::Pool,
+    },
+};
+
+/// What goes into the digests of Ls given in a chat.
+#[derive(Clone, Copy)]
+pub struct DigestRepo<'a> {
+    pool: &'a Pool,
+}
+
+impl<'a> DigestRepo<'a> {
+    pub fn new(pool: &'a Pool) -> Self {
+        Self { pool }
+    }
+
+    /// Everyone who received Ls in `chat_id` since `since`, in Unix seconds,
+    /// most Ls first.
+    pub async fn received_since(&self, chat_id: ChatId, since: i64) -> Result<Vec<MemberCount>> {
+        sqlx::query_as(
+            r#"
+            SELECT Award.receiver_id AS member_id, Member.first_name, COUNT(*) AS ls
+            FROM Award
+            JOIN Member
+              ON Member.id = Award.receiver_id
+            WHERE Award.chat_id = $1 AND Award.created_at >= $2 AND Award.award_type = $3
+            GROUP BY Award.receiver_id, Member.first_name
+            ORDER BY ls DESC, MIN(Award.created_at)
+            "#,
+        )
+        .bind(chat_id.0)
+        .bind(since)
+        .bind(award_types::L)
+        .fetch_all(self.pool)
+        .await
+        .into_diagnostic()
+    }
+
+    /// Up to `limit` of the awards given with a reason in `chat_id` since
+    /// `since`, the ones most Ls were added to first.
+    pub async fn top_reasons(
+        &self,
+        chat_id: ChatId,
+        since: i64,
+        limit: i64,
+    ) -> Result<Vec<ReasonedAward>> {
+        sqlx::query_as(
+            r#"
+            SELECT Award.reason, Member.first_name,
+                   (SELECT COUNT(*) FROM Award AS Added
+                    WHERE Added.id = Award.id OR Added.parent_id = Award.id) AS ls
+            FROM Award
+            JOIN Member
+              ON Member.id = Award.receiver_id
+            WHERE Award.chat_id = $1
+              AND Award.created_at >= $2
+              AND Award.parent_id IS NULL
+              AND Award.reason IS NOT NULL
+              AND Award.award_type = $4
+            ORDER BY ls DESC, Award.id
+            LIMIT $3
+            "#,
+        )
+        .bind(chat_id.0)
+        .bind(since)
+        .bind(limit)
+        .bind(award_types::L)
+        .fetch_all(self.pool)
+        .await
+        .into_diagnostic()
+    }
+
+    /// How many responses were learned since `since`. Learned phrases are
+    /// shared by every chat.
+    pub async fn responses_learned_since(&self, since: i64) -> Result<i64> {
+        sqlx::query_scalar("SELECT COUNT(*) FROM Response WHERE learned_at >= $1")
+            .bind(since)
+            .fetch_one(self.pool)
+            .await
+            .into_diagnostic()
+    }
+
+    /// When the last digest posted in `chat_id` was due, in Unix seconds.
+    pub async fn last_posted(&self, chat_id: ChatId) -> Result<Option<i64>> {
+        sqlx::query_scalar("SELECT posted_at FROM Digest WHERE chat_id = $1")
+            .bind(chat_id.0)
+            .fetch_optional(self.pool)
+            .await
+            .into_diagnostic()
+    }
+
+    pub async fn mark_posted(&self, chat_id: ChatId, posted_at: i64) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO Digest (chat_id, posted_at) VALUES ($1, $2)
+            ON CONFLICT (chat_id) DO UPDATE SET posted_at = excluded.posted_at
+            "#,
+        )
+        .bind(chat_id.0)
+        .bind(posted_at)
+        .execute(self.pool)
+        .await
+        .into_diagnostic()?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,97 @@
+use miette::{IntoDiagnostic, Result};
+use teloxide::types::UserId;
+
+use super::member;
+use crate::db::{
+    models::{Dispute, DisputeTally},
+    pool::Pool,
+};
+
+#[derive(Clone, Copy)]
+pub struct DisputeRepo<'a> {
+    pool: &'a Pool,
+}
+
+impl<'a> DisputeRepo<'a> {
+    pub fn new(pool: &'a Pool) -> Self {
+        Self { pool }
+    }
+
+    /// Puts `award_id` to a vote. Returns `false` if it already was.
+    pub async fn open(&self, award_id: i64, opened_by: UserId) -> Result<bool> {
+        let mut tx = self.pool.begin().await.into_diagnostic()?;
+
+        member::ensure(&mut tx, opened_by).await?;
+        let opened = sqlx::query(
+            "INSERT INTO Dispute (award_id, opened_by) VALUES ($1, $2) ON CONFLICT DO NOTHING",
+        )
+        .bind(award_id)
+        .bind(opened_by.0 as i64)
+        .execute(&mut tx)
+        .await
+        .into_diagnostic()?
+        .rows_affected()
+            > 0;
+
+        tx.commit().await.into_diagnostic()?;
+
+        Ok(opened)
+    }
+
+    pub async fn get(&self, award_id: i64) -> Result<Option<Dispute>> {
+        sqlx::query_as("SELECT award_id, opened_by, outcome FROM Dispute WHERE award_id = $1")
+            .bind(award_id)
+            .fetch_optional(self.pool)
+            .await
+            .into_diagnostic()
+    }
+
+    /// Records whether `voter_id` wants the award kept, replacing an earlier
+    /// vote of theirs, and returns the new tally.
+    pub async fn vote(&self, award_id: i64, voter_id: UserId, keep: bool) -> Result<DisputeTally> {
+        let mut tx = self.pool.begin().await.into_diagnostic()?;
+
+        member::ensure(&mut tx, voter_id).await?;
+        sqlx::query(
+            r#"
+            INSERT INTO DisputeVote (award_id, voter_id, keep) VALUES ($1, $2, $3)
+            ON CONFLICT (award_id, voter_id) DO UPDATE SET keep = excluded.keep
+            "#,
+        )
+        .bind(award_id)
+        .bind(voter_id.0 as i64)
+        .bind(keep)
+        .execute(&mut tx)
+        .await
+        .into_diagnostic()?;
+
+        let tally = sqlx::query_as(
+            r#"
+            SELECT COALESCE(SUM(CASE WHEN keep THEN 1 ELSE 0 END), 0) AS keep_votes,
+                   COALESCE(SUM(CASE WHEN keep THEN 0 ELSE 1 END), 0) AS drop_votes
+            FROM DisputeVote
+            WHERE award_id = $1
+            "#,
+        )
+        .bind(award_id)
+        .fetch_one(&mut tx)
+        .await
+        .into_diagnostic()?;
+
+        tx.commit().await.into_diagnostic()?;
+
+        Ok(tally)
+    }
+
+    /// Ends the vote on `award_id` with the award kept. Awards voted down
+    /// are revoked instead, which removes their dispute along with them.
+    pub async fn keep(&self, award_id: i64) -> Result<()> {
+        sqlx::query("UPDATE Dispute SET outcome = 'kept' WHERE award_id = $1")
+            .bind(award_id)
+            .execute(self.pool)
+            .await
+            .into_diagnostic()?;
+
+        Ok(())
+    }
+}
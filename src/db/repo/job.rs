@@ -0,0 +1,99 @@
+use miette::{IntoDiagnostic, Result};
+
+use crate::db::{models::Job, pool::Pool};
+
+#[derive(Clone, Copy)]
+pub struct JobRepo<'a> {
+    pool: &'a Pool,
+}
+
+impl<'a> JobRepo<'a> {
+    pub fn new(pool: &'a Pool) -> Self {
+        Self { pool }
+    }
+
+    /// Adds `name` to the jobs, as never having run. Known jobs are kept as
+    /// they are.
+    pub async fn register(&self, name: &str) -> Result<()> {
+        sqlx::query("INSERT INTO Job (name) VALUES ($1) ON CONFLICT (name) DO NOTHING")
+            .bind(name)
+            .execute(self.pool)
+            .await
+            .into_diagnostic()?;
+
+        Ok(())
+    }
+
+    pub async fn get(&self, name: &str) -> Result<Option<Job>> {
+        sqlx::query_as(
+            r#"
+            SELECT name, last_run_at, last_error, locked_by, locked_until
+            FROM Job
+            WHERE name = $1
+            "#,
+        )
+        .bind(name)
+        .fetch_optional(self.pool)
+        .await
+        .into_diagnostic()
+    }
+
+    /// Locks `name` for `holder` until `locked_until`, if the job last ran at
+    /// least `interval_secs` before `now` and no one else holds it. Returns
+    /// whether `holder` got the job, which only one caller does at a time.
+    pub async fn claim(
+        &self,
+        name: &str,
+        interval_secs: i64,
+        holder: &str,
+        now: i64,
+        locked_until: i64,
+    ) -> Result<bool> {
+        let claimed = sqlx::query(
+            r#"
+            UPDATE Job SET locked_by = $1, locked_until = $2
+            WHERE name = $3
+              AND (last_run_at IS NULL OR last_run_at + $4 <= $5)
+              AND (locked_until IS NULL OR locked_until <= $5)
+            "#,
+        )
+        .bind(holder)
+        .bind(locked_until)
+        .bind(name)
+        .bind(interval_secs)
+        .bind(now)
+        .execute(self.pool)
+        .await
+        .into_diagnostic()?
+        .rows_affected();
+
+        Ok(claimed == 1)
+    }
+
+    /// Records that `holder` finished the run of `name` it started at
+    /// `started_at`, unlocking the job. Does nothing if the lock ran out and
+    /// someone else took over.
+    pub async fn finish(
+        &self,
+        name: &str,
+        holder: &str,
+        started_at: i64,
+        error: Option<&str>,
+    ) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE Job SET last_run_at = $1, last_error = $2, locked_by = NULL, locked_until = NULL
+            WHERE name = $3 AND locked_by = $4
+            "#,
+        )
+        .bind(started_at)
+        .bind(error)
+        .bind(name)
+        .bind(holder)
+        .execute(self.pool)
+        .await
+        .into_diagnostic()?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,186 @@
+use miette::{IntoDiagnostic, Result};
+use teloxide::types::{ChatId, MessageId, UserId};
+
+use super::{award, member, Awarded};
+use crate::{
+    common::time::unix_time,
+    db::{
+        models::{JuryTally, PendingAward},
+        pool::Pool,
+    },
+};
+
+#[derive(Clone, Copy)]
+pub struct JuryRepo<'a> {
+    pool: &'a Pool,
+}
+
+impl<'a> JuryRepo<'a> {
+    pub fn new(pool: &'a Pool) -> Self {
+        Self { pool }
+    }
+
+    /// Records an award of `award_type` from `giver_id` to `receiver_id`,
+    /// given for `reason`, that only counts once the chat approves it, before
+    /// `closes_at`. Returns its id.
+    pub async fn propose(
+        &self,
+        chat_id: ChatId,
+        award_type: &str,
+        giver_id: UserId,
+        receiver_id: UserId,
+        reason: Option<&str>,
+        closes_at: i64,
+    ) -> Result<i64> {
+        let mut tx = self.pool.begin().await.into_diagnostic()?;
+
+        member::ensure(&mut tx, giver_id).await?;
+        member::ensure(&mut tx, receiver_id).await?;
+        let id = sqlx::query_scalar(
+            r#"
+            INSERT INTO PendingAward
+              (chat_id, giver_id, receiver_id, award_type, created_at, closes_at, reason)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            RETURNING id
+            "#,
+        )
+        .bind(chat_id.0)
+        .bind(giver_id.0 as i64)
+        .bind(receiver_id.0 as i64)
+        .bind(award_type)
+        .bind(unix_time())
+        .bind(closes_at)
+        .bind(reason)
+        .fetch_one(&mut tx)
+        .await
+        .into_diagnostic()?;
+
+        tx.commit().await.into_diagnostic()?;
+
+        Ok(id)
+    }
+
+    /// Remembers the message the chat votes on pending award `id` under.
+    pub async fn set_message(&self, id: i64, message_id: MessageId) -> Result<()> {
+        sqlx::query("UPDATE PendingAward SET message_id = $1 WHERE id = $2")
+            .bind(message_id.0)
+            .bind(id)
+            .execute(self.pool)
+            .await
+            .into_diagnostic()?;
+
+        Ok(())
+    }
+
+    pub async fn get(&self, id: i64) -> Result<Option<PendingAward>> {
+        sqlx::query_as(
+            r#"
+            SELECT id, chat_id, giver_id, receiver_id, award_type, message_id, created_at,
+                   closes_at, reason
+            FROM PendingAward
+            WHERE id = $1
+            "#,
+        )
+        .bind(id)
+        .fetch_optional(self.pool)
+        .await
+        .into_diagnostic()
+    }
+
+    /// Pending awards whose vote closed before `now`, in Unix seconds.
+    pub async fn closed(&self, now: i64) -> Result<Vec<PendingAward>> {
+        sqlx::query_as(
+            r#"
+            SELECT id, chat_id, giver_id, receiver_id, award_type, message_id, created_at,
+                   closes_at, reason
+            FROM PendingAward
+            WHERE closes_at <= $1
+            "#,
+        )
+        .bind(now)
+        .fetch_all(self.pool)
+        .await
+        .into_diagnostic()
+    }
+
+    /// Records whether `voter_id` approves pending award `id`, replacing an
+    /// earlier vote of theirs, and returns the new tally.
+    pub async fn vote(&self, id: i64, voter_id: UserId, approve: bool) -> Result<JuryTally> {
+        let mut tx = self.pool.begin().await.into_diagnostic()?;
+
+        member::ensure(&mut tx, voter_id).await?;
+        sqlx::query(
+            r#"
+            INSERT INTO JuryVote (pending_id, voter_id, approve) VALUES ($1, $2, $3)
+            ON CONFLICT (pending_id, voter_id) DO UPDATE SET approve = excluded.approve
+            "#,
+        )
+        .bind(id)
+        .bind(voter_id.0 as i64)
+        .bind(approve)
+        .execute(&mut tx)
+        .await
+        .into_diagnostic()?;
+
+        let tally = sqlx::query_as(
+            r#"
+            SELECT COALESCE(SUM(CASE WHEN approve THEN 1 ELSE 0 END), 0) AS approvals,
+                   COALESCE(SUM(CASE WHEN approve THEN 0 ELSE 1 END), 0) AS rejections
+            FROM JuryVote
+            WHERE pending_id = $1
+            "#,
+        )
+        .bind(id)
+        .fetch_one(&mut tx)
+        .await
+        .into_diagnostic()?;
+
+        tx.commit().await.into_diagnostic()?;
+
+        Ok(tally)
+    }
+
+    /// Turns pending award `pending` into an actual award. Returns `None` if
+    /// it was already confirmed or discarded, so concurrent votes can't
+    /// award it twice.
+    pub async fn confirm(&self, pending: &PendingAward) -> Result<Option<Awarded>> {
+        let mut tx = self.pool.begin().await.into_diagnostic()?;
+
+        let removed = sqlx::query("DELETE FROM PendingAward WHERE id = $1")
+            .bind(pending.id)
+            .execute(&mut tx)
+            .await
+            .into_diagnostic()?
+            .rows_affected();
+        if removed == 0 {
+            return Ok(None);
+        }
+        let awarded = award::record(
+            &mut tx,
+            ChatId(pending.chat_id),
+            &pending.award_type,
+            UserId(pending.giver_id as u64),
+            UserId(pending.receiver_id as u64),
+            None,
+            pending.reason.as_deref(),
+        )
+        .await?;
+
+        tx.commit().await.into_diagnostic()?;
+
+        Ok(Some(awarded))
+    }
+
+    /// Drops pending award `id`. Returns `false` if it was already confirmed
+    /// or discarded.
+    pub async fn discard(&self, id: i64) -> Result<bool> {
+        let removed = sqlx::query("DELETE FROM PendingAward WHERE id = $1")
+            .bind(id)
+            .execute(self.pool)
+            .await
+            .into_diagnostic()?
+            .rows_affected();
+
+        Ok(removed > 0)
+    }
+}
@@ -0,0 +1,123 @@
+use miette::{IntoDiagnostic, Result};
+use sqlx::AnyConnection;
+use teloxide::types::UserId;
+
+use crate::db::{models::Member, pool::Pool};
+
+#[derive(Clone, Copy)]
+pub struct MemberRepo<'a> {
+    pool: &'a Pool,
+}
+
+impl<'a> MemberRepo<'a> {
+    pub fn new(pool: &'a Pool) -> Self {
+        Self { pool }
+    }
+
+    /// Records a member, refreshing their last known name and username.
+    pub async fn remember(
+        &self,
+        user_id: UserId,
+        first_name: &str,
+        username: Option<&str>,
+    ) -> Result<()> {
+        let mut tx = self.pool.begin().await.into_diagnostic()?;
+
+        let changed = sqlx::query(
+            r#"
+            INSERT INTO Member (id, tg_user_id, first_name, username) VALUES ($1, $2, $3, $4)
+            ON CONFLICT (id) DO UPDATE SET first_name = excluded.first_name,
+                                           username = excluded.username
+            WHERE Member.first_name IS NULL
+               OR Member.first_name <> excluded.first_name
+               OR COALESCE(Member.username, '') <> COALESCE(excluded.username, '')
+            "#,
+        )
+        .bind(user_id.0 as i64)
+        .bind(user_id.to_string())
+        .bind(first_name)
+        .bind(username)
+        .execute(&mut tx)
+        .await
+        .into_diagnostic()?
+        .rows_affected();
+
+        // Usernames can be given up and taken by someone else
+        if let Some(username) = username.filter(|_| changed > 0) {
+            sqlx::query(
+                "UPDATE Member SET username = NULL WHERE LOWER(username) = LOWER($1) AND id <> $2",
+            )
+            .bind(username)
+            .bind(user_id.0 as i64)
+            .execute(&mut tx)
+            .await
+            .into_diagnostic()?;
+        }
+
+        tx.commit().await.into_diagnostic()?;
+
+        Ok(())
+    }
+
+    pub async fn get(&self, user_id: UserId) -> Result<Option<Member>> {
+        sqlx::query_as("SELECT id, tg_user_id, first_name, username FROM Member WHERE id = $1")
+            .bind(user_id.0 as i64)
+            .fetch_optional(self.pool)
+            .await
+            .into_diagnostic()
+    }
+
+    /// The member last seen with `username`, given without the @.
+    pub async fn by_username(&self, username: &str) -> Result<Option<Member>> {
+        sqlx::query_as(
+            r#"
+            SELECT id, tg_user_id, first_name, username
+            FROM Member
+            WHERE LOWER(username) = LOWER($1)
+            "#,
+        )
+        .bind(username)
+        .fetch_optional(self.pool)
+        .await
+        .into_diagnostic()
+    }
+
+    /// Whether a member may run admin commands.
+    pub async fn is_admin(&self, user_id: UserId) -> Result<bool> {
+        let admin = sqlx::query("SELECT 1 FROM Admin WHERE member_id = $1")
+            .bind(user_id.0 as i64)
+            .fetch_optional(self.pool)
+            .await
+            .into_diagnostic()?;
+
+        Ok(admin.is_some())
+    }
+
+    /// Allows a member to run admin commands.
+    pub async fn grant_admin(&self, user_id: UserId) -> Result<()> {
+        let mut tx = self.pool.begin().await.into_diagnostic()?;
+
+        ensure(&mut tx, user_id).await?;
+        sqlx::query("INSERT INTO Admin (member_id) VALUES ($1) ON CONFLICT DO NOTHING")
+            .bind(user_id.0 as i64)
+            .execute(&mut tx)
+            .await
+            .into_diagnostic()?;
+
+        tx.commit().await.into_diagnostic()?;
+
+        Ok(())
+    }
+}
+
+/// Creates a member row for `user_id` if there isn't one yet.
+pub(super) async fn ensure(conn: &mut AnyConnection, user_id: UserId) -> Result<()> {
+    sqlx::query("INSERT INTO Member (id, tg_user_id) VALUES ($1, $2) ON CONFLICT DO NOTHING")
+        .bind(user_id.0 as i64)
+        .bind(user_id.to_string())
+        .execute(conn)
+        .await
+        .into_diagnostic()?;
+
+    Ok(())
+}
@@ -0,0 +1,34 @@
+//! Typed access to stored data. Handlers go through these instead of writing
+//! SQL themselves.
+
+mod award;
+mod award_type;
+mod badge;
+mod deletion;
+mod digest;
+mod dispute;
+mod job;
+mod jury;
+mod member;
+mod phrase;
+mod poll;
+mod reaction;
+mod role;
+mod settings;
+mod stats;
+
+pub use award::{AwardRepo, Awarded};
+pub use award_type::AwardTypeRepo;
+pub use badge::BadgeRepo;
+pub use deletion::DeletionRepo;
+pub use digest::DigestRepo;
+pub use dispute::DisputeRepo;
+pub use job::JobRepo;
+pub use jury::JuryRepo;
+pub use member::MemberRepo;
+pub use phrase::PhraseRepo;
+pub use poll::PollRepo;
+pub use reaction::ReactionRepo;
+pub use role::RoleRepo;
+pub use settings::SettingsRepo;
+pub use stats::StatsRepo;
@@ -0,0 +1,99 @@
+use miette::{IntoDiagnostic, Result};
+use teloxide::types::UserId;
+
+use super::member;
+use crate::{
+    common::time::unix_time,
+    db::{models::DialogTurn, pool::Pool},
+};
+
+#[derive(Clone, Copy)]
+pub struct PhraseRepo<'a> {
+    pool: &'a Pool,
+}
+
+impl<'a> PhraseRepo<'a> {
+    pub fn new(pool: &'a Pool) -> Self {
+        Self { pool }
+    }
+
+    /// Adds `response` as a reply to `phrase`, which should already be
+    /// normalized.
+    pub async fn learn(&self, author_id: UserId, phrase: &str, response: &str) -> Result<()> {
+        let mut tx = self.pool.begin().await.into_diagnostic()?;
+
+        member::ensure(&mut tx, author_id).await?;
+
+        let phrase_id = sqlx::query_scalar::<_, i64>("SELECT id FROM Phrase WHERE content = $1")
+            .bind(phrase)
+            .fetch_optional(&mut tx)
+            .await
+            .into_diagnostic()?;
+        let phrase_id = match phrase_id {
+            Some(phrase_id) => phrase_id,
+            None => sqlx::query_scalar(
+                "INSERT INTO Phrase (author_id, content) VALUES ($1, $2) RETURNING id",
+            )
+            .bind(author_id.0 as i64)
+            .bind(phrase)
+            .fetch_one(&mut tx)
+            .await
+            .into_diagnostic()?,
+        };
+
+        sqlx::query("INSERT INTO Response (phrase_id, content, learned_at) VALUES ($1, $2, $3)")
+            .bind(phrase_id)
+            .bind(response)
+            .bind(unix_time())
+            .execute(&mut tx)
+            .await
+            .into_diagnostic()?;
+
+        tx.commit().await.into_diagnostic()?;
+
+        Ok(())
+    }
+
+    /// Forgets `phrase`, which should already be normalized, along with its
+    /// responses. Returns whether it was known.
+    pub async fn forget(&self, phrase: &str) -> Result<bool> {
+        let mut tx = self.pool.begin().await.into_diagnostic()?;
+
+        sqlx::query(
+            "DELETE FROM Response WHERE phrase_id IN (SELECT id FROM Phrase WHERE content = $1)",
+        )
+        .bind(phrase)
+        .execute(&mut tx)
+        .await
+        .into_diagnostic()?;
+        let forgotten = sqlx::query("DELETE FROM Phrase WHERE content = $1")
+            .bind(phrase)
+            .execute(&mut tx)
+            .await
+            .into_diagnostic()?
+            .rows_affected();
+
+        tx.commit().await.into_diagnostic()?;
+
+        Ok(forgotten > 0)
+    }
+
+    /// Every learned reply to `phrase`, which should already be normalized.
+    pub async fn responses(&self, phrase: &str) -> Result<Vec<DialogTurn>> {
+        sqlx::query_as::<_, DialogTurn>(
+            r#"
+            SELECT
+                Phrase.content   AS phrase,
+                Response.content AS response
+            FROM Phrase
+            JOIN Response
+              ON Response.phrase_id = Phrase.id
+            WHERE Phrase.content = $1
+            "#,
+        )
+        .bind(phrase)
+        .fetch_all(self.pool)
+        .await
+        .into_diagnostic()
+    }
+}
@@ -0,0 +1,94 @@
+use miette::{IntoDiagnostic, Result};
+use teloxide::types::{ChatId, MessageId, UserId};
+
+use super::member;
+use crate::db::pool::Pool;
+
+#[derive(Clone, Copy)]
+pub struct PollRepo<'a> {
+    pool: &'a Pool,
+}
+
+impl<'a> PollRepo<'a> {
+    pub fn new(pool: &'a Pool) -> Self {
+        Self { pool }
+    }
+
+    /// Remembers that the bot sent poll `poll_id` as `message_id`.
+    pub async fn add(&self, chat_id: ChatId, message_id: MessageId, poll_id: &str) -> Result<()> {
+        sqlx::query("INSERT INTO Poll (id, chat_id, message_id) VALUES ($1, $2, $3)")
+            .bind(poll_id)
+            .bind(chat_id.0)
+            .bind(message_id.0)
+            .execute(self.pool)
+            .await
+            .into_diagnostic()?;
+
+        Ok(())
+    }
+
+    /// Records whether `voter_id` has a vote in poll `poll_id`. Votes in
+    /// polls the bot didn't send are ignored.
+    pub async fn vote(&self, poll_id: &str, voter_id: UserId, voted: bool) -> Result<()> {
+        let mut tx = self.pool.begin().await.into_diagnostic()?;
+
+        if voted {
+            member::ensure(&mut tx, voter_id).await?;
+            sqlx::query(
+                r#"
+                INSERT INTO PollVote (poll_id, voter_id)
+                SELECT id, $2 FROM Poll WHERE id = $1
+                ON CONFLICT (poll_id, voter_id) DO NOTHING
+                "#,
+            )
+            .bind(poll_id)
+            .bind(voter_id.0 as i64)
+            .execute(&mut tx)
+            .await
+            .into_diagnostic()?;
+        } else {
+            sqlx::query("DELETE FROM PollVote WHERE poll_id = $1 AND voter_id = $2")
+                .bind(poll_id)
+                .bind(voter_id.0 as i64)
+                .execute(&mut tx)
+                .await
+                .into_diagnostic()?;
+        }
+
+        tx.commit().await.into_diagnostic()?;
+
+        Ok(())
+    }
+
+    /// Everyone with a vote in the poll sent as `message_id`, or `None` if
+    /// the bot didn't send a poll as that message.
+    pub async fn voters(
+        &self,
+        chat_id: ChatId,
+        message_id: MessageId,
+    ) -> Result<Option<Vec<UserId>>> {
+        let Some(poll_id) = sqlx::query_scalar::<_, String>(
+            "SELECT id FROM Poll WHERE chat_id = $1 AND message_id = $2",
+        )
+        .bind(chat_id.0)
+        .bind(message_id.0)
+        .fetch_optional(self.pool)
+        .await
+        .into_diagnostic()?
+        else {
+            return Ok(None);
+        };
+
+        let voters = sqlx::query_scalar::<_, i64>(
+            "SELECT voter_id FROM PollVote WHERE poll_id = $1 ORDER BY voter_id",
+        )
+        .bind(poll_id)
+        .fetch_all(self.pool)
+        .await
+        .into_diagnostic()?;
+
+        Ok(Some(
+            voters.into_iter().map(|id| UserId(id as u64)).collect(),
+        ))
+    }
+}
@@ -0,0 +1,148 @@
+use miette::{IntoDiagnostic, Result};
+use teloxide::types::{ChatId, MessageId, UserId};
+
+use super::{award, Awarded};
+use crate::{award_types, common::time::unix_time, db::pool::Pool};
+
+/// How long message authors are remembered, and so how old a message can be
+/// to still award an L by reacting to it
+const AUTHOR_RETENTION_SECS: i64 = 7 * 24 * 60 * 60;
+
+#[derive(Clone, Copy)]
+pub struct ReactionRepo<'a> {
+    pool: &'a Pool,
+}
+
+impl<'a> ReactionRepo<'a> {
+    pub fn new(pool: &'a Pool) -> Self {
+        Self { pool }
+    }
+
+    /// Remembers who sent `message_id`, forgetting the authors of messages in
+    /// the chat that are too old to be reacted to anymore.
+    pub async fn remember_author(
+        &self,
+        chat_id: ChatId,
+        message_id: MessageId,
+        author_id: UserId,
+    ) -> Result<()> {
+        let now = unix_time();
+        let mut tx = self.pool.begin().await.into_diagnostic()?;
+
+        sqlx::query("DELETE FROM MessageAuthor WHERE chat_id = $1 AND sent_at < $2")
+            .bind(chat_id.0)
+            .bind(now - AUTHOR_RETENTION_SECS)
+            .execute(&mut tx)
+            .await
+            .into_diagnostic()?;
+        sqlx::query(
+            r#"
+            INSERT INTO MessageAuthor (chat_id, message_id, author_id, sent_at)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (chat_id, message_id) DO NOTHING
+            "#,
+        )
+        .bind(chat_id.0)
+        .bind(message_id.0)
+        .bind(author_id.0 as i64)
+        .bind(now)
+        .execute(&mut tx)
+        .await
+        .into_diagnostic()?;
+
+        tx.commit().await.into_diagnostic()?;
+
+        Ok(())
+    }
+
+    pub async fn author(&self, chat_id: ChatId, message_id: MessageId) -> Result<Option<UserId>> {
+        let author_id = sqlx::query_scalar::<_, i64>(
+            "SELECT author_id FROM MessageAuthor WHERE chat_id = $1 AND message_id = $2",
+        )
+        .bind(chat_id.0)
+        .bind(message_id.0)
+        .fetch_optional(self.pool)
+        .await
+        .into_diagnostic()?;
+
+        Ok(author_id.map(|id| UserId(id as u64)))
+    }
+
+    /// Gives `receiver_id` an L from `reactor_id` for reacting to
+    /// `message_id`. Returns `None` if the reaction already awarded one.
+    pub async fn award(
+        &self,
+        chat_id: ChatId,
+        message_id: MessageId,
+        reactor_id: UserId,
+        receiver_id: UserId,
+    ) -> Result<Option<Awarded>> {
+        let mut tx = self.pool.begin().await.into_diagnostic()?;
+
+        let awarded_before = sqlx::query_scalar::<_, i64>(
+            r#"
+            SELECT award_id FROM ReactionAward
+            WHERE chat_id = $1 AND message_id = $2 AND reactor_id = $3
+            "#,
+        )
+        .bind(chat_id.0)
+        .bind(message_id.0)
+        .bind(reactor_id.0 as i64)
+        .fetch_optional(&mut tx)
+        .await
+        .into_diagnostic()?;
+        if awarded_before.is_some() {
+            return Ok(None);
+        }
+
+        let awarded = award::record(
+            &mut tx,
+            chat_id,
+            award_types::L,
+            reactor_id,
+            receiver_id,
+            None,
+            None,
+        )
+        .await?;
+        sqlx::query(
+            r#"
+            INSERT INTO ReactionAward (award_id, chat_id, message_id, reactor_id)
+            VALUES ($1, $2, $3, $4)
+            "#,
+        )
+        .bind(awarded.id)
+        .bind(chat_id.0)
+        .bind(message_id.0)
+        .bind(reactor_id.0 as i64)
+        .execute(&mut tx)
+        .await
+        .into_diagnostic()?;
+
+        tx.commit().await.into_diagnostic()?;
+
+        Ok(Some(awarded))
+    }
+
+    /// The award `reactor_id` gave by reacting to `message_id`, if it still
+    /// stands.
+    pub async fn award_id(
+        &self,
+        chat_id: ChatId,
+        message_id: MessageId,
+        reactor_id: UserId,
+    ) -> Result<Option<i64>> {
+        sqlx::query_scalar(
+            r#"
+            SELECT award_id FROM ReactionAward
+            WHERE chat_id = $1 AND message_id = $2 AND reactor_id = $3
+            "#,
+        )
+        .bind(chat_id.0)
+        .bind(message_id.0)
+        .bind(reactor_id.0 as i64)
+        .fetch_optional(self.pool)
+        .await
+        .into_diagnostic()
+    }
+}
@@ -0,0 +1,71 @@
+use miette::{IntoDiagnostic, Result};
+use teloxide::types::{ChatId, UserId};
+
+use super::member;
+use crate::db::{models::Role, pool::Pool};
+
+#[derive(Clone, Copy)]
+pub struct RoleRepo<'a> {
+    pool: &'a Pool,
+}
+
+impl<'a> RoleRepo<'a> {
+    pub fn new(pool: &'a Pool) -> Self {
+        Self { pool }
+    }
+
+    /// The role `user_id` was given in `chat_id`, if it was overridden.
+    pub async fn chat_role(&self, chat_id: ChatId, user_id: UserId) -> Result<Option<Role>> {
+        let role = sqlx::query_scalar::<_, String>(
+            "SELECT role FROM ChatRole WHERE chat_id = $1 AND member_id = $2",
+        )
+        .bind(chat_id.0)
+        .bind(user_id.0 as i64)
+        .fetch_optional(self.pool)
+        .await
+        .into_diagnostic()?;
+
+        Ok(role.as_deref().and_then(Role::from_name))
+    }
+
+    /// Overrides the role of `user_id` in `chat_id`, or goes back to their
+    /// Telegram admin status when `role` is `None`.
+    pub async fn set_chat_role(
+        &self,
+        chat_id: ChatId,
+        user_id: UserId,
+        role: Option<Role>,
+    ) -> Result<()> {
+        let mut tx = self.pool.begin().await.into_diagnostic()?;
+
+        match role {
+            Some(role) => {
+                member::ensure(&mut tx, user_id).await?;
+                sqlx::query(
+                    r#"
+                    INSERT INTO ChatRole (chat_id, member_id, role) VALUES ($1, $2, $3)
+                    ON CONFLICT (chat_id, member_id) DO UPDATE SET role = excluded.role
+                    "#,
+                )
+                .bind(chat_id.0)
+                .bind(user_id.0 as i64)
+                .bind(role.as_str())
+                .execute(&mut tx)
+                .await
+                .into_diagnostic()?;
+            }
+            None => {
+                sqlx::query("DELETE FROM ChatRole WHERE chat_id = $1 AND member_id = $2")
+                    .bind(chat_id.0)
+                    .bind(user_id.0 as i64)
+                    .execute(&mut tx)
+                    .await
+                    .into_diagnostic()?;
+            }
+        }
+
+        tx.commit().await.into_diagnostic()?;
+
+        Ok(())
+    }
+}
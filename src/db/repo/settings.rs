@@ -0,0 +1,50 @@
+use miette::{IntoDiagnostic, Result};
+use teloxide::types::ChatId;
+
+use crate::db::{models::ChatSetting, pool::Pool};
+
+#[derive(Clone, Copy)]
+pub struct SettingsRepo<'a> {
+    pool: &'a Pool,
+}
+
+impl<'a> SettingsRepo<'a> {
+    pub fn new(pool: &'a Pool) -> Self {
+        Self { pool }
+    }
+
+    /// Settings of `chat_id` that were changed from their defaults.
+    pub async fn changed(&self, chat_id: ChatId) -> Result<Vec<ChatSetting>> {
+        sqlx::query_as("SELECT chat_id, name, value FROM ChatSettings WHERE chat_id = $1")
+            .bind(chat_id.0)
+            .fetch_all(self.pool)
+            .await
+            .into_diagnostic()
+    }
+
+    /// Every chat that changed setting `name`, with the value it chose.
+    pub async fn chats_with(&self, name: &str) -> Result<Vec<ChatSetting>> {
+        sqlx::query_as("SELECT chat_id, name, value FROM ChatSettings WHERE name = $1")
+            .bind(name)
+            .fetch_all(self.pool)
+            .await
+            .into_diagnostic()
+    }
+
+    pub async fn set(&self, chat_id: ChatId, name: &str, value: &str) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO ChatSettings (chat_id, name, value) VALUES ($1, $2, $3)
+            ON CONFLICT (chat_id, name) DO UPDATE SET value = excluded.value
+            "#,
+        )
+        .bind(chat_id.0)
+        .bind(name)
+        .bind(value)
+        .execute(self.pool)
+        .await
+        .into_diagnostic()?;
+
+        Ok(())
+    }
+}
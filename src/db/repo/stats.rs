@@ -0,0 +1,81 @@
+use miette::{IntoDiagnostic, Result};
+use teloxide::types::{ChatId, UserId};
+
+use crate::{
+    award_types,
+    db::{models::ReceivedAward, pool::Pool},
+};
+
+/// What goes into a member's stats in a chat. Only Ls count, not awards of
+/// other types.
+#[derive(Clone, Copy)]
+pub struct StatsRepo<'a> {
+    pool: &'a Pool,
+}
+
+impl<'a> StatsRepo<'a> {
+    pub fn new(pool: &'a Pool) -> Self {
+        Self { pool }
+    }
+
+    /// Every L `member_id` received in `chat_id`, oldest first.
+    pub async fn received(&self, chat_id: ChatId, member_id: UserId) -> Result<Vec<ReceivedAward>> {
+        sqlx::query_as(
+            r#"
+            SELECT Award.giver_id, Member.first_name AS giver_first_name, Award.reason,
+                   Award.created_at
+            FROM Award
+            JOIN Member
+              ON Member.id = Award.giver_id
+            WHERE Award.chat_id = $1 AND Award.receiver_id = $2 AND Award.award_type = $3
+            ORDER BY Award.created_at, Award.id
+            "#,
+        )
+        .bind(chat_id.0)
+        .bind(member_id.0 as i64)
+        .bind(award_types::L)
+        .fetch_all(self.pool)
+        .await
+        .into_diagnostic()
+    }
+
+    /// The Ls `member_id` has on the scoreboard of `chat_id`.
+    pub async fn total(&self, chat_id: ChatId, member_id: UserId) -> Result<i64> {
+        let ls = sqlx::query_scalar::<_, i32>(
+            "SELECT amount FROM Stat WHERE chat_id = $1 AND member_id = $2 AND award_type = $3",
+        )
+        .bind(chat_id.0)
+        .bind(member_id.0 as i64)
+        .bind(award_types::L)
+        .fetch_optional(self.pool)
+        .await
+        .into_diagnostic()?;
+
+        Ok(ls.map_or(0, i64::from))
+    }
+
+    /// How many Ls `member_id` gave in `chat_id`.
+    pub async fn given(&self, chat_id: ChatId, member_id: UserId) -> Result<i64> {
+        sqlx::query_scalar(
+            "SELECT COUNT(*) FROM Award WHERE chat_id = $1 AND giver_id = $2 AND award_type = $3",
+        )
+        .bind(chat_id.0)
+        .bind(member_id.0 as i64)
+        .bind(award_types::L)
+        .fetch_one(self.pool)
+        .await
+        .into_diagnostic()
+    }
+
+    /// When each L in `chat_id` was given, in Unix seconds, oldest first.
+    pub async fn given_at(&self, chat_id: ChatId) -> Result<Vec<i64>> {
+        sqlx::query_scalar(
+            "SELECT created_at FROM Award WHERE chat_id = $1 AND award_type = $2 ORDER BY created_at",
+        )
+        .bind(chat_id.0)
+        .bind(award_types::L)
+        .fetch_all(self.pool)
+        .await
+        .into_diagnostic()
+    }
+}
@@ -1,10 +1,35 @@
-use miette::{bail, IntoDiagnostic, Result};
-use once_cell::sync::{Lazy, OnceCell};
-use sqlx::{sqlite::SqlitePoolOptions, SqlitePool};
-use std::{fs, path::PathBuf};
+use miette::{IntoDiagnostic, Result};
+use once_cell::sync::Lazy;
+use sqlx::AnyConnection;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
 
+use super::pool::Pool;
 use crate::common::constants::PROGRAM_NAME;
 
+/// Schema migrations, applied in order. The index of a migration plus one is
+/// the `user_version` the database is left at once it has been applied.
+pub(super) const MIGRATIONS: &[&str] = &[
+    include_str!("migrations/sqlite/0001_init.sql"),
+    include_str!("migrations/sqlite/0002_chat_stats.sql"),
+    include_str!("migrations/sqlite/0003_member_names.sql"),
+    include_str!("migrations/sqlite/0004_snake_case_columns.sql"),
+    include_str!("migrations/sqlite/0005_chat_roles.sql"),
+    include_str!("migrations/sqlite/0006_chat_settings.sql"),
+    include_str!("migrations/sqlite/0007_awards.sql"),
+    include_str!("migrations/sqlite/0008_jury.sql"),
+    include_str!("migrations/sqlite/0009_reactions.sql"),
+    include_str!("migrations/sqlite/0010_deletions.sql"),
+    include_str!("migrations/sqlite/0011_jobs.sql"),
+    include_str!("migrations/sqlite/0012_digests.sql"),
+    include_str!("migrations/sqlite/0013_stats.sql"),
+    include_str!("migrations/sqlite/0014_badges.sql"),
+    include_str!("migrations/sqlite/0015_award_types.sql"),
+    include_str!("migrations/sqlite/0016_polls.sql"),
+];
+
 static DB_PATH: Lazy<PathBuf> = Lazy::new(|| {
     let db_file_path = dirs::data_dir()
         .unwrap()
@@ -12,39 +37,46 @@ static DB_PATH: Lazy<PathBuf> = Lazy::new(|| {
         .join("db.sqlite3");
     if let Some(program_data_path) = db_file_path.parent() {
         if !program_data_path.exists() {
-            fs::create_dir(&program_data_path).unwrap();
+            fs::create_dir_all(program_data_path).unwrap();
         }
     }
     db_file_path
 });
-static INSTANCE: OnceCell<SqlitePool> = OnceCell::new();
-
-pub async fn init() -> Result<()> {
-    if INSTANCE.get().is_none() {
-        let pool = SqlitePoolOptions::new()
-            .max_connections(5)
-            .connect(&format!("sqlite://{}?mode=rwc", DB_PATH.to_string_lossy()))
-            .await
-            .into_diagnostic()?;
-        if let Err(_) = INSTANCE.set(pool) {
-            bail!("Unable to set SQLite pool instance");
-        }
-        seed().await?;
-    }
 
-    Ok(())
+/// Location of the database file used when no `DATABASE_URL` is set.
+pub fn path<'a>() -> &'a Path {
+    &DB_PATH
+}
+
+pub fn default_url() -> String {
+    format!("sqlite://{}?mode=rwc", DB_PATH.to_string_lossy())
 }
 
-pub fn db<'a>() -> &'a SqlitePool {
-    INSTANCE
-        .get()
-        .expect("SQLite pool instance was never initialized!")
+/// The database file a `sqlite:` connection URL refers to, if any.
+pub fn file_path(url: &str) -> Option<PathBuf> {
+    let path = url
+        .strip_prefix("sqlite://")
+        .or_else(|| url.strip_prefix("sqlite:"))?;
+    let path = path.split('?').next().unwrap_or_default();
+
+    match path {
+        "" | ":memory:" => None,
+        path => Some(PathBuf::from(path)),
+    }
+}
+
+pub(super) async fn version(pool: &Pool) -> Result<i64> {
+    sqlx::query_scalar("PRAGMA user_version")
+        .fetch_one(pool)
+        .await
+        .into_diagnostic()
 }
 
-pub async fn seed() -> Result<()> {
-    sqlx::query(include_str!("seed.sql"))
-        .execute(db())
+pub(super) async fn set_version(conn: &mut AnyConnection, version: i64) -> Result<()> {
+    sqlx::query(&format!("PRAGMA user_version = {}", version))
+        .execute(conn)
         .await
         .into_diagnostic()?;
+
     Ok(())
 }
@@ -0,0 +1,142 @@
+//! Scores in which old awards count for less, for chats that turn on
+//! `decay`. The scoreboard, its chart and members' ranks are worked out this
+//! way: awards are kept as they were given, so a chat can switch back to
+//! lifetime scores any time.
+
+use miette::Result;
+use teloxide::{types::ChatId, utils::markdown};
+
+use crate::{
+    common::time::{unix_time, DAY_SECS},
+    db::{
+        models::{MemberStat, TimedAward, TypedMemberStat},
+        pool::db,
+        repo::AwardRepo,
+    },
+    settings::{ChatSettings, ScoreDecay},
+};
+
+/// How much an award given `age` seconds ago counts under `settings`, from
+/// 0 to 1.
+pub fn weight(settings: &ChatSettings, age: i64) -> f64 {
+    let age = age.max(0);
+    let period = i64::from(settings.decay_days) * DAY_SECS;
+    match settings.decay {
+        ScoreDecay::Off => 1.0,
+        ScoreDecay::HalfLife => 0.5_f64.powf(age as f64 / period as f64),
+        ScoreDecay::Window if age < period => 1.0,
+        ScoreDecay::Window => 0.0,
+    }
+}
+
+/// Every member's score of every award type in `chat_id`, decayed as its
+/// `settings` say.
+pub async fn chat_scores(chat_id: ChatId, settings: &ChatSettings) -> Result<Vec<TypedMemberStat>> {
+    let awards = AwardRepo::new(db());
+    match settings.decay {
+        ScoreDecay::Off => awards.scores(chat_id).await,
+        ScoreDecay::HalfLife | ScoreDecay::Window => Ok(scores(
+            &awards.received_at(chat_id).await?,
+            &awards.undated(chat_id).await?,
+            settings,
+            unix_time(),
+        )),
+    }
+}
+
+/// Everyone with awards of `award_type` in `chat_id`, most first, decayed as
+/// its `settings` say.
+pub async fn scoreboard(
+    chat_id: ChatId,
+    settings: &ChatSettings,
+    award_type: &str,
+) -> Result<Vec<MemberStat>> {
+    if settings.decay == ScoreDecay::Off {
+        return AwardRepo::new(db())
+            .scoreboard_of(chat_id, award_type)
+            .await;
+    }
+
+    let mut stats = chat_scores(chat_id, settings)
+        .await?
+        .into_iter()
+        .filter(|score| score.award_type == award_type)
+        .map(|score| MemberStat {
+            tg_user_id: score.tg_user_id,
+            first_name: score.first_name,
+            amount: score.amount,
+        })
+        .collect::<Vec<_>>();
+    stats.sort_by_key(|stat| -stat.amount);
+
+    Ok(stats)
+}
+
+/// Adds `awards`, grouped by member and award type, up into each member's
+/// score of each type as of `now`, in Unix seconds, weighing every award by
+/// its age. The `undated` parts of scores, which no award accounts for, are
+/// weighed as if given one decay period ago. Scores are rounded to whole
+/// awards, and left out once they round to nothing.
+pub fn scores(
+    awards: &[TimedAward],
+    undated: &[TypedMemberStat],
+    settings: &ChatSettings,
+    now: i64,
+) -> Vec<TypedMemberStat> {
+    let mut sums: Vec<(TypedMemberStat, f64)> = Vec::new();
+    let mut add =
+        |tg_user_id: &str, first_name: &Option<String>, award_type: &str, amount| match sums
+            .iter_mut()
+            .find(|(sum, _)| sum.tg_user_id == tg_user_id && sum.award_type == award_type)
+        {
+            Some((_, sum)) => *sum += amount,
+            None => sums.push((
+                TypedMemberStat {
+                    tg_user_id: tg_user_id.to_owned(),
+                    first_name: first_name.clone(),
+                    award_type: award_type.to_owned(),
+                    amount: 0,
+                },
+                amount,
+            )),
+        };
+
+    for award in awards {
+        let weight = weight(settings, now - award.created_at);
+        add(
+            &award.tg_user_id,
+            &award.first_name,
+            &award.award_type,
+            weight,
+        );
+    }
+    let undated_weight = weight(settings, i64::from(settings.decay_days) * DAY_SECS);
+    for stat in undated {
+        let amount = f64::from(stat.amount) * undated_weight;
+        add(&stat.tg_user_id, &stat.first_name, &stat.award_type, amount);
+    }
+
+    sums.into_iter()
+        .map(|(stat, sum)| TypedMemberStat {
+            amount: sum.round() as i32,
+            ..stat
+        })
+        .filter(|stat| stat.amount > 0)
+        .collect()
+}
+
+/// A line in MarkdownV2 explaining how scores decay under `settings`, if
+/// they do.
+pub fn note(settings: &ChatSettings) -> Option<String> {
+    let days = match settings.decay_days {
+        1 => String::from("day"),
+        days => format!("{} days", days),
+    };
+    let note = match settings.decay {
+        ScoreDecay::Off => return None,
+        ScoreDecay::HalfLife => format!("Awards count half as much every {}", days),
+        ScoreDecay::Window => format!("Only awards from the last {} count", days),
+    };
+
+    Some(format!("_{}_", markdown::escape(&note)))
+}
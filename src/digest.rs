@@ -0,0 +1,269 @@
+//! Digests of the Ls given in a chat, posted daily or weekly at a local time
+//! the chat chooses.
+
+use chrono::{Datelike, TimeZone, Utc, Weekday};
+use miette::{IntoDiagnostic, Result};
+use std::{collections::HashMap, time::Duration};
+use teloxide::{prelude::*, types::ParseMode, utils::markdown};
+use tracing::{info, warn};
+
+use crate::{
+    award_types,
+    common::{
+        text::ls,
+        time::{unix_time, DAY_SECS},
+    },
+    db::{
+        models::{MemberCount, MemberStat, ReasonedAward},
+        pool::db,
+        repo::{DigestRepo, SettingsRepo},
+    },
+    decay,
+    settings::{self, ChatSettings, DigestFrequency, Setting},
+};
+
+/// How often chats are checked for digests that are due
+pub const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// How late a digest may still be posted, e.g. after the bot was down.
+/// Digests missed by longer are skipped.
+const CATCH_UP_SECS: i64 = 60 * 60;
+
+/// How many members and reasons a digest lists at most
+const TOP: usize = 3;
+
+/// The Ls given in a chat over a day or week.
+#[derive(Debug)]
+pub struct Digest {
+    pub frequency: DigestFrequency,
+    /// Those who received the most Ls, most first
+    pub top: Vec<MemberCount>,
+    pub gainer: Option<Gainer>,
+    /// Reasons Ls were given for, the ones most Ls were added to first
+    pub reasons: Vec<ReasonedAward>,
+    pub responses_learned: i64,
+}
+
+/// The member who climbed the most places on the scoreboard.
+#[derive(Debug)]
+pub struct Gainer {
+    pub first_name: Option<String>,
+    pub places: usize,
+    /// Place on the scoreboard now, from 1
+    pub rank: usize,
+}
+
+/// Length of the period a digest posted at `frequency` covers, in seconds.
+fn period_secs(frequency: DigestFrequency) -> i64 {
+    match frequency {
+        DigestFrequency::Weekly => 7 * DAY_SECS,
+        DigestFrequency::Off | DigestFrequency::Daily => DAY_SECS,
+    }
+}
+
+/// The latest time at or before `now`, in Unix seconds, a digest was due in a
+/// chat with `settings`.
+pub fn last_due(settings: &ChatSettings, now: i64) -> Option<i64> {
+    if settings.digest == DigestFrequency::Off {
+        return None;
+    }
+
+    let local_now = Utc
+        .timestamp_opt(now, 0)
+        .single()?
+        .with_timezone(&settings.timezone);
+    let mut date = local_now.date_naive();
+    // A week and a day back always reaches a Sunday that has passed
+    for _ in 0..=7 {
+        let on_day = settings.digest == DigestFrequency::Daily || date.weekday() == Weekday::Sun;
+        // Local times skipped by daylight saving time have no digest
+        let due_at = settings
+            .timezone
+            .from_local_datetime(&date.and_time(settings.digest_time))
+            .earliest()
+            .map(|due_at| due_at.timestamp());
+        match due_at {
+            Some(due_at) if on_day && due_at <= now => return Some(due_at),
+            _ => date = date.pred_opt()?,
+        }
+    }
+
+    None
+}
+
+/// Gathers what happened in `chat_id`, a chat with `settings`, during the
+/// period before `until`, in Unix seconds. Places on the scoreboard are
+/// decayed as the chat's scoreboard is.
+pub async fn collect(chat_id: ChatId, settings: &ChatSettings, until: i64) -> Result<Digest> {
+    let digests = DigestRepo::new(db());
+    let frequency = settings.digest;
+    let since = until - period_secs(frequency);
+
+    let received = digests.received_since(chat_id, since).await?;
+    let totals = decay::scoreboard(chat_id, settings, award_types::L).await?;
+    let gainer = biggest_gainer(&received, &totals);
+
+    Ok(Digest {
+        frequency,
+        top: received.into_iter().take(TOP).collect(),
+        gainer,
+        reasons: digests.top_reasons(chat_id, since, TOP as i64).await?,
+        responses_learned: digests.responses_learned_since(since).await?,
+    })
+}
+
+/// Finds who climbed the most places on the scoreboard thanks to the Ls in
+/// `received`, given the scoreboard `totals` now. Ties go to whoever received
+/// more Ls.
+fn biggest_gainer(received: &[MemberCount], totals: &[MemberStat]) -> Option<Gainer> {
+    let totals = totals
+        .iter()
+        .filter_map(|stat| Some((stat.tg_user_id.parse::<i64>().ok()?, stat.amount as i64)))
+        .collect::<HashMap<_, _>>();
+    let gained = received
+        .iter()
+        .map(|count| (count.member_id, count.ls))
+        .collect::<HashMap<_, _>>();
+    let before = totals
+        .iter()
+        .map(|(member_id, ls)| (*member_id, ls - gained.get(member_id).unwrap_or(&0)))
+        .collect::<HashMap<_, _>>();
+
+    // Members without Ls aren't on the scoreboard, so they come right after
+    // the last one who is
+    let rank = |scores: &HashMap<i64, i64>, member_id: i64| {
+        let score = scores.get(&member_id).copied().unwrap_or(0);
+        let ahead = scores.values().filter(|other| **other > score).count();
+        if score > 0 {
+            ahead + 1
+        } else {
+            scores.values().filter(|other| **other > 0).count() + 1
+        }
+    };
+
+    received
+        .iter()
+        .filter_map(|count| {
+            let now = rank(&totals, count.member_id);
+            let places = rank(&before, count.member_id).checked_sub(now)?;
+            (places > 0).then_some((places, count))
+        })
+        .max_by_key(|(places, count)| (*places, count.ls))
+        .map(|(places, count)| Gainer {
+            first_name: count.first_name.clone(),
+            places,
+            rank: rank(&totals, count.member_id),
+        })
+}
+
+fn name(first_name: &Option<String>) -> String {
+    format!(
+        "__{}__",
+        markdown::escape(first_name.as_deref().unwrap_or("(left)"))
+    )
+}
+
+/// Renders `digest` as MarkdownV2.
+pub fn render(digest: &Digest) -> String {
+    let (title, period) = match digest.frequency {
+        DigestFrequency::Weekly => ("Weekly L digest", "this week"),
+        DigestFrequency::Off | DigestFrequency::Daily => ("Daily L digest", "today"),
+    };
+    let mut lines = vec![format!("*{}*", title), String::new()];
+
+    if digest.top.is_empty() {
+        lines.push(markdown::escape(&format!("No Ls were given {}.", period)));
+    } else {
+        let top = digest
+            .top
+            .iter()
+            .map(|count| format!("{} with {}", name(&count.first_name), ls(count.ls)))
+            .collect::<Vec<_>>();
+        lines.push(format!("Most Ls: {}", top.join(", ")));
+    }
+    if let Some(gainer) = &digest.gainer {
+        lines.push(format!(
+            "Biggest gainer: {}, up {} {} to \\#{}",
+            name(&gainer.first_name),
+            gainer.places,
+            if gainer.places == 1 {
+                "place"
+            } else {
+                "places"
+            },
+            gainer.rank
+        ));
+    }
+    if !digest.reasons.is_empty() {
+        lines.push(String::from("Funniest reasons:"));
+        for award in &digest.reasons {
+            lines.push(format!(
+                "• “{}” — {}, {}",
+                markdown::escape(&award.reason),
+                name(&award.first_name),
+                ls(award.ls)
+            ));
+        }
+    }
+    lines.push(format!("Phrases learned: {}", digest.responses_learned));
+
+    lines.join("\n")
+}
+
+/// Posts the digests that are due at `now`, in Unix seconds, in every chat
+/// with digests on. Returns how many were posted.
+pub async fn post_due(bot: &Bot, now: i64) -> Result<usize> {
+    let digests = DigestRepo::new(db());
+    let mut posted = 0;
+    for chat in SettingsRepo::new(db())
+        .chats_with(Setting::Digest.name())
+        .await?
+    {
+        let chat_id = ChatId(chat.chat_id);
+        let settings = settings::get(chat_id).await?;
+        let Some(due_at) = last_due(&settings, now) else {
+            continue;
+        };
+        let last_posted = digests.last_posted(chat_id).await?;
+        if due_at < now - CATCH_UP_SECS || last_posted.is_some_and(|at| at >= due_at) {
+            continue;
+        }
+
+        let digest = collect(chat_id, &settings, due_at).await?;
+        let sent = bot
+            .send_message(chat_id, render(&digest))
+            .parse_mode(ParseMode::MarkdownV2)
+            .await;
+        match sent {
+            Ok(_) => posted += 1,
+            // The bot may have been removed from the chat, don't try again
+            Err(err) => warn!("Failed to post the digest in {}: {:?}", chat_id, err),
+        }
+        digests.mark_posted(chat_id, due_at).await?;
+    }
+
+    Ok(posted)
+}
+
+/// Posts the digests that are due, run every [`CHECK_INTERVAL`] by the
+/// scheduler.
+pub async fn run_posting(bot: Bot) -> Result<()> {
+    let posted = post_due(&bot, unix_time()).await?;
+    if posted > 0 {
+        info!("Posted {} digests", posted);
+    }
+
+    Ok(())
+}
+
+/// Posts a digest of the period up to now in `chat_id`, whether or not the
+/// chat has digests on.
+pub async fn preview(bot: &Bot, chat_id: ChatId) -> Result<Message> {
+    let settings = settings::get(chat_id).await?;
+    let digest = collect(chat_id, &settings, unix_time()).await?;
+
+    bot.send_message(chat_id, render(&digest))
+        .parse_mode(ParseMode::MarkdownV2)
+        .await
+        .into_diagnostic()
+}
@@ -0,0 +1,129 @@
+use miette::Diagnostic;
+use nanoid::nanoid;
+use teloxide::{
+    prelude::*,
+    types::{CallbackQuery, Message, MessageId},
+};
+use thiserror::Error;
+use tracing::{debug, error};
+
+use crate::metrics;
+
+pub type HandlerResult<T = ()> = std::result::Result<T, BotError>;
+
+/// Ways an update handler can fail, each surfaced to the user differently.
+#[derive(Error, Diagnostic, Debug)]
+pub enum BotError {
+    /// The user asked for something that can't be done. They are told why.
+    #[error("{0}")]
+    User(String),
+    /// There was nothing for the handler to act on. Nobody is told and it is
+    /// not logged as an error.
+    #[error("{0}")]
+    NoMatch(&'static str),
+    /// Something broke on our end. It is logged with a correlation id, which
+    /// is also shown to the user so reports can be matched with the logs.
+    #[error("[{id}] {source}")]
+    Internal {
+        id: String,
+        #[source]
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+}
+
+impl From<miette::Report> for BotError {
+    fn from(report: miette::Report) -> Self {
+        Self::Internal {
+            id: nanoid!(8),
+            source: report.into(),
+        }
+    }
+}
+
+impl BotError {
+    pub fn user(message: impl Into<String>) -> Self {
+        Self::User(message.into())
+    }
+
+    /// Replies to `msg` according to the kind of error, if any. `handler` names
+    /// the handler the error came from in logs and metrics.
+    pub async fn report(
+        result: HandlerResult,
+        handler: &str,
+        bot: &Bot,
+        msg: &Message,
+    ) -> miette::Result<()> {
+        Self::report_to(result, handler, bot, msg.chat.id, msg.id).await
+    }
+
+    /// Like [`report`](Self::report), for updates that refer to a message
+    /// without carrying it, such as reactions.
+    pub async fn report_to(
+        result: HandlerResult,
+        handler: &str,
+        bot: &Bot,
+        chat_id: ChatId,
+        message_id: MessageId,
+    ) -> miette::Result<()> {
+        use miette::IntoDiagnostic;
+
+        let Some(reply) = Self::explain(result, handler) else {
+            return Ok(());
+        };
+
+        bot.send_message(chat_id, reply)
+            .reply_to_message_id(message_id)
+            .await
+            .into_diagnostic()?;
+
+        Ok(())
+    }
+
+    /// Like [`report`](Self::report), for a button press. Errors are shown as
+    /// an alert, and queries that didn't match are answered silently, so the
+    /// button stops loading either way.
+    pub async fn report_callback(
+        result: HandlerResult,
+        handler: &str,
+        bot: &Bot,
+        query: &CallbackQuery,
+    ) -> miette::Result<()> {
+        use miette::IntoDiagnostic;
+
+        let answer = match result {
+            Ok(()) => return Ok(()),
+            Err(Self::NoMatch(reason)) => {
+                debug!("{} handler skipped callback query: {}", handler, reason);
+                bot.answer_callback_query(&query.id)
+            }
+            result => match Self::explain(result, handler) {
+                Some(text) => bot
+                    .answer_callback_query(&query.id)
+                    .text(text)
+                    .show_alert(true),
+                None => return Ok(()),
+            },
+        };
+        answer.await.into_diagnostic()?;
+
+        Ok(())
+    }
+
+    /// What to tell the user about `result`, logging and counting it on the
+    /// way. `None` when there is nothing to tell.
+    fn explain(result: HandlerResult, handler: &str) -> Option<String> {
+        match result {
+            Ok(()) => None,
+            Err(Self::NoMatch(reason)) => {
+                debug!("{} handler skipped update: {}", handler, reason);
+                None
+            }
+            Err(Self::User(message)) => Some(message),
+            Err(Self::Internal { id, source }) => {
+                metrics::HANDLER_ERRORS.with_label_values(&[handler]).inc();
+                error!("[{}] {} handler failed: {:?}", id, handler, source);
+                Some(format!("Something went wrong on my end (error id: {})", id))
+            }
+        }
+    }
+}
@@ -0,0 +1,39 @@
+use axum::{http::StatusCode, response::IntoResponse, routing::get, Router};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::{db::pool::db, metrics};
+
+static DISPATCHER_ALIVE: AtomicBool = AtomicBool::new(false);
+
+/// Records whether the dispatcher is currently processing updates.
+pub fn set_dispatcher_alive(alive: bool) {
+    DISPATCHER_ALIVE.store(alive, Ordering::SeqCst);
+}
+
+/// HTTP app exposing `/healthz` and `/metrics`.
+pub fn router() -> Router {
+    Router::new()
+        .route("/healthz", get(healthz))
+        .route("/metrics", get(|| async { metrics::render() }))
+}
+
+async fn healthz() -> impl IntoResponse {
+    let database = sqlx::query("SELECT 1").execute(db()).await.is_ok();
+    let dispatcher = DISPATCHER_ALIVE.load(Ordering::SeqCst);
+
+    let status = if database && dispatcher {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    let check = |ok| if ok { "ok" } else { "down" };
+
+    (
+        status,
+        format!(
+            "database: {}\ndispatcher: {}\n",
+            check(database),
+            check(dispatcher)
+        ),
+    )
+}
@@ -0,0 +1,130 @@
+//! Answers to inline queries, which members write after the bot's username
+//! in any chat: `scores` for the scoreboards of their chats, or
+//! `phrase <text>` for the responses learned for a phrase.
+
+use teloxide::{
+    types::{
+        ChatId, InlineQueryResult, InlineQueryResultArticle, InputMessageContent,
+        InputMessageContentText, ParseMode,
+    },
+    utils::markdown,
+};
+
+use crate::db::models::DialogTurn;
+
+/// Most results Telegram takes in one answer
+pub const MAX_RESULTS: usize = 50;
+/// Seconds Telegram may show the same results again without asking
+pub const CACHE_SECS: u32 = 10;
+
+/// Members shown on a scoreboard card
+const CARD_ROWS: usize = 10;
+/// Members named in the description of a scoreboard card
+const DESCRIPTION_ROWS: usize = 3;
+
+/// What an inline query asks for.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Query<'a> {
+    /// Scoreboards of the chats the member belongs to, also asked for by an
+    /// empty query
+    Scores,
+    /// Responses learned for the phrase
+    Phrase(&'a str),
+    Unknown,
+}
+
+/// Reads what `query` asks for.
+pub fn parse(query: &str) -> Query<'_> {
+    let query = query.trim();
+    if query.is_empty() || query.eq_ignore_ascii_case("scores") {
+        return Query::Scores;
+    }
+
+    match query.split_once(char::is_whitespace) {
+        Some((command, phrase)) if command.eq_ignore_ascii_case("phrase") => {
+            Query::Phrase(phrase.trim())
+        }
+        _ => Query::Unknown,
+    }
+}
+
+/// A member's line on a scoreboard card.
+#[derive(Clone, Debug)]
+pub struct CardRow {
+    pub name: String,
+    /// Their scores in MarkdownV2, as the chat's scoreboard shows them
+    pub scores: String,
+    /// Their score in plain text
+    pub summary: String,
+}
+
+/// The scoreboard of the chat called `title`, with the members of `rows`
+/// and the `note` on how its scores decay, as a MarkdownV2 message to share.
+pub fn scoreboard_card(title: &str, rows: &[CardRow], note: Option<&str>) -> String {
+    let mut lines = vec![format!("*Scoreboard of {}*", markdown::escape(title))];
+    if rows.is_empty() {
+        lines.push(String::from("Nobody has any Ls yet"));
+    }
+    lines.extend(rows.iter().take(CARD_ROWS).enumerate().map(|(i, row)| {
+        format!(
+            "{}\\. __{}__ — {}",
+            i + 1,
+            markdown::escape(&row.name),
+            row.scores
+        )
+    }));
+    if let Some(note) = note {
+        lines.push(String::new());
+        lines.push(note.to_owned());
+    }
+
+    lines.join("\n")
+}
+
+/// A result sharing the scoreboard of `chat_id`, called `title`.
+pub fn scoreboard_article(
+    chat_id: ChatId,
+    title: &str,
+    rows: &[CardRow],
+    note: Option<String>,
+) -> InlineQueryResult {
+    let content = InputMessageContentText::new(scoreboard_card(title, rows, note.as_deref()))
+        .parse_mode(ParseMode::MarkdownV2);
+    let description = if rows.is_empty() {
+        String::from("Nobody has any Ls yet")
+    } else {
+        rows.iter()
+            .take(DESCRIPTION_ROWS)
+            .map(|row| format!("{} {}", row.name, row.summary))
+            .collect::<Vec<_>>()
+            .join(" · ")
+    };
+
+    InlineQueryResult::Article(
+        InlineQueryResultArticle::new(
+            format!("scores:{}", chat_id),
+            format!("Scoreboard of {}", title),
+            InputMessageContent::Text(content),
+        )
+        .description(description),
+    )
+}
+
+/// A result inserting each of the responses in `turns`.
+pub fn phrase_articles(turns: &[DialogTurn]) -> Vec<InlineQueryResult> {
+    turns
+        .iter()
+        .take(MAX_RESULTS)
+        .enumerate()
+        .map(|(i, turn)| {
+            InlineQueryResult::Article(
+                InlineQueryResultArticle::new(
+                    format!("phrase:{}", i),
+                    turn.response.clone(),
+                    InputMessageContent::Text(InputMessageContentText::new(&turn.response)),
+                )
+                .description(format!("Learned for “{}”", turn.phrase)),
+            )
+        })
+        .collect()
+}
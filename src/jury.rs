@@ -0,0 +1,111 @@
+//! Jury mode, where Ls only count once other members of the chat approve
+//! them in time.
+
+use miette::Result;
+use std::time::Duration;
+use teloxide::{
+    prelude::*,
+    types::{InlineKeyboardButton, InlineKeyboardMarkup, MessageId},
+};
+use tracing::{debug, info};
+
+use crate::{
+    award_types,
+    common::time::unix_time,
+    db::{
+        models::{AwardType, JuryTally},
+        pool::db,
+        repo::JuryRepo,
+    },
+};
+
+/// Prefix of the callback data sent by the buttons of [`keyboard`]
+pub const CALLBACK_PREFIX: &str = "jury:";
+
+/// How often votes that ran out of time are looked for
+pub const EXPIRY_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Parses callback data with [`CALLBACK_PREFIX`] stripped into whether the
+/// voter approves, and the pending award voted on.
+pub fn parse_vote(data: &str) -> Option<(bool, i64)> {
+    let (vote, pending_id) = data.split_once(':')?;
+    let approve = match vote {
+        "approve" => true,
+        "reject" => false,
+        _ => return None,
+    };
+
+    Some((approve, pending_id.parse().ok()?))
+}
+
+/// The buttons for voting on pending award `pending_id`.
+pub fn keyboard(pending_id: i64) -> InlineKeyboardMarkup {
+    InlineKeyboardMarkup::new([[("Approve", "approve"), ("Reject", "reject")].map(
+        |(label, vote)| {
+            InlineKeyboardButton::callback(
+                label,
+                format!("{}{}:{}", CALLBACK_PREFIX, vote, pending_id),
+            )
+        },
+    )])
+}
+
+/// Describes a vote on a pending award of `award_type` in progress.
+pub fn text(award_type: &AwardType, tally: &JuryTally, needed: u32, closes_at: i64) -> String {
+    let minutes_left = (closes_at - unix_time()).max(0) as f64 / 60.0;
+    format!(
+        "This {} counts once {} members approve it within {} minutes. Approved: {}, rejected: {}.",
+        award_type.name,
+        needed,
+        minutes_left.ceil(),
+        tally.approvals,
+        tally.rejections
+    )
+}
+
+/// Discards the pending awards whose vote closed before `now`, in Unix
+/// seconds, saying so under their vote. Returns how many were discarded.
+pub async fn expire(bot: &Bot, now: i64) -> Result<usize> {
+    let jury = JuryRepo::new(db());
+    let mut discarded = 0;
+    for pending in jury.closed(now).await? {
+        // A vote may have settled it in the meantime
+        if !jury.discard(pending.id).await? {
+            continue;
+        }
+        discarded += 1;
+
+        let Some(message_id) = pending.message_id else {
+            continue;
+        };
+        let chat_id = ChatId(pending.chat_id);
+        let award_type = award_types::get(chat_id, &pending.award_type).await?;
+        let edited = bot
+            .edit_message_text(
+                chat_id,
+                MessageId(message_id),
+                format!(
+                    "Not enough members approved this {} in time, so it doesn't count",
+                    award_type.name
+                ),
+            )
+            .await;
+        if let Err(err) = edited {
+            // The message may have been deleted, the L is gone either way
+            debug!("Failed to announce a discarded L: {:?}", err);
+        }
+    }
+
+    Ok(discarded)
+}
+
+/// Discards the pending awards whose vote has run out of time, run every
+/// [`EXPIRY_INTERVAL`] by the scheduler.
+pub async fn run_expiry(bot: Bot) -> Result<()> {
+    let discarded = expire(&bot, unix_time()).await?;
+    if discarded > 0 {
+        info!("Discarded {} Ls that weren't approved in time", discarded);
+    }
+
+    Ok(())
+}
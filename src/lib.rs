@@ -0,0 +1,26 @@
+pub mod award_types;
+pub mod awards;
+pub mod badges;
+pub mod bot;
+pub mod chart;
+pub mod cleanup;
+pub mod cli;
+pub mod common;
+pub mod config;
+pub mod cooldown;
+pub mod db;
+pub mod decay;
+pub mod digest;
+pub mod error;
+pub mod health;
+pub mod inline;
+pub mod jury;
+pub mod metrics;
+pub mod permissions;
+pub mod reactions;
+pub mod scheduler;
+pub mod settings;
+pub mod stats;
+pub mod updates;
+pub mod utterance;
+pub mod webhook;
@@ -1,37 +1,36 @@
-mod bot;
-mod common;
-mod db;
-mod utterance;
-
+use clap::Parser;
 use dotenvy::dotenv;
 use miette::{IntoDiagnostic, Result, WrapErr};
+use std::io;
 
-use bot::*;
+use gustyfring::cli::Cli;
 
-async fn init() -> Result<()> {
+fn init() -> Result<()> {
     // miette panic hooks
     miette::set_panic_hook();
 
-    dotenv()
-        .into_diagnostic()
-        .wrap_err("Failed to load .env file")?;
-
-    // Initialize the logger to use environment variables
-    tracing_subscriber::fmt::init();
+    // A missing .env file is fine, the environment may be set directly
+    if let Err(err) = dotenv() {
+        if !err.not_found() {
+            return Err(err)
+                .into_diagnostic()
+                .wrap_err("Failed to load .env file");
+        }
+    }
 
-    // Initialize SQLite database
-    db::sqlite::init()
-        .await
-        .wrap_err("Failed to initialize SQLite database")?;
+    // Initialize the logger, on stderr so it stays out of command output
+    tracing_subscriber::fmt().with_writer(io::stderr).init();
 
     Ok(())
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    init().await?;
+    let cli = Cli::parse();
+
+    init()?;
 
-    run_bot().await?;
+    cli.run().await?;
 
     Ok(())
 }
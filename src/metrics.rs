@@ -0,0 +1,62 @@
+use lazy_static::lazy_static;
+use prometheus::{
+    register_histogram, register_int_counter, register_int_counter_vec, Encoder, Histogram,
+    IntCounter, IntCounterVec, TextEncoder,
+};
+
+lazy_static! {
+    pub static ref COMMANDS_HANDLED: IntCounterVec = register_int_counter_vec!(
+        "gus_commands_handled_total",
+        "Commands handled, by command",
+        &["command"]
+    )
+    .unwrap();
+    pub static ref AWARDS_GIVEN: IntCounterVec = register_int_counter_vec!(
+        "gus_awards_total",
+        "Awards given, by award type",
+        &["award_type"]
+    )
+    .unwrap();
+    pub static ref FALLBACK_MATCHES: IntCounter = register_int_counter!(
+        "gus_fallback_matches_total",
+        "Messages answered with a learned response"
+    )
+    .unwrap();
+    pub static ref FALLBACK_MISSES: IntCounter = register_int_counter!(
+        "gus_fallback_misses_total",
+        "Messages with no learned response"
+    )
+    .unwrap();
+    pub static ref DIALOGFLOW_REQUESTS: IntCounterVec = register_int_counter_vec!(
+        "gus_dialogflow_requests_total",
+        "Dialogflow intent detection requests, by outcome",
+        &["outcome"]
+    )
+    .unwrap();
+    pub static ref DIALOGFLOW_LATENCY: Histogram = register_histogram!(
+        "gus_dialogflow_request_duration_seconds",
+        "Time taken by Dialogflow intent detection requests"
+    )
+    .unwrap();
+    pub static ref JOB_RUNS: IntCounterVec = register_int_counter_vec!(
+        "gus_job_runs_total",
+        "Runs of background jobs, by job and outcome",
+        &["job", "outcome"]
+    )
+    .unwrap();
+    pub static ref HANDLER_ERRORS: IntCounterVec = register_int_counter_vec!(
+        "gus_handler_errors_total",
+        "Errors returned by update handlers, by handler",
+        &["handler"]
+    )
+    .unwrap();
+}
+
+/// Renders every registered metric in the Prometheus text format.
+pub fn render() -> String {
+    let mut buffer = Vec::new();
+    TextEncoder::new()
+        .encode(&prometheus::gather(), &mut buffer)
+        .expect("Failed to encode metrics");
+    String::from_utf8(buffer).expect("Metrics are not valid UTF-8")
+}
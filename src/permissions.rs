@@ -0,0 +1,87 @@
+//! Who may run which commands.
+
+use miette::{IntoDiagnostic, Result};
+use once_cell::sync::{Lazy, OnceCell};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+use teloxide::{
+    prelude::*,
+    types::{Chat, UserId},
+};
+
+use crate::db::{
+    models::Role,
+    pool::db,
+    repo::{MemberRepo, RoleRepo},
+};
+
+/// How long the admin list of a chat is reused before asking Telegram again
+const CHAT_ADMINS_TTL: Duration = Duration::from_secs(10 * 60);
+
+/// Admins of each chat, with when they were fetched
+type AdminCache = HashMap<ChatId, (Instant, HashSet<UserId>)>;
+
+static OWNERS: OnceCell<HashSet<UserId>> = OnceCell::new();
+static CHAT_ADMINS: Lazy<Mutex<AdminCache>> = Lazy::new(Default::default);
+
+/// Sets the bot owners from the configuration. Only the first call has any
+/// effect.
+pub fn set_owners(owners: impl IntoIterator<Item = UserId>) {
+    OWNERS.set(owners.into_iter().collect()).ok();
+}
+
+/// The most privileged role `user_id` holds in `chat`.
+///
+/// Owners outrank everything. Below that, a role override for the chat wins
+/// over the user's Telegram admin status. Everyone administers their own
+/// private chat with the bot.
+pub async fn role(bot: &Bot, chat: &Chat, user_id: UserId) -> Result<Role> {
+    let is_owner = OWNERS.get().is_some_and(|owners| owners.contains(&user_id));
+    if is_owner || MemberRepo::new(db()).is_admin(user_id).await? {
+        return Ok(Role::Owner);
+    }
+
+    if let Some(role) = RoleRepo::new(db()).chat_role(chat.id, user_id).await? {
+        return Ok(role.min(Role::ChatAdmin));
+    }
+
+    if chat.is_private() || chat_admins(bot, chat.id).await?.contains(&user_id) {
+        Ok(Role::ChatAdmin)
+    } else {
+        Ok(Role::Member)
+    }
+}
+
+/// Whether `user_id` holds `required` or a more privileged role in `chat`.
+pub async fn has_role(bot: &Bot, chat: &Chat, user_id: UserId, required: Role) -> Result<bool> {
+    if required == Role::Member {
+        return Ok(true);
+    }
+
+    Ok(role(bot, chat, user_id).await? >= required)
+}
+
+async fn chat_admins(bot: &Bot, chat_id: ChatId) -> Result<HashSet<UserId>> {
+    if let Some((fetched_at, admins)) = CHAT_ADMINS.lock().unwrap().get(&chat_id) {
+        if fetched_at.elapsed() < CHAT_ADMINS_TTL {
+            return Ok(admins.clone());
+        }
+    }
+
+    let admins = bot
+        .get_chat_administrators(chat_id)
+        .await
+        .into_diagnostic()?
+        .into_iter()
+        .map(|member| member.user.id)
+        .collect::<HashSet<_>>();
+    CHAT_ADMINS
+        .lock()
+        .unwrap()
+        .insert(chat_id, (Instant::now(), admins.clone()));
+
+    Ok(admins)
+}
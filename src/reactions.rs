@@ -0,0 +1,69 @@
+//! Ls awarded by reacting to a message with a chat's chosen emoji.
+//!
+//! teloxide doesn't know about reaction updates yet, so they arrive as
+//! [`UpdateKind::Error`] and are read from their JSON here.
+
+use serde::Deserialize;
+use teloxide::types::{Chat, MessageId, Update, UpdateKind, User};
+
+/// A change to the reactions a member left on a message.
+#[derive(Deserialize, Clone, Debug)]
+pub struct MessageReactionUpdated {
+    pub chat: Chat,
+    pub message_id: i32,
+    /// Missing when the member reacted anonymously
+    #[serde(default)]
+    pub user: Option<User>,
+    #[serde(default)]
+    pub old_reaction: Vec<ReactionType>,
+    #[serde(default)]
+    pub new_reaction: Vec<ReactionType>,
+}
+
+#[derive(Deserialize, Clone, PartialEq, Eq, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ReactionType {
+    Emoji {
+        emoji: String,
+    },
+    /// Custom emoji and any kinds of reaction added later
+    #[serde(other)]
+    Other,
+}
+
+/// How a reaction update affects the Ls given with `emoji`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Change {
+    Added,
+    Removed,
+}
+
+impl MessageReactionUpdated {
+    /// Reads a reaction update out of `update`, if it is one.
+    pub fn from_update(update: &Update) -> Option<Self> {
+        let UpdateKind::Error(value) = &update.kind else {
+            return None;
+        };
+
+        serde_json::from_value(value.get("message_reaction")?.clone()).ok()
+    }
+
+    pub fn message_id(&self) -> MessageId {
+        MessageId(self.message_id)
+    }
+
+    /// Whether `emoji` was added or removed by this update, if either.
+    pub fn change(&self, emoji: &str) -> Option<Change> {
+        let has = |reactions: &[ReactionType]| {
+            reactions.iter().any(|reaction| {
+                matches!(reaction, ReactionType::Emoji { emoji: reacted } if reacted == emoji)
+            })
+        };
+
+        match (has(&self.old_reaction), has(&self.new_reaction)) {
+            (false, true) => Some(Change::Added),
+            (true, false) => Some(Change::Removed),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,171 @@
+//! Runs recurring background jobs, such as discarding expired jury votes or
+//! taking backups. When each job last ran is stored in the database, so
+//! intervals carry over restarts and a job only runs once at a time, even
+//! with several bots sharing the database.
+
+use futures::future::BoxFuture;
+use miette::Result;
+use nanoid::nanoid;
+use std::{future::Future, sync::Arc, time::Duration};
+use teloxide::Bot;
+use tokio::{sync::watch, task::JoinHandle};
+use tracing::{debug, warn};
+
+use crate::{
+    common::time::unix_time,
+    db::{pool::db, repo::JobRepo},
+    metrics,
+};
+
+/// Longest a job waits to be looked at, however long its interval
+const MAX_POLL_INTERVAL: Duration = Duration::from_secs(60);
+
+/// How long a bot may run a job before others assume it died and take over
+const LEASE: Duration = Duration::from_secs(10 * 60);
+
+type Run = Arc<dyn Fn(Bot) -> BoxFuture<'static, Result<()>> + Send + Sync>;
+
+struct Job {
+    name: &'static str,
+    interval: Duration,
+    run: Run,
+}
+
+/// A set of jobs, each run every so often.
+pub struct Scheduler {
+    bot: Bot,
+    /// Tells this bot's runs apart from those of others sharing the database
+    holder: String,
+    jobs: Vec<Job>,
+}
+
+impl Scheduler {
+    pub fn new(bot: Bot) -> Self {
+        Self {
+            bot,
+            holder: nanoid!(),
+            jobs: Vec::new(),
+        }
+    }
+
+    /// Adds a job called `name`, run with `run` at most once every
+    /// `interval`. A job that never ran before runs right away.
+    pub fn every<F, Fut>(mut self, name: &'static str, interval: Duration, run: F) -> Self
+    where
+        F: Fn(Bot) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        self.jobs.push(Job {
+            name,
+            interval,
+            run: Arc::new(move |bot| Box::pin(run(bot))),
+        });
+        self
+    }
+
+    /// Runs the jobs that are due at `now`, in Unix seconds, and that no one
+    /// else is running. Returns the names of the jobs that ran.
+    pub async fn run_pending(&self, now: i64) -> Result<Vec<&'static str>> {
+        let mut ran = Vec::new();
+        for job in &self.jobs {
+            JobRepo::new(db()).register(job.name).await?;
+            if run_if_due(&self.bot, &self.holder, job, now).await? {
+                ran.push(job.name);
+            }
+        }
+
+        Ok(ran)
+    }
+
+    /// Starts running the jobs in the background.
+    pub async fn start(self) -> Result<SchedulerHandle> {
+        let jobs = JobRepo::new(db());
+        for job in &self.jobs {
+            jobs.register(job.name).await?;
+        }
+
+        let (stop, stopped) = watch::channel(false);
+        let holder = Arc::new(self.holder);
+        let tasks = self
+            .jobs
+            .into_iter()
+            .map(|job| {
+                tokio::spawn(run_job(
+                    self.bot.clone(),
+                    holder.clone(),
+                    job,
+                    stopped.clone(),
+                ))
+            })
+            .collect();
+
+        Ok(SchedulerHandle { stop, tasks })
+    }
+}
+
+/// Jobs running in the background.
+pub struct SchedulerHandle {
+    stop: watch::Sender<bool>,
+    tasks: Vec<JoinHandle<()>>,
+}
+
+impl SchedulerHandle {
+    /// Stops looking for due jobs, waiting for those running to finish.
+    pub async fn shutdown(self) {
+        self.stop.send(true).ok();
+        for task in self.tasks {
+            if let Err(err) = task.await {
+                warn!("A job panicked: {:?}", err);
+            }
+        }
+    }
+}
+
+async fn run_job(bot: Bot, holder: Arc<String>, job: Job, mut stopped: watch::Receiver<bool>) {
+    let mut interval = tokio::time::interval(job.interval.min(MAX_POLL_INTERVAL));
+    loop {
+        tokio::select! {
+            _ = interval.tick() => {}
+            _ = stopped.changed() => break,
+        }
+
+        if let Err(err) = run_if_due(&bot, &holder, &job, unix_time()).await {
+            warn!("Failed to run job {}: {:?}", job.name, err);
+        }
+    }
+}
+
+/// Runs `job` if it is due and `holder` gets hold of it. Returns whether it
+/// ran.
+async fn run_if_due(bot: &Bot, holder: &str, job: &Job, now: i64) -> Result<bool> {
+    let jobs = JobRepo::new(db());
+    let claimed = jobs
+        .claim(
+            job.name,
+            job.interval.as_secs() as i64,
+            holder,
+            now,
+            now + LEASE.as_secs() as i64,
+        )
+        .await?;
+    if !claimed {
+        return Ok(false);
+    }
+
+    debug!("Running job {}", job.name);
+    let result = (job.run)(bot.clone()).await;
+    let error = match &result {
+        Ok(()) => None,
+        Err(err) => {
+            warn!("Job {} failed: {:?}", job.name, err);
+            Some(format!("{:?}", err))
+        }
+    };
+    metrics::JOB_RUNS
+        .with_label_values(&[job.name, if error.is_some() { "error" } else { "ok" }])
+        .inc();
+
+    jobs.finish(job.name, holder, now, error.as_deref()).await?;
+
+    Ok(true)
+}
@@ -0,0 +1,498 @@
+//! Per-chat settings. Handlers read them through [`get`], which caches them.
+
+use chrono::NaiveTime;
+use chrono_tz::Tz;
+use miette::Result;
+use once_cell::sync::Lazy;
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+use teloxide::types::{ChatId, InlineKeyboardButton, InlineKeyboardMarkup};
+use tracing::warn;
+
+use crate::{
+    db::{pool::db, repo::SettingsRepo},
+    error::{BotError, HandlerResult},
+};
+
+/// Prefix of the callback data sent by the buttons of [`keyboard`]
+pub const CALLBACK_PREFIX: &str = "settings:";
+
+/// Most days awards can take to fade
+const MAX_DECAY_DAYS: u32 = 3650;
+/// Longest delay before a message is deleted. Telegram only lets bots delete
+/// messages up to 48 hours old.
+const MAX_DELETE_DELAY_SECS: u64 = 48 * 60 * 60;
+/// Most minutes a jury vote can stay open, a week
+const MAX_JURY_MINUTES: u64 = 7 * 24 * 60;
+
+/// How long settings are reused before reading them again, so changes made
+/// through another replica are picked up
+const CACHE_TTL: Duration = Duration::from_secs(30);
+
+/// Settings of each chat, with when they were read
+type SettingsCache = HashMap<ChatId, (Instant, ChatSettings)>;
+
+static CACHE: Lazy<Mutex<SettingsCache>> = Lazy::new(Default::default);
+
+/// How the bot answers a message.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ReplyStyle {
+    /// Quotes the message being answered
+    Quote,
+    /// Sends a plain message to the chat
+    Plain,
+}
+
+/// How often the chat's digest is posted.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DigestFrequency {
+    Off,
+    Daily,
+    /// On Sundays
+    Weekly,
+}
+
+/// How old awards count on the scoreboard.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ScoreDecay {
+    /// Every award counts in full, however old
+    Off,
+    /// Awards count half as much every `decay_days`
+    HalfLife,
+    /// Only awards from the last `decay_days` count
+    Window,
+}
+
+#[derive(Clone, Debug)]
+pub struct ChatSettings {
+    /// Time a member has to wait between giving Ls
+    pub award_cooldown: Duration,
+    /// Whether messages are answered with learned phrases
+    pub fallback_replies: bool,
+    /// Whether commands written in plain words are understood
+    pub nlu: bool,
+    /// Language code messages are understood in
+    pub language: String,
+    pub reply_style: ReplyStyle,
+    /// Votes either way that settle a disputed L
+    pub dispute_votes: u32,
+    /// Whether Ls only count once other members approve them
+    pub jury: bool,
+    /// Approvals an L needs in jury mode
+    pub jury_votes: u32,
+    /// Time members have to approve an L in jury mode
+    pub jury_time: Duration,
+    /// Emoji that awards an L to the author of the message reacted to
+    pub reaction: Option<String>,
+    /// Time after which the bot deletes its answers to commands, if not zero
+    pub delete_confirmations: Duration,
+    /// Time after which the bot deletes commands sent to it, if not zero
+    pub delete_commands: Duration,
+    /// Time after which the bot deletes its answers with learned phrases, if
+    /// not zero
+    pub delete_replies: Duration,
+    pub digest: DigestFrequency,
+    /// Local time the digest is posted at
+    pub digest_time: NaiveTime,
+    /// Time zone the chat's local times are in
+    pub timezone: Tz,
+    pub decay: ScoreDecay,
+    /// Days over which awards fade, as set by `decay`
+    pub decay_days: u32,
+}
+
+impl Default for ChatSettings {
+    fn default() -> Self {
+        Self {
+            award_cooldown: Duration::ZERO,
+            fallback_replies: true,
+            nlu: true,
+            language: String::from("en"),
+            reply_style: ReplyStyle::Quote,
+            dispute_votes: 3,
+            jury: false,
+            jury_votes: 3,
+            jury_time: Duration::from_secs(60 * 60),
+            reaction: None,
+            delete_confirmations: Duration::ZERO,
+            delete_commands: Duration::ZERO,
+            delete_replies: Duration::ZERO,
+            digest: DigestFrequency::Off,
+            digest_time: NaiveTime::from_hms_opt(20, 0, 0).unwrap(),
+            timezone: Tz::UTC,
+            decay: ScoreDecay::Off,
+            decay_days: 30,
+        }
+    }
+}
+
+/// A setting as listed and edited through `/settings`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Setting {
+    Cooldown,
+    Fallback,
+    Nlu,
+    Language,
+    ReplyStyle,
+    DisputeVotes,
+    Jury,
+    JuryVotes,
+    JuryTime,
+    Reaction,
+    DeleteConfirmations,
+    DeleteCommands,
+    DeleteReplies,
+    Digest,
+    DigestTime,
+    Timezone,
+    Decay,
+    DecayDays,
+}
+
+impl Setting {
+    pub const ALL: [Self; 18] = [
+        Self::Cooldown,
+        Self::Fallback,
+        Self::Nlu,
+        Self::Language,
+        Self::ReplyStyle,
+        Self::DisputeVotes,
+        Self::Jury,
+        Self::JuryVotes,
+        Self::JuryTime,
+        Self::Reaction,
+        Self::DeleteConfirmations,
+        Self::DeleteCommands,
+        Self::DeleteReplies,
+        Self::Digest,
+        Self::DigestTime,
+        Self::Timezone,
+        Self::Decay,
+        Self::DecayDays,
+    ];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|setting| setting.name() == name)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Cooldown => "cooldown",
+            Self::Fallback => "fallback",
+            Self::Nlu => "nlu",
+            Self::Language => "language",
+            Self::ReplyStyle => "replystyle",
+            Self::DisputeVotes => "disputevotes",
+            Self::Jury => "jury",
+            Self::JuryVotes => "juryvotes",
+            Self::JuryTime => "jurytime",
+            Self::Reaction => "reaction",
+            Self::DeleteConfirmations => "deleteconfirmations",
+            Self::DeleteCommands => "deletecommands",
+            Self::DeleteReplies => "deletereplies",
+            Self::Digest => "digest",
+            Self::DigestTime => "digesttime",
+            Self::Timezone => "timezone",
+            Self::Decay => "decay",
+            Self::DecayDays => "decaydays",
+        }
+    }
+
+    fn description(self) -> &'static str {
+        match self {
+            Self::Cooldown => "seconds a member waits between giving Ls",
+            Self::Fallback => "answer messages with learned phrases",
+            Self::Nlu => "understand commands written in plain words",
+            Self::Language => "language messages are understood in",
+            Self::ReplyStyle => "quote the message being answered, or plain",
+            Self::DisputeVotes => "votes either way that settle a disputed L",
+            Self::Jury => "Ls only count once other members approve them",
+            Self::JuryVotes => "approvals an L needs in jury mode",
+            Self::JuryTime => "minutes members have to approve an L in jury mode",
+            Self::Reaction => "emoji that awards an L when reacting with it, or off",
+            Self::DeleteConfirmations => {
+                "seconds before answers to commands are deleted, 0 keeps them"
+            }
+            Self::DeleteCommands => "seconds before commands are deleted, 0 keeps them",
+            Self::DeleteReplies => {
+                "seconds before answers with learned phrases are deleted, 0 keeps them"
+            }
+            Self::Digest => "post a digest of the Ls given daily, weekly on Sundays, or off",
+            Self::DigestTime => "local time the digest is posted at",
+            Self::Timezone => "time zone of local times, such as Europe/Berlin",
+            Self::Decay => "count old awards less: halflife, window, or off for lifetime scores",
+            Self::DecayDays => "days of the decay's half-life or window",
+        }
+    }
+
+    /// Current value of the setting in `settings`, as it is stored.
+    pub fn value(self, settings: &ChatSettings) -> String {
+        match self {
+            Self::Cooldown => settings.award_cooldown.as_secs().to_string(),
+            Self::Fallback => on_off(settings.fallback_replies).to_owned(),
+            Self::Nlu => on_off(settings.nlu).to_owned(),
+            Self::Language => settings.language.clone(),
+            Self::ReplyStyle => match settings.reply_style {
+                ReplyStyle::Quote => "quote",
+                ReplyStyle::Plain => "plain",
+            }
+            .to_owned(),
+            Self::DisputeVotes => settings.dispute_votes.to_string(),
+            Self::Jury => on_off(settings.jury).to_owned(),
+            Self::JuryVotes => settings.jury_votes.to_string(),
+            Self::JuryTime => (settings.jury_time.as_secs() / 60).to_string(),
+            Self::Reaction => settings.reaction.as_deref().unwrap_or("off").to_owned(),
+            Self::DeleteConfirmations => settings.delete_confirmations.as_secs().to_string(),
+            Self::DeleteCommands => settings.delete_commands.as_secs().to_string(),
+            Self::DeleteReplies => settings.delete_replies.as_secs().to_string(),
+            Self::Digest => match settings.digest {
+                DigestFrequency::Off => "off",
+                DigestFrequency::Daily => "daily",
+                DigestFrequency::Weekly => "weekly",
+            }
+            .to_owned(),
+            Self::DigestTime => settings.digest_time.format("%H:%M").to_string(),
+            Self::Timezone => settings.timezone.name().to_owned(),
+            Self::Decay => match settings.decay {
+                ScoreDecay::Off => "off",
+                ScoreDecay::HalfLife => "halflife",
+                ScoreDecay::Window => "window",
+            }
+            .to_owned(),
+            Self::DecayDays => settings.decay_days.to_string(),
+        }
+    }
+
+    /// Parses `value` into `settings`, explaining what is expected if it
+    /// isn't valid.
+    pub fn apply(self, settings: &mut ChatSettings, value: &str) -> Result<(), String> {
+        let invalid = |expected: &str| Err(format!("{} must be {}", self.name(), expected));
+
+        match self {
+            Self::Cooldown
+            | Self::DeleteConfirmations
+            | Self::DeleteCommands
+            | Self::DeleteReplies => {
+                let Ok(secs) = value.parse() else {
+                    return invalid("a number of seconds");
+                };
+                if self != Self::Cooldown && secs > MAX_DELETE_DELAY_SECS {
+                    return invalid(&format!(
+                        "a number of seconds from 0 to {}",
+                        MAX_DELETE_DELAY_SECS
+                    ));
+                }
+                let delay = Duration::from_secs(secs);
+                match self {
+                    Self::Cooldown => settings.award_cooldown = delay,
+                    Self::DeleteConfirmations => settings.delete_confirmations = delay,
+                    Self::DeleteCommands => settings.delete_commands = delay,
+                    _ => settings.delete_replies = delay,
+                }
+            }
+            Self::Fallback | Self::Nlu | Self::Jury => {
+                let enabled = match value {
+                    "on" => true,
+                    "off" => false,
+                    _ => return invalid("on or off"),
+                };
+                match self {
+                    Self::Fallback => settings.fallback_replies = enabled,
+                    Self::Nlu => settings.nlu = enabled,
+                    _ => settings.jury = enabled,
+                }
+            }
+            Self::Language => {
+                let valid = (2..=8).contains(&value.len())
+                    && value.chars().all(|c| c.is_ascii_alphanumeric() || c == '-');
+                if !valid {
+                    return invalid("a language code such as en or pt-BR");
+                }
+                settings.language = value.to_owned();
+            }
+            Self::ReplyStyle => {
+                settings.reply_style = match value {
+                    "quote" => ReplyStyle::Quote,
+                    "plain" => ReplyStyle::Plain,
+                    _ => return invalid("quote or plain"),
+                }
+            }
+            Self::DisputeVotes | Self::JuryVotes => match value.parse() {
+                Ok(votes) if votes > 0 => {
+                    if self == Self::DisputeVotes {
+                        settings.dispute_votes = votes;
+                    } else {
+                        settings.jury_votes = votes;
+                    }
+                }
+                _ => return invalid("a number of votes of at least 1"),
+            },
+            Self::JuryTime => match value.parse::<u64>() {
+                Ok(minutes) if (1..=MAX_JURY_MINUTES).contains(&minutes) => {
+                    settings.jury_time = Duration::from_secs(minutes * 60)
+                }
+                _ => {
+                    return invalid(&format!(
+                        "a number of minutes from 1 to {}",
+                        MAX_JURY_MINUTES
+                    ))
+                }
+            },
+            Self::Reaction => {
+                settings.reaction = match value {
+                    "off" => None,
+                    // Emoji, possibly made of several code points, but no text
+                    emoji
+                        if emoji.chars().count() <= 8
+                            && !emoji.chars().any(|c| c.is_alphanumeric()) =>
+                    {
+                        Some(emoji.to_owned())
+                    }
+                    _ => return invalid("an emoji, or off"),
+                }
+            }
+            Self::Digest => {
+                settings.digest = match value {
+                    "off" => DigestFrequency::Off,
+                    "daily" => DigestFrequency::Daily,
+                    "weekly" => DigestFrequency::Weekly,
+                    _ => return invalid("daily, weekly or off"),
+                }
+            }
+            Self::DigestTime => match NaiveTime::parse_from_str(value, "%H:%M") {
+                Ok(time) => settings.digest_time = time,
+                Err(_) => return invalid("a time such as 20:00"),
+            },
+            Self::Timezone => match value.parse() {
+                Ok(timezone) => settings.timezone = timezone,
+                Err(_) => return invalid("a time zone such as Europe/Berlin"),
+            },
+            Self::Decay => {
+                settings.decay = match value {
+                    "off" => ScoreDecay::Off,
+                    "halflife" => ScoreDecay::HalfLife,
+                    "window" => ScoreDecay::Window,
+                    _ => return invalid("halflife, window or off"),
+                }
+            }
+            Self::DecayDays => match value.parse() {
+                Ok(days) if (1..=MAX_DECAY_DAYS).contains(&days) => settings.decay_days = days,
+                _ => return invalid(&format!("a number of days from 1 to {}", MAX_DECAY_DAYS)),
+            },
+        }
+
+        Ok(())
+    }
+
+    /// The value a button press switches the setting to, for settings with
+    /// only two values.
+    pub fn toggled(self, settings: &ChatSettings) -> Option<&'static str> {
+        match self {
+            Self::Fallback => Some(on_off(!settings.fallback_replies)),
+            Self::Nlu => Some(on_off(!settings.nlu)),
+            Self::Jury => Some(on_off(!settings.jury)),
+            Self::ReplyStyle => Some(match settings.reply_style {
+                ReplyStyle::Quote => "plain",
+                ReplyStyle::Plain => "quote",
+            }),
+            Self::Cooldown
+            | Self::Language
+            | Self::DisputeVotes
+            | Self::JuryVotes
+            | Self::JuryTime
+            | Self::Reaction
+            | Self::DeleteConfirmations
+            | Self::DeleteCommands
+            | Self::DeleteReplies
+            | Self::Digest
+            | Self::DigestTime
+            | Self::Timezone
+            | Self::Decay
+            | Self::DecayDays => None,
+        }
+    }
+}
+
+fn on_off(enabled: bool) -> &'static str {
+    if enabled {
+        "on"
+    } else {
+        "off"
+    }
+}
+
+/// Settings of `chat_id`, loaded from the database when they haven't been
+/// read in the last [`CACHE_TTL`].
+pub async fn get(chat_id: ChatId) -> Result<ChatSettings> {
+    if let Some((read_at, settings)) = CACHE.lock().unwrap().get(&chat_id) {
+        if read_at.elapsed() < CACHE_TTL {
+            return Ok(settings.clone());
+        }
+    }
+
+    let mut settings = ChatSettings::default();
+    for changed in SettingsRepo::new(db()).changed(chat_id).await? {
+        let applied = match Setting::from_name(&changed.name) {
+            Some(setting) => setting.apply(&mut settings, &changed.value),
+            None => Err(format!("unknown setting {}", changed.name)),
+        };
+        if let Err(err) = applied {
+            warn!("Ignoring stored setting in chat {}: {}", chat_id, err);
+        }
+    }
+
+    CACHE
+        .lock()
+        .unwrap()
+        .insert(chat_id, (Instant::now(), settings.clone()));
+
+    Ok(settings)
+}
+
+/// Changes a setting of `chat_id`, returning the updated settings. Invalid
+/// values are rejected with a [`BotError::User`] saying what is expected.
+pub async fn set(chat_id: ChatId, setting: Setting, value: &str) -> HandlerResult<ChatSettings> {
+    let mut settings = get(chat_id).await?;
+    setting
+        .apply(&mut settings, value)
+        .map_err(BotError::User)?;
+
+    SettingsRepo::new(db())
+        .set(chat_id, setting.name(), &setting.value(&settings))
+        .await?;
+    CACHE
+        .lock()
+        .unwrap()
+        .insert(chat_id, (Instant::now(), settings.clone()));
+
+    Ok(settings)
+}
+
+/// Lists every setting with its value.
+pub fn render(settings: &ChatSettings) -> String {
+    let mut text = String::from("Settings for this chat:");
+    for setting in Setting::ALL {
+        text.push_str(&format!(
+            "\n{}: {} — {}",
+            setting.name(),
+            setting.value(settings),
+            setting.description()
+        ));
+    }
+    text.push_str("\n\nChange one with /settings <name> <value>");
+    text
+}
+
+/// A button for each setting with two values, switching it to the other.
+pub fn keyboard(settings: &ChatSettings) -> InlineKeyboardMarkup {
+    InlineKeyboardMarkup::new(Setting::ALL.into_iter().filter_map(|setting| {
+        let toggled = setting.toggled(settings)?;
+        Some(vec![InlineKeyboardButton::callback(
+            format!("Switch {} to {}", setting.name(), toggled),
+            format!("{}{}", CALLBACK_PREFIX, setting.name()),
+        )])
+    }))
+}
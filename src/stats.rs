@@ -0,0 +1,283 @@
+//! A member's Ls in a chat: their standing, who gives them Ls and how that
+//! changed lately.
+
+use chrono::{NaiveDate, TimeZone, Utc};
+use chrono_tz::Tz;
+use miette::Result;
+use std::{
+    cmp::{Ordering, Reverse},
+    collections::HashMap,
+};
+use teloxide::{
+    types::{ChatId, UserId},
+    utils::markdown,
+};
+
+use crate::{
+    award_types,
+    badges::{self, Badge},
+    common::text::ls,
+    db::{
+        models::ReceivedAward,
+        pool::db,
+        repo::{BadgeRepo, StatsRepo},
+    },
+    decay,
+    settings::ChatSettings,
+};
+
+/// How many days the trend covers
+pub const TREND_DAYS: usize = 30;
+
+/// Bars of the trend, from fewest Ls to most
+const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// A member's Ls in a chat.
+#[derive(Debug)]
+pub struct Profile {
+    /// Ls on the scoreboard
+    pub ls: i64,
+    /// Place on the scoreboard, from 1, if they have any Ls
+    pub rank: Option<usize>,
+    pub given: i64,
+    pub received: i64,
+    pub top_giver: Option<TopGiver>,
+    /// Most days in a row they received Ls on, in the chat's time zone
+    pub longest_streak: usize,
+    pub first: Option<ReceivedAward>,
+    pub latest: Option<ReceivedAward>,
+    /// Ls received on each of the last [`TREND_DAYS`] days, oldest first
+    pub trend: Vec<i64>,
+    /// Ls received in the [`TREND_DAYS`] days before those
+    pub previous_period: i64,
+    pub badges: Vec<&'static Badge>,
+}
+
+/// Whoever gave a member the most Ls.
+#[derive(Debug)]
+pub struct TopGiver {
+    pub first_name: Option<String>,
+    pub ls: i64,
+}
+
+/// Gathers the Ls of `member_id` in `chat_id` as of `now`, in Unix seconds.
+/// Days are counted in the chat's time zone, and the scoreboard decays as
+/// its `settings` say.
+pub async fn collect(
+    chat_id: ChatId,
+    member_id: UserId,
+    settings: &ChatSettings,
+    now: i64,
+) -> Result<Profile> {
+    let timezone = settings.timezone;
+    let stats = StatsRepo::new(db());
+    let received = stats.received(chat_id, member_id).await?;
+
+    let scoreboard = decay::scoreboard(chat_id, settings, award_types::L).await?;
+    let member_id_str = member_id.to_string();
+    let ls = scoreboard
+        .iter()
+        .find(|stat| stat.tg_user_id == member_id_str)
+        .map_or(0, |stat| i64::from(stat.amount));
+    let rank = (ls > 0).then(|| {
+        scoreboard
+            .iter()
+            .filter(|stat| i64::from(stat.amount) > ls)
+            .count()
+            + 1
+    });
+
+    let times = received
+        .iter()
+        .map(|award| award.created_at)
+        .collect::<Vec<_>>();
+    let (trend, previous_period) = trend(&times, timezone, now);
+
+    Ok(Profile {
+        ls,
+        rank,
+        given: stats.given(chat_id, member_id).await?,
+        received: received.len() as i64,
+        top_giver: top_giver(&received),
+        longest_streak: longest_streak(&times, timezone),
+        first: received.first().cloned(),
+        latest: received.last().cloned(),
+        trend,
+        previous_period,
+        badges: badges::definitions(&BadgeRepo::new(db()).earned(chat_id, member_id).await?),
+    })
+}
+
+fn local_date(time: i64, timezone: Tz) -> Option<NaiveDate> {
+    Some(
+        Utc.timestamp_opt(time, 0)
+            .single()?
+            .with_timezone(&timezone)
+            .date_naive(),
+    )
+}
+
+/// Finds who gave the most of the Ls in `received`. Ties go to whoever gave
+/// one first.
+fn top_giver(received: &[ReceivedAward]) -> Option<TopGiver> {
+    let mut counts = HashMap::<i64, (i64, usize)>::new();
+    for (i, award) in received.iter().enumerate() {
+        counts.entry(award.giver_id).or_insert((0, i)).0 += 1;
+    }
+
+    let (ls, first) = counts
+        .into_values()
+        .max_by_key(|(ls, first)| (*ls, Reverse(*first)))?;
+    Some(TopGiver {
+        first_name: received[first].giver_first_name.clone(),
+        ls,
+    })
+}
+
+/// Most days in a row with an L at one of `times`, in Unix seconds and in
+/// order.
+pub fn longest_streak(times: &[i64], timezone: Tz) -> usize {
+    let mut days = times
+        .iter()
+        .filter_map(|time| local_date(*time, timezone))
+        .collect::<Vec<_>>();
+    days.dedup();
+
+    let mut longest = 0;
+    let mut current = 0;
+    let mut previous: Option<NaiveDate> = None;
+    for day in days {
+        current = match previous {
+            Some(previous) if previous.succ_opt() == Some(day) => current + 1,
+            _ => 1,
+        };
+        longest = longest.max(current);
+        previous = Some(day);
+    }
+
+    longest
+}
+
+/// Counts the Ls at `times` on each of the last [`TREND_DAYS`] days up to
+/// `now`, oldest first, along with how many there were in as many days
+/// before that.
+pub fn trend(times: &[i64], timezone: Tz, now: i64) -> (Vec<i64>, i64) {
+    let mut trend = vec![0; TREND_DAYS];
+    let mut previous_period = 0;
+    let Some(today) = local_date(now, timezone) else {
+        return (trend, previous_period);
+    };
+
+    for day in times.iter().filter_map(|time| local_date(*time, timezone)) {
+        let Ok(ago) = usize::try_from((today - day).num_days()) else {
+            continue;
+        };
+        if ago < TREND_DAYS {
+            trend[TREND_DAYS - 1 - ago] += 1;
+        } else if ago < 2 * TREND_DAYS {
+            previous_period += 1;
+        }
+    }
+
+    (trend, previous_period)
+}
+
+/// Draws `counts` as a line of bars, days without Ls at the bottom.
+pub fn sparkline(counts: &[i64]) -> String {
+    let max = counts.iter().copied().max().unwrap_or(0);
+    counts
+        .iter()
+        .map(|count| {
+            if max == 0 || *count <= 0 {
+                BARS[0]
+            } else {
+                // Days with any Ls are drawn above those without
+                let level = (count * (BARS.len() as i64 - 1) + max - 1) / max;
+                BARS[level as usize]
+            }
+        })
+        .collect()
+}
+
+fn award_line(label: &str, award: &ReceivedAward, timezone: Tz) -> String {
+    let date = local_date(award.created_at, timezone)
+        .map(|date| date.format("%Y-%m-%d").to_string())
+        .unwrap_or_default();
+    let mut line = format!(
+        "{}: {} from __{}__",
+        label,
+        markdown::escape(&date),
+        markdown::escape(award.giver_first_name.as_deref().unwrap_or("(left)"))
+    );
+    if let Some(reason) = &award.reason {
+        line.push_str(&format!(" “{}”", markdown::escape(reason)));
+    }
+
+    line
+}
+
+/// Renders the profile of the member called `name` as MarkdownV2, with
+/// dates in `timezone`.
+pub fn render(name: &str, profile: &Profile, timezone: Tz) -> String {
+    let mut lines = vec![
+        format!("*L stats for __{}__*", markdown::escape(name)),
+        String::new(),
+    ];
+
+    lines.push(match profile.rank {
+        Some(rank) => format!("Total: *{}*, \\#{} in this chat", ls(profile.ls), rank),
+        None => String::from("Total: *0 Ls*"),
+    });
+    lines.push(format!(
+        "Given: {} · Received: {}",
+        profile.given, profile.received
+    ));
+    if !profile.badges.is_empty() {
+        let badges = profile
+            .badges
+            .iter()
+            .map(|badge| format!("{} {}", badge.emoji, markdown::escape(badge.name)))
+            .collect::<Vec<_>>();
+        lines.push(format!("Badges: {}", badges.join(", ")));
+    }
+    if let Some(giver) = &profile.top_giver {
+        lines.push(format!(
+            "Top giver: __{}__ with {}",
+            markdown::escape(giver.first_name.as_deref().unwrap_or("(left)")),
+            ls(giver.ls)
+        ));
+    }
+    if profile.longest_streak > 0 {
+        lines.push(format!(
+            "Longest streak: {} {} in a row",
+            profile.longest_streak,
+            if profile.longest_streak == 1 {
+                "day"
+            } else {
+                "days"
+            }
+        ));
+    }
+    if let Some(first) = &profile.first {
+        lines.push(award_line("First L", first, timezone));
+    }
+    if let Some(latest) = profile.latest.as_ref().filter(|_| profile.received > 1) {
+        lines.push(award_line("Latest L", latest, timezone));
+    }
+
+    let recent = profile.trend.iter().sum::<i64>();
+    let change = match recent.cmp(&profile.previous_period) {
+        Ordering::Greater => format!("up from {}", profile.previous_period),
+        Ordering::Less => format!("down from {}", profile.previous_period),
+        Ordering::Equal => String::from("same as before"),
+    };
+    lines.push(format!(
+        "Last {} days: {} \\({}, {}\\)",
+        TREND_DAYS,
+        sparkline(&profile.trend),
+        ls(recent),
+        change
+    ));
+
+    lines.join("\n")
+}
@@ -0,0 +1,110 @@
+//! Which kinds of updates Telegram sends the bot.
+//!
+//! teloxide only knows the update kinds of the Bot API version it was built
+//! for, so it can't ask for newer ones like message reactions. The list is
+//! sent with payloads of our own instead, and kept from being replaced by the
+//! one teloxide derives from the handler tree.
+
+use miette::{IntoDiagnostic, Result};
+use serde::Serialize;
+use std::time::Duration;
+use teloxide::{
+    dispatching::update_listeners::{AsUpdateStream, UpdateListener},
+    prelude::*,
+    requests::{JsonRequest, Payload},
+    stop::StopToken,
+    types::True,
+};
+
+/// Update kinds the bot handles
+pub const ALLOWED_UPDATES: &[&str] = &[
+    "message",
+    "callback_query",
+    "message_reaction",
+    "poll_answer",
+    "inline_query",
+];
+
+#[derive(Serialize)]
+struct GetUpdates {
+    limit: u8,
+    timeout: u32,
+    allowed_updates: &'static [&'static str],
+}
+
+impl Payload for GetUpdates {
+    type Output = Vec<serde_json::Value>;
+
+    const NAME: &'static str = "GetUpdates";
+}
+
+#[derive(Serialize)]
+struct SetWebhook {
+    url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    secret_token: Option<String>,
+    allowed_updates: &'static [&'static str],
+}
+
+impl Payload for SetWebhook {
+    type Output = True;
+
+    const NAME: &'static str = "SetWebhook";
+}
+
+/// Asks for [`ALLOWED_UPDATES`] when polling. Telegram keeps using the list
+/// for later `getUpdates` calls that don't send one of their own.
+pub async fn subscribe(bot: &Bot) -> Result<()> {
+    // Without an offset, the update this returns isn't confirmed, so it is
+    // still delivered to the dispatcher
+    let payload = GetUpdates {
+        limit: 1,
+        timeout: 0,
+        allowed_updates: ALLOWED_UPDATES,
+    };
+    JsonRequest::new(bot.clone(), payload)
+        .await
+        .into_diagnostic()?;
+
+    Ok(())
+}
+
+/// Tells Telegram to send [`ALLOWED_UPDATES`] to `url`, along with
+/// `secret_token`.
+pub async fn set_webhook(bot: &Bot, url: &str, secret_token: Option<&str>) -> Result<()> {
+    let payload = SetWebhook {
+        url: url.to_owned(),
+        secret_token: secret_token.map(str::to_owned),
+        allowed_updates: ALLOWED_UPDATES,
+    };
+    JsonRequest::new(bot.clone(), payload)
+        .await
+        .into_diagnostic()?;
+
+    Ok(())
+}
+
+/// An update listener that ignores the update kinds teloxide hints at, so
+/// the ones asked for with [`subscribe`] stay in effect.
+pub struct KeepAllowedUpdates<L>(pub L);
+
+impl<L: UpdateListener> UpdateListener for KeepAllowedUpdates<L> {
+    type Err = L::Err;
+
+    fn stop_token(&mut self) -> StopToken {
+        self.0.stop_token()
+    }
+
+    fn timeout_hint(&self) -> Option<Duration> {
+        self.0.timeout_hint()
+    }
+}
+
+impl<'a, L: AsUpdateStream<'a>> AsUpdateStream<'a> for KeepAllowedUpdates<L> {
+    type StreamErr = L::StreamErr;
+    type Stream = L::Stream;
+
+    fn as_stream(&'a mut self) -> Self::Stream {
+        self.0.as_stream()
+    }
+}
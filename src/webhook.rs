@@ -0,0 +1,65 @@
+use axum::Router;
+use miette::{IntoDiagnostic, Result};
+use std::{convert::Infallible, future::Future, net::SocketAddr};
+use teloxide::{
+    dispatching::update_listeners::{
+        webhooks::{self, Options},
+        UpdateListener,
+    },
+    prelude::*,
+};
+use tracing::info;
+
+use crate::{config::WebhookConfig, updates};
+
+/// Builds teloxide webhook options from configuration, generating a secret
+/// token when none was configured.
+pub fn options(config: &WebhookConfig) -> Options {
+    let mut options = Options::new(config.address, config.url.clone());
+    if let Some(secret_token) = &config.secret_token {
+        options = options.secret_token(secret_token.clone());
+    }
+    options.get_or_gen_secret_token();
+    options
+}
+
+/// Creates an update listener fed by an HTTP app. Requests to the webhook path
+/// without the expected `X-Telegram-Bot-Api-Secret-Token` header are rejected.
+///
+/// The returned future resolves once the listener has been stopped, and is
+/// meant to be used as the shutdown signal for [`serve`].
+pub fn listener(
+    options: Options,
+) -> (
+    impl UpdateListener<Err = Infallible>,
+    impl Future<Output = ()>,
+    Router,
+) {
+    webhooks::axum_no_setup(options)
+}
+
+/// Tells Telegram where to send updates, and which secret token to send them
+/// with.
+pub async fn set_webhook(bot: &Bot, options: &Options) -> Result<()> {
+    updates::set_webhook(bot, options.url.as_str(), options.secret_token.as_deref()).await?;
+
+    info!("Webhook set to {}", options.url);
+
+    Ok(())
+}
+
+/// Serves `router` on `address` until `shutdown` resolves.
+pub async fn serve(
+    router: Router,
+    address: SocketAddr,
+    shutdown: impl Future<Output = ()>,
+) -> Result<()> {
+    info!("HTTP server listening on {}", address);
+
+    axum::Server::try_bind(&address)
+        .into_diagnostic()?
+        .serve(router.into_make_service())
+        .with_graceful_shutdown(shutdown)
+        .await
+        .into_diagnostic()
+}
@@ -0,0 +1,183 @@
+mod common;
+
+use common::{Harness, ALICE, BOB, CAROL, OWNER};
+use gustyfring::{
+    award_types::{self, NetScore},
+    db::models::{AwardType, TypedMemberStat},
+};
+use teloxide::types::ChatId;
+
+#[tokio::test]
+async fn ws_and_ls_add_up_to_a_net_score() {
+    let harness = Harness::new().await;
+    let bobs_message = harness.message(&BOB, "i forgot the cake");
+    let carols_message = harness.message(&CAROL, "i brought two cakes");
+    for giver in [&ALICE, &CAROL] {
+        harness
+            .send(harness.reply(giver, "/givel", &bobs_message))
+            .await;
+    }
+    harness
+        .send(harness.reply(&ALICE, "/givew", &carols_message))
+        .await;
+    assert!(harness
+        .sent_messages()
+        .contains(&String::from("W has been awarded")));
+
+    harness
+        .send(harness.message(&ALICE, "/viewscoreboard"))
+        .await;
+    assert_eq!(
+        harness.last_message(),
+        "__Carol__ — 🤡 *0* · 👑 *1* · net *1*\n__Bob__ 🥉 — 🤡 *2* · 👑 *0* · net *\\-2*"
+    );
+    harness
+        .send(harness.message(&ALICE, "/viewscoreboard w"))
+        .await;
+    assert_eq!(harness.last_message(), "__Carol__ — *1* Ws");
+    harness
+        .send(harness.message(&ALICE, "/viewscoreboard l"))
+        .await;
+    assert_eq!(harness.last_message(), "__Bob__ 🥉 — *2* Ls");
+    harness
+        .send(harness.message(&ALICE, "/viewscoreboard cakes"))
+        .await;
+    assert_eq!(
+        harness.last_message(),
+        "There is no award type called cakes, see /awardtypes"
+    );
+}
+
+#[tokio::test]
+async fn chat_admins_can_add_and_change_award_types() {
+    let harness = Harness::new().await;
+    harness.promote(&OWNER);
+    harness.send(harness.message(&ALICE, "/awardtypes")).await;
+    let listed = harness.last_message();
+    assert!(listed.starts_with("*Award types*"));
+    assert!(listed.contains("👑 W: /give w, \\+1 to the net score each"));
+
+    harness
+        .send(harness.message(&ALICE, "/awardtypes set k 🍪 2 Cookie"))
+        .await;
+    assert_eq!(
+        harness.last_message(),
+        "Only chat admins can change award types"
+    );
+    harness
+        .send(harness.message(&OWNER, "/awardtypes set k 🍪 2 Cookie"))
+        .await;
+    assert_eq!(
+        harness.last_message(),
+        "🍪 Cookies can be given with /give k"
+    );
+
+    let bobs_message = harness.message(&BOB, "i baked for everyone");
+    harness
+        .send(harness.reply(&ALICE, "/give k they were great", &bobs_message))
+        .await;
+    assert!(harness
+        .sent_messages()
+        .contains(&String::from("Cookie has been awarded")));
+    harness
+        .send(harness.message(&ALICE, "/viewscoreboard"))
+        .await;
+    assert_eq!(harness.last_message(), "__Bob__ — *1* Cookies");
+
+    harness
+        .send(harness.reply(&ALICE, "/give", &bobs_message))
+        .await;
+    assert!(harness.last_message().starts_with("Use /give <type>"));
+
+    harness
+        .send(harness.message(&OWNER, "/awardtypes remove w"))
+        .await;
+    assert_eq!(
+        harness.last_message(),
+        "Ws are built in, so they can only be changed"
+    );
+    harness
+        .send(harness.message(&OWNER, "/awardtypes set w 🌟 3 Win"))
+        .await;
+    harness
+        .send(harness.message(&OWNER, "/awardtypes remove w"))
+        .await;
+    assert_eq!(harness.last_message(), "Ws are back to how they were");
+    harness
+        .send(harness.message(&OWNER, "/awardtypes remove k"))
+        .await;
+    assert_eq!(harness.last_message(), "Cookies can't be given anymore");
+    harness
+        .send(harness.reply(&CAROL, "/give k", &bobs_message))
+        .await;
+    assert_eq!(
+        harness.last_message(),
+        "There is no award type called k, see /awardtypes"
+    );
+}
+
+#[test]
+fn net_scores_weigh_each_type() {
+    let [l, w] = award_types::built_in(ChatId(1));
+    let score = |tg_user_id: &str, award_type: &str, amount| TypedMemberStat {
+        tg_user_id: tg_user_id.to_owned(),
+        first_name: None,
+        award_type: award_type.to_owned(),
+        amount,
+    };
+    let scores = [
+        score("1", "l", 3),
+        score("1", "w", 1),
+        score("2", "w", 2),
+        score("2", "removed", 5),
+    ];
+
+    let net = |tg_user_id: &str, amounts: Vec<i64>, net| NetScore {
+        tg_user_id: tg_user_id.to_owned(),
+        first_name: None,
+        amounts,
+        net,
+    };
+    assert_eq!(
+        award_types::net_scores(&[l, w], &scores),
+        [net("2", vec![0, 2], 2), net("1", vec![3, 1], -2)]
+    );
+}
+
+#[test]
+fn award_types_are_named_with_the_right_article() {
+    let named = |name: &str| AwardType {
+        chat_id: 1,
+        code: String::from("x"),
+        name: name.to_owned(),
+        emoji: String::from("✨"),
+        weight: 1,
+    };
+
+    assert_eq!(named("L").one(), "an L");
+    assert_eq!(named("W").one(), "a W");
+    assert_eq!(named("Oof").one(), "an Oof");
+    assert_eq!(named("Cookie").one(), "a Cookie");
+    assert_eq!(named("Cookie").count(3), "3 Cookies");
+    assert_eq!(named("Cookie").count(1), "1 Cookie");
+}
+
+#[test]
+fn award_types_are_checked_before_they_are_added() {
+    let chat = ChatId(1);
+    let added = award_types::parse(chat, "K 🍪 -2 Bad cookie").unwrap();
+    assert_eq!(
+        (added.code.as_str(), added.name.as_str(), added.weight),
+        ("k", "Bad cookie", -2)
+    );
+
+    for args in [
+        "k 🍪 2",
+        "k-k 🍪 2 Cookie",
+        "k cookie 2 Cookie",
+        "k 🍪 two Cookie",
+        "k 🍪 1000 Cookie",
+    ] {
+        assert!(award_types::parse(chat, args).is_err(), "{}", args);
+    }
+}
@@ -0,0 +1,168 @@
+mod common;
+
+use common::{Harness, DAVE, OWNER};
+use gustyfring::{
+    award_types::L,
+    db::{
+        backup,
+        pool::{self, Pool},
+        repo::{AwardRepo, MemberRepo},
+    },
+};
+use std::{
+    fs,
+    path::Path,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use teloxide::types::{ChatId, UserId};
+use tempfile::TempDir;
+
+const CHAT: ChatId = ChatId(-100);
+const ALICE: UserId = UserId(1);
+const BOB: UserId = UserId(2);
+const DAY: u64 = 24 * 60 * 60;
+
+/// An empty directory of its own, removed when dropped.
+fn temp_dir() -> TempDir {
+    tempfile::tempdir().unwrap()
+}
+
+async fn open(path: &Path) -> Pool {
+    let pool = pool::connect(&format!("sqlite://{}?mode=rwc", path.display()))
+        .await
+        .unwrap();
+    pool::migrate_pool(&pool).await.unwrap();
+    pool
+}
+
+async fn score(pool: &Pool) -> i32 {
+    AwardRepo::new(pool).scoreboard(CHAT).await.unwrap()[0].amount
+}
+
+#[tokio::test]
+async fn restore_brings_back_a_backup() {
+    let dir = temp_dir();
+    let db_path = dir.path().join("db.sqlite3");
+
+    let pool = open(&db_path).await;
+    AwardRepo::new(&pool)
+        .award(CHAT, L, BOB, ALICE, None)
+        .await
+        .unwrap();
+    let backup_path = backup::backup(&pool, &dir.path().join("backups"))
+        .await
+        .unwrap();
+    AwardRepo::new(&pool)
+        .award(CHAT, L, BOB, ALICE, None)
+        .await
+        .unwrap();
+    assert_eq!(score(&pool).await, 2);
+    pool.close().await;
+
+    let previous = backup::restore(&backup_path, &db_path).await.unwrap();
+
+    let pool = open(&db_path).await;
+    assert_eq!(score(&pool).await, 1);
+    pool.close().await;
+    let previous = previous.unwrap();
+    assert_eq!(score(&open(&previous).await).await, 2);
+
+    // Restoring again would replace the database kept from before
+    assert!(backup::restore(&backup_path, &db_path).await.is_err());
+    assert_eq!(score(&open(&previous).await).await, 2);
+}
+
+#[tokio::test]
+async fn backups_taken_in_the_same_second_are_all_kept() {
+    let dir = temp_dir();
+    let pool = open(&dir.path().join("db.sqlite3")).await;
+    let backups = dir.path().join("backups");
+
+    let mut paths = Vec::new();
+    for _ in 0..3 {
+        paths.push(backup::backup(&pool, &backups).await.unwrap());
+    }
+    pool.close().await;
+    assert!(paths[0] != paths[1] && paths[1] != paths[2] && paths[0] != paths[2]);
+
+    // The last one taken counts as the newest
+    assert_eq!(
+        backup::rotate(&backups, 1, Duration::from_secs(DAY)).unwrap(),
+        2
+    );
+    assert!(paths[2].exists());
+}
+
+#[tokio::test]
+async fn restore_rejects_damaged_files() {
+    let dir = temp_dir();
+    let db_path = dir.path().join("db.sqlite3");
+    let damaged = dir.path().join("damaged.sqlite3");
+    fs::write(&damaged, "definitely not a database").unwrap();
+
+    let pool = open(&db_path).await;
+    AwardRepo::new(&pool)
+        .award(CHAT, L, BOB, ALICE, None)
+        .await
+        .unwrap();
+    pool.close().await;
+
+    assert!(backup::restore(&damaged, &db_path).await.is_err());
+    assert_eq!(score(&open(&db_path).await).await, 1);
+}
+
+#[tokio::test]
+async fn rotate_removes_old_and_excess_backups() {
+    let dir = temp_dir();
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    let backup = |days_ago: u64| {
+        dir.path()
+            .join(format!("db-{}.sqlite3", now - days_ago * DAY))
+    };
+    for days_ago in 0..5 {
+        fs::write(backup(days_ago), "").unwrap();
+    }
+    fs::write(dir.path().join("notes.txt"), "").unwrap();
+
+    assert_eq!(
+        backup::rotate(dir.path(), 3, Duration::from_secs(30 * DAY)).unwrap(),
+        2
+    );
+    assert!(!backup(3).exists() && !backup(4).exists());
+
+    assert_eq!(
+        backup::rotate(dir.path(), 3, Duration::from_secs(DAY + DAY / 2)).unwrap(),
+        1
+    );
+    assert!(backup(0).exists() && backup(1).exists() && !backup(2).exists());
+
+    // The newest backup is kept however old it is
+    assert_eq!(backup::rotate(dir.path(), 0, Duration::ZERO).unwrap(), 1);
+    assert!(backup(0).exists());
+    assert!(dir.path().join("notes.txt").exists());
+}
+
+// The only test here to use the harness, whose database has to be a file
+#[tokio::test]
+async fn backup_is_sent_to_bot_owners_privately() {
+    let dir = temp_dir();
+    let harness = Harness::with_file_db(&dir).await;
+    MemberRepo::new(pool::db())
+        .grant_admin(UserId(DAVE.id))
+        .await
+        .unwrap();
+
+    // Granted with `grant-admin`, or from the configuration
+    for owner in [&DAVE, &OWNER] {
+        harness.send(harness.message(owner, "/backup")).await;
+        assert_eq!(harness.last_message(), "Backup sent to you privately");
+    }
+
+    let documents = harness.requests("senddocument");
+    assert_eq!(documents.len(), 2);
+    assert_eq!(documents[0].body["chat_id"], DAVE.id);
+    assert_eq!(documents[1].body["chat_id"], OWNER.id);
+}
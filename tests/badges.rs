@@ -0,0 +1,140 @@
+mod common;
+
+use common::{Harness, ALICE, BOB, CAROL, OWNER};
+use gustyfring::{badges, db::models::EarnedBadge, db::pool::db};
+
+const DAY: i64 = 24 * 60 * 60;
+
+fn announcements(harness: &Harness) -> Vec<String> {
+    harness
+        .sent_messages()
+        .into_iter()
+        .filter(|text| text.contains(" earned "))
+        .collect()
+}
+
+#[tokio::test]
+async fn badges_are_announced_once_when_earned() {
+    let harness = Harness::new().await;
+    harness.join(&BOB);
+    let bobs_message = harness.message(&BOB, "i left the oven on");
+    for _ in 0..10 {
+        harness
+            .send(harness.reply(&ALICE, "/givel", &bobs_message))
+            .await;
+    }
+    assert_eq!(
+        announcements(&harness),
+        [
+            "__Bob__ earned a badge: 🥉 *First L*",
+            "__Bob__ earned a badge: 🔟 *10 Ls*",
+        ]
+    );
+
+    harness
+        .send(harness.message(&ALICE, "/viewscoreboard"))
+        .await;
+    assert_eq!(harness.last_message(), "__Bob__ 🥉🔟 — *10* Ls");
+    harness.send(harness.message(&BOB, "/mystats")).await;
+    assert!(harness
+        .last_message()
+        .contains("Badges: 🥉 First L, 🔟 10 Ls"));
+}
+
+#[tokio::test]
+async fn givers_earn_badges_too() {
+    let harness = Harness::new().await;
+    let bobs_message = harness.message(&BOB, "i reply all to everything");
+    for _ in 0..50 {
+        harness
+            .send(harness.reply(&ALICE, "/givel", &bobs_message))
+            .await;
+    }
+
+    assert!(announcements(&harness)
+        .contains(&String::from("__Alice__ earned a badge: 🎁 *Gave 50 Ls*")));
+}
+
+#[tokio::test]
+async fn receiving_ls_seven_days_in_a_row_earns_a_streak() {
+    let harness = Harness::new().await;
+    let bobs_message = harness.message(&BOB, "i still use internet explorer");
+    for _ in 0..6 {
+        harness
+            .send(harness.reply(&ALICE, "/givel", &bobs_message))
+            .await;
+    }
+    // Spread those Ls over the six days before today
+    let ids = sqlx::query_scalar::<_, i64>("SELECT id FROM Award WHERE chat_id = $1 ORDER BY id")
+        .bind(harness.chat_id)
+        .fetch_all(db())
+        .await
+        .unwrap();
+    for (days_ago, id) in (1..=6).rev().zip(ids) {
+        sqlx::query("UPDATE Award SET created_at = created_at - $1 WHERE id = $2")
+            .bind(days_ago * DAY)
+            .bind(id)
+            .execute(db())
+            .await
+            .unwrap();
+    }
+    assert!(!announcements(&harness)
+        .iter()
+        .any(|text| text.contains("streak")));
+
+    harness
+        .send(harness.reply(&CAROL, "/givel", &bobs_message))
+        .await;
+    assert_eq!(
+        harness.last_message(),
+        "__Bob__ earned a badge: 🔥 *L streak 7 days*"
+    );
+}
+
+#[tokio::test]
+async fn resetting_the_scoreboard_crowns_the_season_champion() {
+    let harness = Harness::new().await;
+    harness.promote(&OWNER);
+    let bobs_message = harness.message(&BOB, "tabs over spaces");
+    let carols_message = harness.message(&CAROL, "spaces over tabs");
+    for (giver, message) in [
+        (&ALICE, &bobs_message),
+        (&CAROL, &bobs_message),
+        (&ALICE, &carols_message),
+    ] {
+        harness.send(harness.reply(giver, "/givel", message)).await;
+    }
+
+    harness.send(harness.message(&OWNER, "/resetscores")).await;
+    let sent = harness.sent_messages();
+    assert_eq!(
+        sent[sent.len() - 2..],
+        [
+            "__Bob__ earned a badge: 🏆 *Season champion*",
+            "Scoreboard has been reset",
+        ]
+    );
+
+    // Badges outlast the season
+    harness.send(harness.message(&BOB, "/mystats")).await;
+    assert!(harness
+        .last_message()
+        .contains("Badges: 🥉 First L, 🏆 Season champion"));
+}
+
+#[test]
+fn badges_are_listed_in_definition_order() {
+    let earned = ["season-champion", "retired-badge", "first-l"].map(|badge| EarnedBadge {
+        chat_id: 1,
+        member_id: 1,
+        badge: badge.to_owned(),
+        granted_at: 0,
+    });
+
+    let names = badges::definitions(&earned)
+        .iter()
+        .map(|badge| badge.name)
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["First L", "Season champion"]);
+    assert_eq!(badges::emojis(&earned), "🥉🏆");
+}
@@ -0,0 +1,128 @@
+mod common;
+
+use common::{Harness, ALICE, BOB, CAROL, DAVE, OWNER};
+use serde_json::json;
+
+#[tokio::test]
+async fn givel_awards_everyone_mentioned() {
+    let harness = Harness::new().await;
+    for member in [&BOB, &CAROL] {
+        harness.send(harness.message(member, "hi")).await;
+    }
+
+    let mut givel = harness.message(&ALICE, "/givel @bob Dave @carol for the meeting @bob");
+    givel["entities"] = json!([{
+        "type": "text_mention",
+        "offset": 12,
+        "length": 4,
+        "user": { "id": DAVE.id, "is_bot": false, "first_name": DAVE.first_name },
+    }]);
+    harness.send(givel).await;
+    assert!(harness
+        .sent_messages()
+        .contains(&String::from("Ls have been awarded to Bob, Dave and Carol")));
+
+    harness
+        .send(harness.message(&ALICE, "/viewscoreboard"))
+        .await;
+    let scoreboard = harness.last_message();
+    for name in ["Bob", "Carol", "Dave"] {
+        assert!(scoreboard.contains(&format!("__{}__ 🥉 — *1* Ls", name)));
+    }
+    harness.send(harness.message(&ALICE, "/stats @bob")).await;
+    assert!(harness.last_message().contains("“for the meeting @bob”"));
+
+    // Mentioning someone works for a single member too, and for any type
+    harness.send(harness.message(&CAROL, "/give w @bob")).await;
+    assert_eq!(
+        harness.sent_messages().last().unwrap(),
+        "W has been awarded"
+    );
+}
+
+#[tokio::test]
+async fn bulk_awards_are_all_or_nothing() {
+    let harness = Harness::new().await;
+    harness.promote(&OWNER);
+    for member in [&BOB, &CAROL] {
+        harness.send(harness.message(member, "hi")).await;
+    }
+
+    harness
+        .send(harness.message(&BOB, "/givel @carol @bob"))
+        .await;
+    assert_eq!(harness.last_message(), "You can't give yourself an L");
+    harness
+        .send(harness.message(&BOB, "/givel @carol @nobody"))
+        .await;
+    assert_eq!(harness.last_message(), "I haven't seen @nobody yet");
+    harness
+        .send(harness.message(&ALICE, "/viewscoreboard"))
+        .await;
+    assert_eq!(harness.last_message(), "Scoreboard is empty\\!");
+
+    harness
+        .send(harness.message(&OWNER, "/settings cooldown 60"))
+        .await;
+    harness
+        .send(harness.message(&ALICE, "/givel @bob @carol"))
+        .await;
+    harness
+        .send(harness.message(&ALICE, "/givel @bob @carol"))
+        .await;
+    assert!(harness
+        .last_message()
+        .starts_with("You can give another L in "));
+
+    harness
+        .send(harness.message(&OWNER, "/settings jury on"))
+        .await;
+    harness
+        .send(harness.message(&OWNER, "/givel @bob @carol"))
+        .await;
+    assert_eq!(
+        harness.last_message(),
+        "In jury mode, Ls are given to one member at a time"
+    );
+}
+
+#[tokio::test]
+async fn givel_all_who_voted_awards_the_voters_of_a_poll() {
+    let harness = Harness::new().await;
+    harness
+        .send(harness.message(&ALICE, "/poll Who broke the build? | Bob | Carol"))
+        .await;
+    let poll_request = harness.requests("sendpoll").pop().unwrap();
+    assert_eq!(poll_request.body["is_anonymous"], false);
+    let poll = poll_request.response["result"].clone();
+
+    harness.vote(&BOB, &poll, &[1]).await;
+    harness.vote(&CAROL, &poll, &[0]).await;
+    harness.vote(&ALICE, &poll, &[0]).await;
+    harness.vote(&DAVE, &poll, &[0]).await;
+    harness.vote(&DAVE, &poll, &[]).await;
+
+    harness
+        .send(harness.reply(&ALICE, "/givel all-who-voted for voting", &poll))
+        .await;
+    assert!(harness
+        .sent_messages()
+        .contains(&String::from("Ls have been awarded to Bob and Carol")));
+    harness.send(harness.message(&ALICE, "/stats @carol")).await;
+    assert!(harness.last_message().contains("“for voting”"));
+
+    let bobs_message = harness.message(&BOB, "i voted");
+    harness
+        .send(harness.reply(&ALICE, "/givel all-who-voted", &bobs_message))
+        .await;
+    assert_eq!(
+        harness.last_message(),
+        "Reply to a poll started with /poll to award everyone who voted an L"
+    );
+
+    harness.send(harness.message(&ALICE, "/poll Lunch?")).await;
+    assert_eq!(
+        harness.last_message(),
+        "Use /poll question | answer | answer, with 2 to 10 answers"
+    );
+}
@@ -0,0 +1,154 @@
+mod common;
+
+use chrono::NaiveDate;
+use chrono_tz::Tz;
+use common::{Harness, ALICE, BOB, CAROL, OWNER};
+use flate2::read::ZlibDecoder;
+use gustyfring::{
+    chart,
+    db::{models::MemberStat, pool::db},
+};
+use std::io::Read;
+
+const DAY: i64 = 24 * 60 * 60;
+
+/// Splits a PNG into its chunks, checking the signature and every checksum.
+fn chunks(png: &[u8]) -> Vec<(String, Vec<u8>)> {
+    assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+
+    let mut chunks = Vec::new();
+    let mut rest = &png[8..];
+    while !rest.is_empty() {
+        let length = u32::from_be_bytes(rest[..4].try_into().unwrap()) as usize;
+        let (kind, data) = (&rest[4..8], &rest[8..8 + length]);
+        let crc = u32::from_be_bytes(rest[8 + length..12 + length].try_into().unwrap());
+        let mut hasher = crc32fast::Hasher::new();
+        hasher.update(kind);
+        hasher.update(data);
+        assert_eq!(hasher.finalize(), crc);
+
+        chunks.push((String::from_utf8(kind.to_vec()).unwrap(), data.to_vec()));
+        rest = &rest[12 + length..];
+    }
+
+    chunks
+}
+
+/// The width and height of a PNG, checking its pixels add up to them.
+fn size(png: &[u8]) -> (u32, u32) {
+    let chunks = chunks(png);
+    let kinds = chunks
+        .iter()
+        .map(|(kind, _)| kind.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(kinds, ["IHDR", "IDAT", "IEND"]);
+
+    let header = &chunks[0].1;
+    let width = u32::from_be_bytes(header[..4].try_into().unwrap());
+    let height = u32::from_be_bytes(header[4..8].try_into().unwrap());
+    let mut pixels = Vec::new();
+    ZlibDecoder::new(&chunks[1].1[..])
+        .read_to_end(&mut pixels)
+        .unwrap();
+    assert_eq!(pixels.len() as u32, (width * 3 + 1) * height);
+
+    (width, height)
+}
+
+fn stats(count: usize) -> Vec<MemberStat> {
+    (0..count)
+        .map(|i| MemberStat {
+            tg_user_id: i.to_string(),
+            first_name: Some(format!("Member {}", i)),
+            amount: (count - i) as i32,
+        })
+        .collect()
+}
+
+#[test]
+fn scoreboard_chart_grows_with_members_up_to_a_limit() {
+    let (_, three) = size(&chart::scoreboard(&stats(3)));
+    let (_, four) = size(&chart::scoreboard(&stats(4)));
+    assert!(four > three);
+
+    let (_, most) = size(&chart::scoreboard(&stats(chart::MAX_BARS)));
+    let (_, more) = size(&chart::scoreboard(&stats(chart::MAX_BARS + 10)));
+    let (_, many_more) = size(&chart::scoreboard(&stats(chart::MAX_BARS + 20)));
+    // Only a line saying how many more members there are is added
+    assert!(more > most);
+    assert_eq!(more, many_more);
+}
+
+#[test]
+fn running_totals_cover_every_day_in_the_chats_time_zone() {
+    let utc: Tz = "UTC".parse().unwrap();
+    let date = |day| NaiveDate::from_ymd_opt(1970, 1, day).unwrap();
+    let times = [0, 60, 2 * DAY, 2 * DAY + 5];
+    assert_eq!(
+        chart::running_totals(&times, utc, 3 * DAY),
+        [(date(1), 2), (date(2), 2), (date(3), 4), (date(4), 4)]
+    );
+    assert!(chart::running_totals(&[], utc, 3 * DAY).is_empty());
+
+    // It's already the 2nd in Tokyo
+    let tokyo: Tz = "Asia/Tokyo".parse().unwrap();
+    assert_eq!(
+        chart::running_totals(&[23 * 60 * 60], tokyo, DAY),
+        [(date(2), 1)]
+    );
+
+    let (width, height) = size(&chart::over_time(&chart::running_totals(
+        &times,
+        utc,
+        3 * DAY,
+    )));
+    assert!(width > 0 && height > 0);
+    size(&chart::over_time(&[(date(1), 1)]));
+}
+
+#[tokio::test]
+async fn chart_sends_the_scoreboard_and_ls_over_time_as_photos() {
+    let harness = Harness::new().await;
+    harness.send(harness.message(&ALICE, "/chart")).await;
+    assert_eq!(harness.last_message(), "Scoreboard is empty\\!");
+
+    let bobs_message = harness.message(&BOB, "i microwave my tea");
+    for giver in [&ALICE, &CAROL] {
+        harness
+            .send(harness.reply(giver, "/givel", &bobs_message))
+            .await;
+    }
+    harness.send(harness.message(&ALICE, "/chart")).await;
+
+    let photos = harness.requests("sendphoto");
+    assert_eq!(photos.len(), 2);
+    assert!(photos
+        .iter()
+        .all(|photo| photo.body["chat_id"] == harness.chat_id));
+}
+
+#[tokio::test]
+async fn chart_follows_the_chats_score_decay() {
+    let harness = Harness::new().await;
+    harness.promote(&OWNER);
+    let bobs_message = harness.message(&BOB, "i reply-all to everything");
+    harness
+        .send(harness.reply(&ALICE, "/givel", &bobs_message))
+        .await;
+    sqlx::query("UPDATE Award SET created_at = created_at - $1 WHERE chat_id = $2")
+        .bind(60 * DAY)
+        .bind(harness.chat_id)
+        .execute(db())
+        .await
+        .unwrap();
+
+    harness
+        .send(harness.message(&OWNER, "/settings decay window"))
+        .await;
+    harness.send(harness.message(&ALICE, "/chart")).await;
+    assert_eq!(harness.last_message(), "Scoreboard is empty\\!");
+    assert!(harness.requests("sendphoto").is_empty());
+
+    harness.send(harness.message(&BOB, "/mystats")).await;
+    assert!(harness.last_message().contains("Total: *0 Ls*"));
+}
@@ -0,0 +1,630 @@
+//! Test harness running update handlers against a fake Telegram Bot API
+//! server and an in-memory database.
+
+#![allow(dead_code)]
+
+use axum::{
+    body::Bytes,
+    extract::{Extension, Path},
+    routing::post,
+    Json, Router,
+};
+use gustyfring::{bot::schema, db::pool, permissions};
+use serde_json::{json, Value};
+use std::{
+    collections::{HashMap, HashSet},
+    net::{SocketAddr, TcpListener},
+    ops::ControlFlow,
+    sync::{
+        atomic::{AtomicI32, AtomicI64, Ordering},
+        Arc, Mutex,
+    },
+};
+use teloxide::{
+    prelude::*,
+    types::{Me, Update, UserId},
+};
+use tempfile::TempDir;
+use tokio::sync::OnceCell;
+
+pub const BOT_ID: u64 = 1_000_000;
+pub const BOT_USERNAME: &str = "gustyfring_bot";
+
+static DATABASE: OnceCell<()> = OnceCell::const_new();
+static NEXT_CHAT_ID: AtomicI64 = AtomicI64::new(-1_000_000);
+
+/// A user that can send messages in tests.
+#[derive(Clone, Debug)]
+pub struct TestUser {
+    pub id: u64,
+    pub first_name: &'static str,
+    pub username: &'static str,
+}
+
+pub const ALICE: TestUser = TestUser {
+    id: 101,
+    first_name: "Alice",
+    username: "alice",
+};
+pub const BOB: TestUser = TestUser {
+    id: 102,
+    first_name: "Bob",
+    username: "bob",
+};
+pub const CAROL: TestUser = TestUser {
+    id: 103,
+    first_name: "Carol",
+    username: "carol",
+};
+pub const DAVE: TestUser = TestUser {
+    id: 104,
+    first_name: "Dave",
+    username: "dave",
+};
+/// Configured as a bot owner
+pub const OWNER: TestUser = TestUser {
+    id: 100,
+    first_name: "Gus",
+    username: "gus",
+};
+
+/// A request the bot made to the fake Bot API. `method` is lowercased. For
+/// file uploads, `body` holds the text fields of the form.
+#[derive(Clone, Debug)]
+pub struct ApiRequest {
+    pub method: String,
+    pub body: Value,
+    /// What the fake API answered with
+    pub response: Value,
+}
+
+type Members = HashMap<(i64, u64), (TestUser, &'static str)>;
+
+#[derive(Default)]
+struct ApiState {
+    requests: Mutex<Vec<ApiRequest>>,
+    /// Membership status keyed by chat and user
+    members: Mutex<Members>,
+    /// Messages deleted so far, keyed by chat and message
+    deleted: Mutex<HashSet<(i64, i64)>>,
+    next_message_id: AtomicI32,
+}
+
+/// Local stand-in for `api.telegram.org`, recording every request.
+pub struct FakeApi {
+    pub url: reqwest::Url,
+    state: Arc<ApiState>,
+}
+
+impl FakeApi {
+    pub async fn start() -> Self {
+        let state = Arc::new(ApiState {
+            next_message_id: AtomicI32::new(10_000),
+            ..Default::default()
+        });
+        let router = Router::new()
+            .route("/:token/:method", post(handle_request))
+            .layer(Extension(state.clone()));
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address: SocketAddr = listener.local_addr().unwrap();
+        tokio::spawn(
+            axum::Server::from_tcp(listener)
+                .unwrap()
+                .serve(router.into_make_service()),
+        );
+
+        Self {
+            url: format!("http://{}/", address).parse().unwrap(),
+            state,
+        }
+    }
+
+    pub fn requests(&self) -> Vec<ApiRequest> {
+        self.state.requests.lock().unwrap().clone()
+    }
+
+    /// Sets the status `getChatMember` reports for `user` in `chat_id`, e.g.
+    /// `member` or `left`.
+    pub fn set_member_status(&self, chat_id: i64, user: &TestUser, status: &'static str) {
+        self.state
+            .members
+            .lock()
+            .unwrap()
+            .insert((chat_id, user.id), (user.clone(), status));
+    }
+
+    /// Deletes a message as if someone else had, so the bot can't anymore.
+    pub fn delete_message(&self, chat_id: i64, message_id: i64) {
+        self.state
+            .deleted
+            .lock()
+            .unwrap()
+            .insert((chat_id, message_id));
+    }
+}
+
+async fn handle_request(
+    Path((_token, method)): Path<(String, String)>,
+    Extension(state): Extension<Arc<ApiState>>,
+    body: Bytes,
+) -> Json<Value> {
+    // Bot API method names are case-insensitive
+    let method = method.to_lowercase();
+    let body = serde_json::from_slice(&body).unwrap_or_else(|_| multipart_fields(&body));
+    let response = respond(&state, &method, &body);
+    state.requests.lock().unwrap().push(ApiRequest {
+        method,
+        body,
+        response: response.clone(),
+    });
+
+    Json(response)
+}
+
+fn respond(state: &ApiState, method: &str, body: &Value) -> Value {
+    let result = match method {
+        "getme" => json!({
+            "id": BOT_ID,
+            "is_bot": true,
+            "first_name": "Gus",
+            "username": BOT_USERNAME,
+            "can_join_groups": true,
+            "can_read_all_group_messages": true,
+            "supports_inline_queries": false,
+        }),
+        "sendmessage" => {
+            let message_id = state.next_message_id.fetch_add(1, Ordering::SeqCst);
+            let chat_id = body["chat_id"].as_i64().unwrap();
+            json!({
+                "message_id": message_id,
+                "date": 0,
+                "chat": chat(chat_id),
+                "from": { "id": BOT_ID, "is_bot": true, "first_name": "Gus" },
+                "text": body["text"],
+            })
+        }
+        "editmessagetext" => json!({
+            "message_id": body["message_id"],
+            "date": 0,
+            "chat": chat(body["chat_id"].as_i64().unwrap()),
+            "from": { "id": BOT_ID, "is_bot": true, "first_name": "Gus" },
+            "text": body["text"],
+        }),
+        "sendpoll" => {
+            let message_id = state.next_message_id.fetch_add(1, Ordering::SeqCst);
+            let chat_id = body["chat_id"].as_i64().unwrap();
+            let options = body["options"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|option| json!({ "text": option, "voter_count": 0 }))
+                .collect::<Vec<_>>();
+            json!({
+                "message_id": message_id,
+                "date": 0,
+                "chat": chat(chat_id),
+                "from": { "id": BOT_ID, "is_bot": true, "first_name": "Gus" },
+                "poll": {
+                    "id": format!("poll-{}-{}", chat_id, message_id),
+                    "question": body["question"],
+                    "options": options,
+                    "total_voter_count": 0,
+                    "is_closed": false,
+                    "is_anonymous": body["is_anonymous"],
+                    "type": "regular",
+                    "allows_multiple_answers": false,
+                },
+            })
+        }
+        "senddocument" => {
+            let message_id = state.next_message_id.fetch_add(1, Ordering::SeqCst);
+            let chat_id = body["chat_id"].as_i64().unwrap();
+            json!({
+                "message_id": message_id,
+                "date": 0,
+                "chat": chat(chat_id),
+                "from": { "id": BOT_ID, "is_bot": true, "first_name": "Gus" },
+                "document": { "file_id": "document", "file_unique_id": "document" },
+            })
+        }
+        "sendphoto" => {
+            let message_id = state.next_message_id.fetch_add(1, Ordering::SeqCst);
+            let chat_id = body["chat_id"].as_i64().unwrap();
+            json!({
+                "message_id": message_id,
+                "date": 0,
+                "chat": chat(chat_id),
+                "from": { "id": BOT_ID, "is_bot": true, "first_name": "Gus" },
+                "photo": [{
+                    "file_id": "photo",
+                    "file_unique_id": "photo",
+                    "width": 1,
+                    "height": 1,
+                }],
+            })
+        }
+        "getchat" => chat(body["chat_id"].as_i64().unwrap()),
+        "getchatadministrators" => {
+            let chat_id = body["chat_id"].as_i64().unwrap();
+            let admins = state
+                .members
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|((chat, _), (_, status))| *chat == chat_id && *status == "administrator")
+                .map(|(_, (user, _))| {
+                    json!({
+                        "status": "administrator",
+                        "user": user_json(user),
+                        "is_anonymous": false,
+                        "can_be_edited": false,
+                        "can_manage_chat": true,
+                        "can_change_info": false,
+                        "can_delete_messages": true,
+                        "can_manage_video_chats": false,
+                        "can_invite_users": true,
+                        "can_restrict_members": true,
+                        "can_promote_members": false,
+                    })
+                })
+                .collect::<Vec<_>>();
+            json!(admins)
+        }
+        "getchatmember" => {
+            let chat_id = body["chat_id"].as_i64().unwrap();
+            let user_id = body["user_id"].as_u64().unwrap();
+            match state.members.lock().unwrap().get(&(chat_id, user_id)) {
+                Some((user, status)) => json!({
+                    "status": status,
+                    "user": user_json(user),
+                }),
+                None => {
+                    return json!({
+                        "ok": false,
+                        "error_code": 400,
+                        "description": "Bad Request: user not found",
+                    })
+                }
+            }
+        }
+        "deletemessage" => {
+            let message = (
+                body["chat_id"].as_i64().unwrap(),
+                body["message_id"].as_i64().unwrap(),
+            );
+            if !state.deleted.lock().unwrap().insert(message) {
+                return json!({
+                    "ok": false,
+                    "error_code": 400,
+                    "description": "Bad Request: message to delete not found",
+                });
+            }
+            json!(true)
+        }
+        _ => json!(true),
+    };
+
+    json!({ "ok": true, "result": result })
+}
+
+/// Collects the text fields of a `multipart/form-data` body, leaving out
+/// uploaded files. Numeric fields become JSON numbers.
+fn multipart_fields(body: &[u8]) -> Value {
+    let body = String::from_utf8_lossy(body);
+    let Some(boundary) = body.lines().next() else {
+        return Value::Null;
+    };
+
+    let mut fields = serde_json::Map::new();
+    for part in body.split(boundary) {
+        let Some((headers, value)) = part.split_once("\r\n\r\n") else {
+            continue;
+        };
+        if headers.contains("filename=") {
+            continue;
+        }
+        let Some(name) = headers
+            .split("name=\"")
+            .nth(1)
+            .and_then(|rest| rest.split('"').next())
+        else {
+            continue;
+        };
+
+        let value = value.trim_end_matches("\r\n");
+        let value = match value.parse::<i64>() {
+            Ok(number) => json!(number),
+            Err(_) => json!(value),
+        };
+        fields.insert(name.to_owned(), value);
+    }
+
+    Value::Object(fields)
+}
+
+fn chat(chat_id: i64) -> Value {
+    if chat_id < 0 {
+        json!({ "id": chat_id, "type": "supergroup", "title": "Test chat" })
+    } else {
+        json!({ "id": chat_id, "type": "private", "first_name": "Test" })
+    }
+}
+
+fn user_json(user: &TestUser) -> Value {
+    json!({
+        "id": user.id,
+        "is_bot": false,
+        "first_name": user.first_name,
+        "username": user.username,
+    })
+}
+
+/// A bot wired to a fresh [`FakeApi`] and a group chat of its own, so tests
+/// can share the in-memory database without seeing each other's data.
+pub struct Harness {
+    pub api: FakeApi,
+    pub bot: Bot,
+    pub me: Me,
+    pub chat_id: i64,
+    next_message_id: AtomicI32,
+}
+
+impl Harness {
+    pub async fn new() -> Self {
+        Self::with_database("sqlite::memory:").await
+    }
+
+    /// A harness whose database is a file, removed once `dir` is dropped,
+    /// for tests that back the database up, which can't be done in memory.
+    /// The database is shared by the whole test binary, so this must be the
+    /// only harness of its test file.
+    pub async fn with_file_db(dir: &TempDir) -> Self {
+        assert!(
+            !DATABASE.initialized(),
+            "A harness with a file database must be the only one of its test file"
+        );
+        let path = dir.path().join("gus-test.sqlite3");
+        Self::with_database(&format!("sqlite://{}?mode=rwc", path.display())).await
+    }
+
+    async fn with_database(url: &str) -> Self {
+        DATABASE
+            .get_or_init(|| async {
+                // Keep intent detection offline
+                std::env::remove_var("GOOGLE_APPLICATION_CREDENTIALS");
+
+                pool::init_with_url(url).await.unwrap();
+                // An in-memory database is gone once its last connection
+                // closes, which happens whenever the test that opened the
+                // pool's connections ends, so keep one open for good
+                std::mem::forget(pool::db().acquire().await.unwrap().detach());
+                pool::migrate().await.unwrap();
+                permissions::set_owners([UserId(OWNER.id)]);
+            })
+            .await;
+
+        let api = FakeApi::start().await;
+        let bot = Bot::new("TEST_TOKEN").set_api_url(api.url.clone());
+        let me = bot.get_me().await.unwrap();
+
+        Self {
+            api,
+            bot,
+            me,
+            chat_id: NEXT_CHAT_ID.fetch_sub(1, Ordering::SeqCst),
+            next_message_id: AtomicI32::new(1),
+        }
+    }
+
+    /// Adds `user` to the test chat as far as `getChatMember` is concerned.
+    pub fn join(&self, user: &TestUser) {
+        self.api.set_member_status(self.chat_id, user, "member");
+    }
+
+    /// Makes `user` an administrator of the test chat.
+    pub fn promote(&self, user: &TestUser) {
+        self.api
+            .set_member_status(self.chat_id, user, "administrator");
+    }
+
+    /// Marks `user` as having left the test chat.
+    pub fn leave(&self, user: &TestUser) {
+        self.api.set_member_status(self.chat_id, user, "left");
+    }
+
+    /// Builds a text message from `from` in the test chat.
+    pub fn message(&self, from: &TestUser, text: &str) -> Value {
+        json!({
+            "message_id": self.next_message_id.fetch_add(1, Ordering::SeqCst),
+            "date": 0,
+            "chat": chat(self.chat_id),
+            "from": user_json(from),
+            "text": text,
+        })
+    }
+
+    /// Builds a text message from `from` replying to `to`.
+    pub fn reply(&self, from: &TestUser, text: &str, to: &Value) -> Value {
+        let mut message = self.message(from, text);
+        message["reply_to_message"] = to.clone();
+        message
+    }
+
+    /// Runs a message through the bot's update handlers, panicking if they
+    /// fail.
+    pub async fn send(&self, message: Value) {
+        self.dispatch(json!({ "update_id": 1, "message": message }))
+            .await;
+    }
+
+    /// Presses an inline keyboard button with callback `data` under
+    /// `message`, which the bot sent.
+    pub async fn press(&self, from: &TestUser, message: &Value, data: &str) {
+        self.dispatch(json!({
+            "update_id": 1,
+            "callback_query": {
+                "id": format!("{}", self.next_message_id.fetch_add(1, Ordering::SeqCst)),
+                "from": user_json(from),
+                "message": message,
+                "chat_instance": "1",
+                "data": data,
+            },
+        }))
+        .await;
+    }
+
+    /// Changes the reactions `from` left on `message` from the emoji in `old`
+    /// to the emoji in `new`.
+    pub async fn react(&self, from: &TestUser, message: &Value, old: &[&str], new: &[&str]) {
+        let reactions = |emoji: &[&str]| -> Vec<Value> {
+            emoji
+                .iter()
+                .map(|emoji| json!({ "type": "emoji", "emoji": emoji }))
+                .collect()
+        };
+
+        self.dispatch(json!({
+            "update_id": 1,
+            "message_reaction": {
+                "chat": chat(self.chat_id),
+                "message_id": message["message_id"],
+                "user": user_json(from),
+                "date": 0,
+                "old_reaction": reactions(old),
+                "new_reaction": reactions(new),
+            },
+        }))
+        .await;
+    }
+
+    /// Changes the answers `from` chose in the poll sent as `message`, an
+    /// empty `options` retracting their vote.
+    pub async fn vote(&self, from: &TestUser, message: &Value, options: &[i32]) {
+        self.dispatch(json!({
+            "update_id": 1,
+            "poll_answer": {
+                "poll_id": message["poll"]["id"],
+                "user": user_json(from),
+                "option_ids": options,
+            },
+        }))
+        .await;
+    }
+
+    /// Writes `query` after the bot's username as `from`, in any chat.
+    pub async fn inline(&self, from: &TestUser, query: &str) {
+        self.dispatch(json!({
+            "update_id": 1,
+            "inline_query": {
+                "id": format!("{}", self.next_message_id.fetch_add(1, Ordering::SeqCst)),
+                "from": user_json(from),
+                "query": query,
+                "offset": "",
+            },
+        }))
+        .await;
+    }
+
+    async fn dispatch(&self, update: Value) {
+        // `Update` only deserializes from borrowed input, not from a `Value`
+        let update: Update = serde_json::from_str(&update.to_string()).unwrap();
+
+        let result = schema()
+            .dispatch(dptree::deps![update, self.bot.clone(), self.me.clone()])
+            .await;
+        match result {
+            ControlFlow::Break(result) => result.unwrap(),
+            ControlFlow::Continue(_) => panic!("Update was not handled"),
+        }
+    }
+
+    /// Texts of the messages sent to the test chat so far, oldest first.
+    pub fn sent_messages(&self) -> Vec<String> {
+        self.api
+            .requests()
+            .into_iter()
+            .filter(|request| {
+                request.method == "sendmessage" && request.body["chat_id"] == self.chat_id
+            })
+            .map(|request| request.body["text"].as_str().unwrap().to_owned())
+            .collect()
+    }
+
+    /// The last message the bot sent to the test chat, as Telegram returned
+    /// it.
+    pub fn last_sent(&self) -> Value {
+        self.api
+            .requests()
+            .into_iter()
+            .filter(|request| {
+                request.method == "sendmessage" && request.body["chat_id"] == self.chat_id
+            })
+            .last()
+            .expect("No message was sent to the chat")
+            .response["result"]
+            .clone()
+    }
+
+    /// Requests the bot made with `method`, lowercased, oldest first.
+    pub fn requests(&self, method: &str) -> Vec<ApiRequest> {
+        self.api
+            .requests()
+            .into_iter()
+            .filter(|request| request.method == method)
+            .collect()
+    }
+
+    /// Callback data of the buttons under the last message sent to the test
+    /// chat, row by row.
+    fn last_request_with_buttons(&self) -> ApiRequest {
+        self.requests("sendmessage")
+            .into_iter()
+            .filter(|request| {
+                request.body["chat_id"] == self.chat_id && !request.body["reply_markup"].is_null()
+            })
+            .last()
+            .expect("No message with buttons was sent to the chat")
+    }
+
+    /// The last message the bot sent to the test chat with inline keyboard
+    /// buttons, such as the announcement of an L.
+    pub fn last_with_buttons(&self) -> Value {
+        self.last_request_with_buttons().response["result"].clone()
+    }
+
+    /// Callback data of the buttons under [`Self::last_with_buttons`].
+    pub fn last_buttons(&self) -> Vec<String> {
+        let sent = self.last_request_with_buttons();
+        let Some(rows) = sent.body["reply_markup"]["inline_keyboard"].as_array() else {
+            return Vec::new();
+        };
+
+        rows.iter()
+            .flat_map(|row| row.as_array().unwrap())
+            .map(|button| button["callback_data"].as_str().unwrap().to_owned())
+            .collect()
+    }
+
+    /// Text the bot answered the last button press with, if any.
+    pub fn last_answer(&self) -> Option<String> {
+        let answer = self.requests("answercallbackquery").pop()?;
+        answer.body["text"].as_str().map(str::to_owned)
+    }
+
+    /// Text the bot last edited one of its messages to.
+    pub fn last_edit(&self) -> String {
+        let edit = self
+            .requests("editmessagetext")
+            .pop()
+            .expect("No message was edited");
+        edit.body["text"].as_str().unwrap().to_owned()
+    }
+
+    /// Text of the last message sent to the test chat.
+    pub fn last_message(&self) -> String {
+        self.sent_messages()
+            .pop()
+            .expect("No message was sent to the chat")
+    }
+}
@@ -0,0 +1,208 @@
+mod common;
+
+use common::{Harness, ALICE, BOB, CAROL, DAVE, OWNER};
+use gustyfring::{
+    db::{
+        models::{TimedAward, TypedMemberStat},
+        pool::db,
+    },
+    decay,
+    settings::{ChatSettings, ScoreDecay},
+};
+
+const DAY: i64 = 24 * 60 * 60;
+
+#[tokio::test]
+async fn decayed_scoreboards_favour_recent_ls() {
+    let harness = Harness::new().await;
+    harness.promote(&OWNER);
+    let bobs_message = harness.message(&BOB, "i replied all");
+    for giver in [&ALICE, &CAROL, &DAVE] {
+        harness
+            .send(harness.reply(giver, "/givel", &bobs_message))
+            .await;
+    }
+    // Bob's Ls were given two months ago
+    sqlx::query("UPDATE Award SET created_at = created_at - $1 WHERE chat_id = $2")
+        .bind(60 * DAY)
+        .bind(harness.chat_id)
+        .execute(db())
+        .await
+        .unwrap();
+    let carols_message = harness.message(&CAROL, "i replied all too");
+    for giver in [&ALICE, &DAVE] {
+        harness
+            .send(harness.reply(giver, "/givel", &carols_message))
+            .await;
+    }
+
+    harness
+        .send(harness.message(&ALICE, "/viewscoreboard"))
+        .await;
+    assert_eq!(
+        harness.last_message(),
+        "__Bob__ 🥉 — *3* Ls\n__Carol__ 🥉 — *2* Ls"
+    );
+
+    harness
+        .send(harness.message(&OWNER, "/settings decay halflife"))
+        .await;
+    harness
+        .send(harness.message(&ALICE, "/viewscoreboard"))
+        .await;
+    assert_eq!(
+        harness.last_message(),
+        "__Carol__ 🥉 — *2* Ls\n__Bob__ 🥉 — *1* Ls\n\n_Awards count half as much every 30 days_"
+    );
+
+    harness
+        .send(harness.message(&OWNER, "/settings decay window"))
+        .await;
+    harness
+        .send(harness.message(&ALICE, "/viewscoreboard l"))
+        .await;
+    assert_eq!(
+        harness.last_message(),
+        "__Carol__ 🥉 — *2* Ls\n\n_Only awards from the last 30 days count_"
+    );
+    harness
+        .send(harness.message(&OWNER, "/settings decaydays 90"))
+        .await;
+    harness
+        .send(harness.message(&ALICE, "/viewscoreboard l"))
+        .await;
+    assert!(harness
+        .last_message()
+        .starts_with("__Bob__ 🥉 — *3* Ls\n__Carol__ 🥉 — *2* Ls"));
+
+    // The awards themselves are untouched
+    harness
+        .send(harness.message(&OWNER, "/settings decay off"))
+        .await;
+    harness
+        .send(harness.message(&ALICE, "/viewscoreboard"))
+        .await;
+    assert_eq!(
+        harness.last_message(),
+        "__Bob__ 🥉 — *3* Ls\n__Carol__ 🥉 — *2* Ls"
+    );
+}
+
+#[tokio::test]
+async fn scores_from_before_awards_were_recorded_fade_too() {
+    let harness = Harness::new().await;
+    harness.promote(&OWNER);
+    harness.send(harness.message(&BOB, "hi")).await;
+    // Bob's 4 Ls were counted before each award was recorded
+    sqlx::query(
+        "INSERT INTO Stat (chat_id, member_id, award_type, amount) VALUES ($1, $2, 'l', 4)",
+    )
+    .bind(harness.chat_id)
+    .bind(BOB.id as i64)
+    .execute(db())
+    .await
+    .unwrap();
+
+    harness
+        .send(harness.message(&OWNER, "/settings decay halflife"))
+        .await;
+    harness
+        .send(harness.message(&ALICE, "/viewscoreboard"))
+        .await;
+    assert_eq!(
+        harness.last_message(),
+        "__Bob__ — *2* Ls\n\n_Awards count half as much every 30 days_"
+    );
+
+    harness
+        .send(harness.message(&OWNER, "/settings decay window"))
+        .await;
+    harness
+        .send(harness.message(&ALICE, "/viewscoreboard"))
+        .await;
+    assert_eq!(harness.last_message(), "Scoreboard is empty\\!");
+}
+
+#[tokio::test]
+async fn decay_settings_are_checked() {
+    let harness = Harness::new().await;
+    harness.promote(&OWNER);
+    harness
+        .send(harness.message(&OWNER, "/settings decay sometimes"))
+        .await;
+    assert_eq!(
+        harness.last_message(),
+        "decay must be halflife, window or off"
+    );
+    harness
+        .send(harness.message(&OWNER, "/settings decaydays 0"))
+        .await;
+    assert_eq!(
+        harness.last_message(),
+        "decaydays must be a number of days from 1 to 3650"
+    );
+}
+
+#[test]
+fn awards_fade_by_their_age() {
+    let mut settings = ChatSettings {
+        decay: ScoreDecay::HalfLife,
+        decay_days: 10,
+        ..ChatSettings::default()
+    };
+    assert_eq!(decay::weight(&settings, 0), 1.0);
+    assert_eq!(decay::weight(&settings, 10 * DAY), 0.5);
+    assert_eq!(decay::weight(&settings, 20 * DAY), 0.25);
+
+    settings.decay = ScoreDecay::Window;
+    assert_eq!(decay::weight(&settings, 10 * DAY - 1), 1.0);
+    assert_eq!(decay::weight(&settings, 10 * DAY), 0.0);
+
+    settings.decay = ScoreDecay::Off;
+    assert_eq!(decay::weight(&settings, 1000 * DAY), 1.0);
+    assert_eq!(decay::note(&settings), None);
+}
+
+#[test]
+fn decayed_scores_are_summed_per_member_and_type() {
+    let settings = ChatSettings {
+        decay: ScoreDecay::HalfLife,
+        decay_days: 1,
+        ..ChatSettings::default()
+    };
+    let now = 100 * DAY;
+    let award = |tg_user_id: &str, award_type: &str, days_ago| TimedAward {
+        tg_user_id: tg_user_id.to_owned(),
+        first_name: None,
+        award_type: award_type.to_owned(),
+        created_at: now - days_ago * DAY,
+    };
+    let awards = [
+        award("1", "l", 0),
+        award("1", "l", 1),
+        award("1", "l", 1),
+        award("1", "w", 0),
+        award("2", "l", 3),
+    ];
+
+    // Member 2 also has Ls from before awards were recorded
+    let undated = [TypedMemberStat {
+        tg_user_id: String::from("2"),
+        first_name: None,
+        award_type: String::from("l"),
+        amount: 4,
+    }];
+
+    let scores = decay::scores(&awards, &undated, &settings, now)
+        .into_iter()
+        .map(|score| (score.tg_user_id, score.award_type, score.amount))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        scores,
+        [
+            (String::from("1"), String::from("l"), 2),
+            (String::from("1"), String::from("w"), 1),
+            (String::from("2"), String::from("l"), 2),
+        ]
+    );
+}
@@ -0,0 +1,171 @@
+mod common;
+
+use common::{Harness, ALICE, BOB, CAROL, DAVE, OWNER};
+use gustyfring::{common::time::unix_time, db::pool::db, digest, settings};
+use teloxide::types::ChatId;
+
+const DAY: i64 = 24 * 60 * 60;
+
+#[tokio::test]
+async fn digest_lists_who_got_the_most_ls_and_why() {
+    let harness = Harness::new().await;
+    harness.join(&BOB);
+    harness.join(&CAROL);
+    let bobs_message = harness.message(&BOB, "i locked myself out again");
+    harness
+        .send(harness.reply(&ALICE, "/givel for forgetting his keys", &bobs_message))
+        .await;
+    let announcement = harness.last_with_buttons();
+    let plus_one = &harness.last_buttons()[0];
+    harness.press(&DAVE, &announcement, plus_one).await;
+    let carols_message = harness.message(&CAROL, "i like pineapple on pizza");
+    harness
+        .send(harness.reply(&ALICE, "/givel", &carols_message))
+        .await;
+
+    harness.send(harness.message(&BOB, "/digest now")).await;
+    let digest = harness.last_message();
+    assert!(digest.starts_with("*Daily L digest*"));
+    assert!(digest.contains("Most Ls: __Bob__ with 2 Ls, __Carol__ with 1 L"));
+    assert!(digest.contains("Funniest reasons:\n• “for forgetting his keys” — __Bob__, 2 Ls"));
+    assert!(digest.contains("Phrases learned: "));
+}
+
+#[tokio::test]
+async fn digest_names_the_biggest_gainer() {
+    let harness = Harness::new().await;
+    harness.join(&BOB);
+    harness.join(&CAROL);
+    let carols_message = harness.message(&CAROL, "cereal is soup");
+    for giver in [&ALICE, &DAVE] {
+        harness
+            .send(harness.reply(giver, "/givel", &carols_message))
+            .await;
+    }
+    let bobs_message = harness.message(&BOB, "water isn't wet");
+    harness
+        .send(harness.reply(&ALICE, "/givel", &bobs_message))
+        .await;
+    // Those Ls were given the day before yesterday
+    sqlx::query("UPDATE Award SET created_at = created_at - $1 WHERE chat_id = $2")
+        .bind(2 * DAY)
+        .bind(harness.chat_id)
+        .execute(db())
+        .await
+        .unwrap();
+
+    for giver in [&CAROL, &DAVE] {
+        harness
+            .send(harness.reply(giver, "/givel", &bobs_message))
+            .await;
+    }
+    harness.send(harness.message(&BOB, "/digest now")).await;
+    let digest = harness.last_message();
+    assert!(digest.contains("Most Ls: __Bob__ with 2 Ls\n"));
+    assert!(digest.contains("Biggest gainer: __Bob__, up 1 place to \\#1"));
+}
+
+#[tokio::test]
+async fn digest_places_follow_the_decayed_scoreboard() {
+    let harness = Harness::new().await;
+    harness.promote(&OWNER);
+    let shift_awards = |days: i64| {
+        sqlx::query("UPDATE Award SET created_at = created_at - $1 WHERE chat_id = $2")
+            .bind(days * DAY)
+            .bind(harness.chat_id)
+            .execute(db())
+    };
+    let carols_message = harness.message(&CAROL, "socks with sandals");
+    for giver in [&ALICE, &BOB, &DAVE] {
+        harness
+            .send(harness.reply(giver, "/givel", &carols_message))
+            .await;
+    }
+    shift_awards(58).await.unwrap();
+    let daves_message = harness.message(&DAVE, "ketchup on steak");
+    harness
+        .send(harness.reply(&ALICE, "/givel", &daves_message))
+        .await;
+    // Carol's Ls are two months old now, Dave's two days
+    shift_awards(2).await.unwrap();
+    let bobs_message = harness.message(&BOB, "milk before cereal");
+    for giver in [&ALICE, &CAROL] {
+        harness
+            .send(harness.reply(giver, "/givel", &bobs_message))
+            .await;
+    }
+
+    harness.send(harness.message(&BOB, "/digest now")).await;
+    assert!(harness
+        .last_message()
+        .contains("Biggest gainer: __Bob__, up 1 place to \\#2"));
+
+    // Carol's Ls fall outside the window, so Bob leads the scoreboard
+    harness
+        .send(harness.message(&OWNER, "/settings decay window"))
+        .await;
+    harness.send(harness.message(&BOB, "/digest now")).await;
+    assert!(harness
+        .last_message()
+        .contains("Biggest gainer: __Bob__, up 1 place to \\#1"));
+}
+
+#[tokio::test]
+async fn digests_are_posted_once_at_the_chats_local_time() {
+    let harness = Harness::new().await;
+    harness.promote(&OWNER);
+    for setting in [
+        "digest daily",
+        "digesttime 12:00",
+        "timezone Asia/Singapore",
+    ] {
+        harness
+            .send(harness.message(&OWNER, &format!("/settings {}", setting)))
+            .await;
+    }
+    harness.send(harness.message(&BOB, "/digest")).await;
+    assert_eq!(
+        harness.last_message(),
+        "The digest is posted every day at 12:00 \\(Asia/Singapore\\)\\. Use /digest now to see it so far\\."
+    );
+    let sent = harness.sent_messages().len();
+
+    // Noon in Singapore is 04:00 UTC
+    let now = unix_time();
+    let noon = (now - 4 * 60 * 60).div_euclid(DAY) * DAY + 4 * 60 * 60 + DAY;
+    digest::post_due(&harness.bot, noon - 60).await.unwrap();
+    assert_eq!(harness.sent_messages().len(), sent);
+    digest::post_due(&harness.bot, noon + 30).await.unwrap();
+    assert_eq!(harness.sent_messages().len(), sent + 1);
+    assert!(harness.last_message().starts_with("*Daily L digest*"));
+    digest::post_due(&harness.bot, noon + 90).await.unwrap();
+    assert_eq!(harness.sent_messages().len(), sent + 1);
+
+    // A digest missed by hours is skipped
+    digest::post_due(&harness.bot, noon + DAY + 3 * 60 * 60)
+        .await
+        .unwrap();
+    assert_eq!(harness.sent_messages().len(), sent + 1);
+}
+
+#[tokio::test]
+async fn weekly_digests_are_due_on_sundays() {
+    let harness = Harness::new().await;
+    harness.promote(&OWNER);
+    for setting in ["digest weekly", "digesttime 09:30"] {
+        harness
+            .send(harness.message(&OWNER, &format!("/settings {}", setting)))
+            .await;
+    }
+    let settings = settings::get(ChatId(harness.chat_id)).await.unwrap();
+
+    // Thursday 2 January 2020 at noon UTC, and the Sunday before at 09:30
+    let thursday = 1_577_966_400;
+    let sunday = thursday - 4 * DAY + (9 * 60 + 30) * 60 - 12 * 60 * 60;
+    assert_eq!(digest::last_due(&settings, thursday), Some(sunday));
+    assert_eq!(digest::last_due(&settings, sunday), Some(sunday));
+    assert_eq!(
+        digest::last_due(&settings, sunday - 1),
+        Some(sunday - 7 * DAY)
+    );
+}
@@ -0,0 +1,658 @@
+mod common;
+
+use common::{Harness, ALICE, BOB, CAROL, DAVE, OWNER};
+use gustyfring::{cleanup, common::time::unix_time, jury};
+
+#[tokio::test]
+async fn givel_awards_the_replied_to_member() {
+    let harness = Harness::new().await;
+    harness.join(&BOB);
+
+    let bobs_message = harness.message(&BOB, "i forgot my keys again");
+    harness
+        .send(harness.reply(&ALICE, "/givel", &bobs_message))
+        .await;
+    assert_eq!(
+        harness.sent_messages(),
+        ["L has been awarded", "__Bob__ earned a badge: 🥉 *First L*"]
+    );
+
+    harness
+        .send(harness.message(&ALICE, "/viewscoreboard"))
+        .await;
+    assert_eq!(harness.last_message(), "__Bob__ 🥉 — *1* Ls");
+}
+
+#[tokio::test]
+async fn givel_without_reply_explains_usage() {
+    let harness = Harness::new().await;
+
+    harness.send(harness.message(&ALICE, "/givel")).await;
+    assert_eq!(
+        harness.last_message(),
+        "Reply to someone's message or mention them to award them an L"
+    );
+}
+
+#[tokio::test]
+async fn scoreboard_is_ranked_and_scoped_to_the_chat() {
+    let harness = Harness::new().await;
+    let other = Harness::new().await;
+    harness.join(&BOB);
+    harness.join(&CAROL);
+
+    let bobs_message = harness.message(&BOB, "pineapple belongs on pizza");
+    let carols_message = harness.message(&CAROL, "i agree");
+    harness
+        .send(harness.reply(&ALICE, "/givel", &bobs_message))
+        .await;
+    harness
+        .send(harness.reply(&ALICE, "/givel", &carols_message))
+        .await;
+    harness
+        .send(harness.reply(&BOB, "/givel", &carols_message))
+        .await;
+
+    harness
+        .send(harness.message(&ALICE, "/viewscoreboard"))
+        .await;
+    assert_eq!(
+        harness.last_message(),
+        "__Carol__ 🥉 — *2* Ls\n__Bob__ 🥉 — *1* Ls"
+    );
+
+    other.send(other.message(&ALICE, "/viewscoreboard")).await;
+    assert_eq!(other.last_message(), "Scoreboard is empty\\!");
+}
+
+#[tokio::test]
+async fn scoreboard_shows_departed_members_by_last_known_name() {
+    let harness = Harness::new().await;
+    harness.join(&BOB);
+
+    let bobs_message = harness.message(&BOB, "brb");
+    harness.send(bobs_message.clone()).await;
+    harness
+        .send(harness.reply(&ALICE, "/givel", &bobs_message))
+        .await;
+    harness.leave(&BOB);
+
+    harness
+        .send(harness.message(&ALICE, "/viewscoreboard"))
+        .await;
+    assert_eq!(harness.last_message(), "__Bob__ 🥉 — *1* Ls");
+}
+
+#[tokio::test]
+async fn learned_phrases_are_answered_by_the_fallback_handler() {
+    let harness = Harness::new().await;
+
+    harness
+        .send(harness.message(&ALICE, "/learn hello there | general kenobi"))
+        .await;
+    assert_eq!(harness.last_message(), "learnt");
+
+    harness.send(harness.message(&BOB, "Hello there")).await;
+    assert_eq!(harness.last_message(), "general kenobi");
+}
+
+#[tokio::test]
+async fn learning_a_phrase_again_adds_a_response() {
+    let harness = Harness::new().await;
+
+    harness
+        .send(harness.message(&ALICE, "/learn Good Morning | morning!"))
+        .await;
+    harness
+        .send(harness.message(&BOB, "/learn good morning | morning!"))
+        .await;
+    assert_eq!(harness.sent_messages(), ["learnt", "learnt"]);
+
+    harness.send(harness.message(&CAROL, "good morning")).await;
+    assert_eq!(harness.last_message(), "morning!");
+}
+
+#[tokio::test]
+async fn unmatched_messages_are_ignored() {
+    let harness = Harness::new().await;
+
+    harness
+        .send(harness.message(&ALICE, "nobody taught me this"))
+        .await;
+    assert!(harness.sent_messages().is_empty());
+}
+
+#[tokio::test]
+async fn backup_is_refused_to_other_members() {
+    let harness = Harness::new().await;
+
+    harness.send(harness.message(&CAROL, "/backup")).await;
+    assert_eq!(harness.last_message(), "Only bot owners can use /backup");
+}
+
+#[tokio::test]
+async fn resetting_scores_requires_a_chat_admin() {
+    let harness = Harness::new().await;
+    harness.promote(&ALICE);
+    harness.join(&BOB);
+    let bobs_message = harness.message(&BOB, "oops");
+    harness
+        .send(harness.reply(&ALICE, "/givel", &bobs_message))
+        .await;
+
+    harness.send(harness.message(&BOB, "/resetscores")).await;
+    assert_eq!(
+        harness.last_message(),
+        "Only chat admins can use /resetscores"
+    );
+
+    harness.send(harness.message(&ALICE, "/resetscores")).await;
+    assert_eq!(harness.last_message(), "Scoreboard has been reset");
+    harness
+        .send(harness.message(&ALICE, "/viewscoreboard"))
+        .await;
+    assert_eq!(harness.last_message(), "Scoreboard is empty\\!");
+}
+
+#[tokio::test]
+async fn role_overrides_take_precedence_over_telegram_admins() {
+    let harness = Harness::new().await;
+    harness.join(&CAROL);
+    harness.promote(&BOB);
+
+    let carols_message = harness.message(&CAROL, "hi");
+    harness
+        .send(harness.reply(&BOB, "/setrole admin", &carols_message))
+        .await;
+    assert_eq!(harness.last_message(), "Role updated");
+
+    let bobs_message = harness.message(&BOB, "hi");
+    harness
+        .send(harness.reply(&CAROL, "/setrole member", &bobs_message))
+        .await;
+    harness.send(harness.message(&BOB, "/resetscores")).await;
+    assert_eq!(
+        harness.last_message(),
+        "Only chat admins can use /resetscores"
+    );
+
+    harness
+        .send(harness.reply(&CAROL, "/setrole default", &bobs_message))
+        .await;
+    harness.send(harness.message(&BOB, "/resetscores")).await;
+    assert_eq!(harness.last_message(), "Scoreboard has been reset");
+}
+
+#[tokio::test]
+async fn only_owners_can_forget_phrases() {
+    let harness = Harness::new().await;
+    harness.promote(&ALICE);
+    harness
+        .send(harness.message(&ALICE, "/learn see you | later"))
+        .await;
+
+    harness
+        .send(harness.message(&ALICE, "/forget see you"))
+        .await;
+    assert_eq!(harness.last_message(), "Only bot owners can use /forget");
+
+    harness
+        .send(harness.message(&OWNER, "/forget See you"))
+        .await;
+    assert_eq!(harness.last_message(), "forgotten");
+    harness.send(harness.message(&BOB, "see you")).await;
+    assert_eq!(harness.last_message(), "forgotten");
+}
+
+#[tokio::test]
+async fn settings_are_listed_with_their_defaults() {
+    let harness = Harness::new().await;
+
+    harness.send(harness.message(&BOB, "/settings")).await;
+    let listing = harness.last_message();
+    assert!(listing.contains("cooldown: 0"));
+    assert!(listing.contains("nlu: on"));
+    assert!(harness.last_sent()["reply_markup"].is_null());
+}
+
+#[tokio::test]
+async fn award_cooldown_is_set_per_chat() {
+    let harness = Harness::new().await;
+    let other = Harness::new().await;
+    harness.promote(&ALICE);
+    harness.join(&BOB);
+    other.join(&BOB);
+
+    harness
+        .send(harness.message(&ALICE, "/settings cooldown 60"))
+        .await;
+    assert_eq!(harness.last_message(), "cooldown is now 60");
+
+    let bobs_message = harness.message(&BOB, "first");
+    harness
+        .send(harness.reply(&ALICE, "/givel", &bobs_message))
+        .await;
+    harness
+        .send(harness.reply(&ALICE, "/givel", &bobs_message))
+        .await;
+    assert!(harness
+        .last_message()
+        .starts_with("You can give another L in"));
+
+    let bobs_message = other.message(&BOB, "elsewhere");
+    other
+        .send(other.reply(&ALICE, "/givel", &bobs_message))
+        .await;
+    assert!(other
+        .sent_messages()
+        .contains(&String::from("L has been awarded")));
+}
+
+#[tokio::test]
+async fn changing_settings_requires_a_chat_admin() {
+    let harness = Harness::new().await;
+    harness.promote(&ALICE);
+
+    harness
+        .send(harness.message(&BOB, "/settings fallback off"))
+        .await;
+    assert_eq!(
+        harness.last_message(),
+        "Only chat admins can change settings"
+    );
+
+    harness
+        .send(harness.message(&ALICE, "/settings cooldown soon"))
+        .await;
+    assert_eq!(
+        harness.last_message(),
+        "cooldown must be a number of seconds"
+    );
+
+    harness
+        .send(harness.message(&ALICE, "/settings jurytime 307445734561825861"))
+        .await;
+    assert_eq!(
+        harness.last_message(),
+        "jurytime must be a number of minutes from 1 to 10080"
+    );
+    harness
+        .send(harness.message(&ALICE, "/settings deletereplies 9223372036854775808"))
+        .await;
+    assert_eq!(
+        harness.last_message(),
+        "deletereplies must be a number of seconds from 0 to 172800"
+    );
+}
+
+#[tokio::test]
+async fn settings_buttons_toggle_fallback_replies() {
+    let harness = Harness::new().await;
+    harness.promote(&ALICE);
+    harness
+        .send(harness.message(&ALICE, "/learn ping | pong"))
+        .await;
+
+    harness.send(harness.message(&ALICE, "/settings")).await;
+    let listing = harness.last_sent();
+
+    harness.press(&BOB, &listing, "settings:fallback").await;
+    let answers = harness.requests("answercallbackquery");
+    assert_eq!(
+        answers[0].body["text"],
+        "Only chat admins can change settings"
+    );
+
+    harness.press(&ALICE, &listing, "settings:fallback").await;
+    let edits = harness.requests("editmessagetext");
+    assert_eq!(edits.len(), 1);
+    assert!(edits[0].body["text"]
+        .as_str()
+        .unwrap()
+        .contains("fallback: off"));
+
+    let sent = harness.sent_messages().len();
+    harness.send(harness.message(&BOB, "ping")).await;
+    assert_eq!(harness.sent_messages().len(), sent);
+}
+
+#[tokio::test]
+async fn members_can_add_to_an_awarded_l() {
+    let harness = Harness::new().await;
+    harness.join(&BOB);
+    let bobs_message = harness.message(&BOB, "i microwaved fish at work");
+    harness
+        .send(harness.reply(&ALICE, "/givel", &bobs_message))
+        .await;
+    let announcement = harness.last_with_buttons();
+    let plus_one = &harness.last_buttons()[0];
+
+    harness.press(&CAROL, &announcement, plus_one).await;
+    assert_eq!(harness.last_answer().as_deref(), Some("+1 L given"));
+    assert_eq!(harness.last_edit(), "2 Ls have been awarded");
+
+    harness.press(&CAROL, &announcement, plus_one).await;
+    assert_eq!(
+        harness.last_answer().as_deref(),
+        Some("You already gave this L")
+    );
+    harness.press(&BOB, &announcement, plus_one).await;
+    assert_eq!(
+        harness.last_answer().as_deref(),
+        Some("You can't give yourself an L")
+    );
+
+    harness
+        .send(harness.message(&ALICE, "/viewscoreboard"))
+        .await;
+    assert_eq!(harness.last_message(), "__Bob__ 🥉 — *2* Ls");
+}
+
+#[tokio::test]
+async fn only_givers_and_chat_admins_can_undo_an_l() {
+    let harness = Harness::new().await;
+    harness.promote(&DAVE);
+    harness.join(&BOB);
+    let bobs_message = harness.message(&BOB, "tabs are better than spaces");
+    harness
+        .send(harness.reply(&ALICE, "/givel", &bobs_message))
+        .await;
+    let announcement = harness.last_with_buttons();
+    let buttons = harness.last_buttons();
+    let (plus_one, undo) = (&buttons[0], &buttons[1]);
+
+    harness.press(&CAROL, &announcement, undo).await;
+    assert_eq!(
+        harness.last_answer().as_deref(),
+        Some("Only its givers and chat admins can undo this L")
+    );
+
+    harness.press(&CAROL, &announcement, plus_one).await;
+    harness.press(&CAROL, &announcement, undo).await;
+    assert_eq!(harness.last_answer().as_deref(), Some("L taken back"));
+    assert_eq!(harness.last_edit(), "L has been awarded");
+
+    harness.press(&DAVE, &announcement, undo).await;
+    assert_eq!(harness.last_edit(), "L has been taken back");
+    harness
+        .send(harness.message(&ALICE, "/viewscoreboard"))
+        .await;
+    assert_eq!(harness.last_message(), "Scoreboard is empty\\!");
+
+    harness.press(&ALICE, &announcement, plus_one).await;
+    assert_eq!(
+        harness.last_answer().as_deref(),
+        Some("This L has been taken back")
+    );
+}
+
+#[tokio::test]
+async fn undoing_an_l_keeps_the_ls_added_to_it() {
+    let harness = Harness::new().await;
+    harness.join(&BOB);
+    let bobs_message = harness.message(&BOB, "i replied all");
+    harness
+        .send(harness.reply(&ALICE, "/givel", &bobs_message))
+        .await;
+    let announcement = harness.last_with_buttons();
+    let buttons = harness.last_buttons();
+    let (plus_one, undo) = (&buttons[0], &buttons[1]);
+
+    harness.press(&CAROL, &announcement, plus_one).await;
+    harness.press(&ALICE, &announcement, undo).await;
+    assert_eq!(harness.last_answer().as_deref(), Some("L taken back"));
+    assert_eq!(harness.last_edit(), "L has been awarded");
+    harness
+        .send(harness.message(&ALICE, "/viewscoreboard"))
+        .await;
+    assert_eq!(harness.last_message(), "__Bob__ 🥉 — *1* Ls");
+
+    // The buttons now belong to Carol's L
+    let edit = harness.requests("editmessagetext").pop().unwrap();
+    let plus_one = edit.body["reply_markup"]["inline_keyboard"][0][0]["callback_data"]
+        .as_str()
+        .unwrap()
+        .to_owned();
+    harness.press(&DAVE, &announcement, &plus_one).await;
+    assert_eq!(harness.last_edit(), "2 Ls have been awarded");
+}
+
+#[tokio::test]
+async fn disputed_ls_are_settled_by_a_vote() {
+    let harness = Harness::new().await;
+    harness.promote(&OWNER);
+    harness
+        .send(harness.message(&OWNER, "/settings disputevotes 2"))
+        .await;
+    harness.join(&BOB);
+    let bobs_message = harness.message(&BOB, "the earth is a cube");
+    harness
+        .send(harness.reply(&ALICE, "/givel", &bobs_message))
+        .await;
+    let announcement = harness.last_with_buttons();
+    let dispute = &harness.last_buttons()[2];
+
+    harness.press(&ALICE, &announcement, dispute).await;
+    assert_eq!(
+        harness.last_answer().as_deref(),
+        Some("You can't dispute an L you gave")
+    );
+    harness.press(&BOB, &announcement, dispute).await;
+    assert_eq!(harness.last_answer().as_deref(), Some("Vote opened"));
+    assert!(harness.last_message().starts_with("This L is disputed"));
+
+    let vote = harness.last_sent();
+    let drop = &harness.last_buttons()[1];
+    harness.press(&BOB, &vote, drop).await;
+    assert_eq!(
+        harness.last_answer().as_deref(),
+        Some("You can't vote on an L you gave or received")
+    );
+    harness.press(&CAROL, &vote, drop).await;
+    assert!(harness.last_edit().contains("drop: 1"));
+    harness.press(&DAVE, &vote, drop).await;
+    assert_eq!(harness.last_edit(), "The L was dropped after a vote");
+
+    harness
+        .send(harness.message(&ALICE, "/viewscoreboard"))
+        .await;
+    assert_eq!(harness.last_message(), "Scoreboard is empty\\!");
+}
+
+#[tokio::test]
+async fn members_cannot_give_themselves_an_l() {
+    let harness = Harness::new().await;
+    let own_message = harness.message(&ALICE, "i'm great");
+
+    harness
+        .send(harness.reply(&ALICE, "/givel", &own_message))
+        .await;
+    assert_eq!(harness.last_message(), "You can't give yourself an L");
+}
+
+#[tokio::test]
+async fn ls_count_once_the_jury_approves_them() {
+    let harness = Harness::new().await;
+    harness.promote(&OWNER);
+    for setting in ["jury on", "juryvotes 2", "jurytime 1440"] {
+        harness
+            .send(harness.message(&OWNER, &format!("/settings {}", setting)))
+            .await;
+    }
+    harness.join(&BOB);
+    let bobs_message = harness.message(&BOB, "i put milk in first");
+    harness
+        .send(harness.reply(&ALICE, "/givel", &bobs_message))
+        .await;
+    assert!(harness
+        .last_message()
+        .starts_with("This L counts once 2 members approve it"));
+    let vote = harness.last_sent();
+    let approve = &harness.last_buttons()[0];
+
+    harness.press(&BOB, &vote, approve).await;
+    assert_eq!(
+        harness.last_answer().as_deref(),
+        Some("You can't vote on an L you gave or received")
+    );
+    harness.press(&CAROL, &vote, approve).await;
+    assert!(harness.last_edit().contains("Approved: 1"));
+    harness
+        .send(harness.message(&ALICE, "/viewscoreboard"))
+        .await;
+    assert_eq!(harness.last_message(), "Scoreboard is empty\\!");
+
+    harness.press(&DAVE, &vote, approve).await;
+    assert_eq!(
+        harness.last_edit(),
+        "The jury approved this L, so it counts"
+    );
+    harness
+        .send(harness.message(&ALICE, "/viewscoreboard"))
+        .await;
+    assert_eq!(harness.last_message(), "__Bob__ 🥉 — *1* Ls");
+}
+
+#[tokio::test]
+async fn ls_the_jury_doesnt_approve_in_time_are_discarded() {
+    let harness = Harness::new().await;
+    harness.promote(&OWNER);
+    harness
+        .send(harness.message(&OWNER, "/settings jury on"))
+        .await;
+    harness.join(&BOB);
+    let bobs_message = harness.message(&BOB, "cereal is soup");
+    harness
+        .send(harness.reply(&ALICE, "/givel", &bobs_message))
+        .await;
+    let vote = harness.last_sent();
+    let approve = &harness.last_buttons()[0];
+    harness.press(&CAROL, &vote, approve).await;
+
+    let two_hours_later = unix_time() + 2 * 60 * 60;
+    assert!(jury::expire(&harness.bot, two_hours_later).await.unwrap() >= 1);
+    assert_eq!(
+        harness.last_edit(),
+        "Not enough members approved this L in time, so it doesn't count"
+    );
+
+    harness.press(&DAVE, &vote, approve).await;
+    assert_eq!(harness.last_answer().as_deref(), Some("This vote is over"));
+    harness
+        .send(harness.message(&ALICE, "/viewscoreboard"))
+        .await;
+    assert_eq!(harness.last_message(), "Scoreboard is empty\\!");
+}
+
+#[tokio::test]
+async fn reacting_with_the_chat_emoji_awards_an_l() {
+    let harness = Harness::new().await;
+    harness.promote(&OWNER);
+    harness
+        .send(harness.message(&OWNER, "/settings reaction 🤡"))
+        .await;
+    assert_eq!(harness.last_message(), "reaction is now 🤡");
+    harness.join(&BOB);
+    let bobs_message = harness.message(&BOB, "pineapple belongs on pizza");
+    harness.send(bobs_message.clone()).await;
+
+    harness.react(&ALICE, &bobs_message, &[], &["👍"]).await;
+    harness.react(&ALICE, &bobs_message, &["👍"], &["🤡"]).await;
+    harness
+        .send(harness.message(&ALICE, "/viewscoreboard"))
+        .await;
+    assert_eq!(harness.last_message(), "__Bob__ 🥉 — *1* Ls");
+
+    harness.react(&ALICE, &bobs_message, &["🤡"], &[]).await;
+    harness
+        .send(harness.message(&ALICE, "/viewscoreboard"))
+        .await;
+    assert_eq!(harness.last_message(), "Scoreboard is empty\\!");
+}
+
+#[tokio::test]
+async fn reactions_follow_the_givel_rules() {
+    let harness = Harness::new().await;
+    harness.promote(&OWNER);
+    for setting in ["reaction 🤡", "cooldown 60"] {
+        harness
+            .send(harness.message(&OWNER, &format!("/settings {}", setting)))
+            .await;
+    }
+    let alices_message = harness.message(&ALICE, "i'm great");
+    harness.send(alices_message.clone()).await;
+
+    harness.react(&ALICE, &alices_message, &[], &["🤡"]).await;
+    assert_eq!(harness.last_message(), "You can't give yourself an L");
+
+    harness.join(&BOB);
+    let bobs_message = harness.message(&BOB, "first");
+    harness.send(bobs_message.clone()).await;
+    harness
+        .send(harness.reply(&ALICE, "/givel", &bobs_message))
+        .await;
+    harness.react(&ALICE, &bobs_message, &[], &["🤡"]).await;
+    assert!(harness
+        .last_message()
+        .starts_with("You can give another L in"));
+}
+
+#[tokio::test]
+async fn reactions_award_nothing_unless_configured() {
+    let harness = Harness::new().await;
+    harness.join(&BOB);
+    let bobs_message = harness.message(&BOB, "tabs over spaces");
+    harness.send(bobs_message.clone()).await;
+    let sent = harness.sent_messages().len();
+
+    harness.react(&ALICE, &bobs_message, &[], &["🤡"]).await;
+    assert_eq!(harness.sent_messages().len(), sent);
+    harness
+        .send(harness.message(&ALICE, "/viewscoreboard"))
+        .await;
+    assert_eq!(harness.last_message(), "Scoreboard is empty\\!");
+}
+
+#[tokio::test]
+async fn chatter_is_deleted_after_the_chats_delays() {
+    let harness = Harness::new().await;
+    harness.promote(&OWNER);
+    for setting in ["deletecommands 60", "deleteconfirmations 600"] {
+        harness
+            .send(harness.message(&OWNER, &format!("/settings {}", setting)))
+            .await;
+    }
+    harness.join(&BOB);
+    let bobs_message = harness.message(&BOB, "i forgot my keys again");
+    let givel = harness.reply(&ALICE, "/givel", &bobs_message);
+    harness.send(givel.clone()).await;
+    let announcement = harness.last_with_buttons()["message_id"].clone();
+    // Someone beat the bot to it
+    harness
+        .api
+        .delete_message(harness.chat_id, announcement.as_i64().unwrap());
+
+    let deleted = |harness: &Harness| -> Vec<_> {
+        harness
+            .requests("deletemessage")
+            .into_iter()
+            .filter(|request| request.body["chat_id"] == harness.chat_id)
+            .map(|request| request.body["message_id"].clone())
+            .collect()
+    };
+    cleanup::sweep(&harness.bot, unix_time() + 120)
+        .await
+        .unwrap();
+    assert!(deleted(&harness).contains(&givel["message_id"]));
+    assert!(!deleted(&harness).contains(&announcement));
+
+    cleanup::sweep(&harness.bot, unix_time() + 1200)
+        .await
+        .unwrap();
+    assert!(deleted(&harness).contains(&announcement));
+    let tried = deleted(&harness).len();
+    cleanup::sweep(&harness.bot, unix_time() + 1200)
+        .await
+        .unwrap();
+    assert_eq!(deleted(&harness).len(), tried);
+}
@@ -0,0 +1,106 @@
+mod common;
+
+use common::{Harness, ALICE, BOB};
+use gustyfring::{db::pool::db, health, webhook};
+use reqwest::StatusCode;
+use std::net::{SocketAddr, TcpListener};
+
+fn free_address() -> SocketAddr {
+    TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+}
+
+/// The value of the metric sample `sample`, name and labels as written in
+/// `/metrics`, or 0 if it hasn't been recorded yet.
+async fn scrape(address: SocketAddr, sample: &str) -> u64 {
+    let metrics = reqwest::get(format!("http://{}/metrics", address))
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+
+    metrics
+        .lines()
+        .find_map(|line| line.strip_prefix(sample)?.strip_prefix(' '))
+        .map_or(0, |value| value.parse().unwrap())
+}
+
+async fn healthz(address: SocketAddr) -> (StatusCode, String) {
+    let response = reqwest::get(format!("http://{}/healthz", address))
+        .await
+        .unwrap();
+    (response.status(), response.text().await.unwrap())
+}
+
+// One test, since it ends by closing the database the whole file shares
+#[tokio::test]
+async fn health_server_reports_counters_and_what_is_down() {
+    let harness = Harness::new().await;
+    let address = free_address();
+    tokio::spawn(webhook::serve(
+        health::router(),
+        address,
+        futures::future::pending(),
+    ));
+    // Give the server a moment to bind
+    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+
+    const HELP: &str = r#"gus_commands_handled_total{command="help"}"#;
+    const MATCHES: &str = "gus_fallback_matches_total";
+    const MISSES: &str = "gus_fallback_misses_total";
+    const LS: &str = r#"gus_awards_total{award_type="l"}"#;
+    const WS: &str = r#"gus_awards_total{award_type="w"}"#;
+    let (help, matches, misses, ls, ws) = (
+        scrape(address, HELP).await,
+        scrape(address, MATCHES).await,
+        scrape(address, MISSES).await,
+        scrape(address, LS).await,
+        scrape(address, WS).await,
+    );
+    harness.send(harness.message(&ALICE, "/help")).await;
+    harness
+        .send(harness.message(&ALICE, "/learn health check | all good"))
+        .await;
+    harness.send(harness.message(&BOB, "health check")).await;
+    harness
+        .send(harness.message(&BOB, "nothing learned for this"))
+        .await;
+    assert_eq!(scrape(address, HELP).await, help + 1);
+    assert_eq!(scrape(address, MATCHES).await, matches + 1);
+    assert_eq!(scrape(address, MISSES).await, misses + 1);
+
+    let bobs_message = harness.message(&BOB, "i fixed the build");
+    harness
+        .send(harness.reply(&ALICE, "/givew", &bobs_message))
+        .await;
+    assert_eq!(scrape(address, WS).await, ws + 1);
+    assert_eq!(scrape(address, LS).await, ls);
+
+    assert_eq!(
+        healthz(address).await,
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            String::from("database: ok\ndispatcher: down\n")
+        )
+    );
+    health::set_dispatcher_alive(true);
+    assert_eq!(
+        healthz(address).await,
+        (
+            StatusCode::OK,
+            String::from("database: ok\ndispatcher: ok\n")
+        )
+    );
+
+    db().close().await;
+    assert_eq!(
+        healthz(address).await,
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            String::from("database: down\ndispatcher: ok\n")
+        )
+    );
+}
@@ -0,0 +1,158 @@
+mod common;
+
+use common::{Harness, TestUser, ALICE, BOB, OWNER};
+use gustyfring::{
+    award_types::L,
+    db::{pool::db, repo::AwardRepo},
+    inline::{self, Query},
+};
+use serde_json::Value;
+use teloxide::types::{ChatId, UserId};
+
+/// Only seen by this file's tests, so the chats she has scores in are known
+const ERIN: TestUser = TestUser {
+    id: 105,
+    first_name: "Erin",
+    username: "erin",
+};
+
+/// Only seen by this file's tests, like Erin
+const FRANK: TestUser = TestUser {
+    id: 106,
+    first_name: "Frank",
+    username: "frank",
+};
+
+fn last_results(harness: &Harness) -> Vec<Value> {
+    let answer = harness.requests("answerinlinequery").pop().unwrap();
+    assert_eq!(answer.body["is_personal"], true);
+    answer.body["results"].as_array().unwrap().clone()
+}
+
+#[tokio::test]
+async fn inline_scores_only_show_chats_the_member_is_in() {
+    let harness = Harness::new().await;
+    let other = Harness::new().await;
+    harness.join(&ERIN);
+    harness.join(&BOB);
+    let erins_message = harness.message(&ERIN, "i locked myself out");
+    harness
+        .send(harness.reply(&ALICE, "/givel for the keys", &erins_message))
+        .await;
+    let bobs_message = harness.message(&BOB, "me too");
+    for giver in [&ALICE, &ERIN] {
+        harness
+            .send(harness.reply(giver, "/givel", &bobs_message))
+            .await;
+    }
+    // Erin isn't a member of the other chat as far as this bot can tell
+    let erins_other_message = other.message(&ERIN, "hello");
+    other
+        .send(other.reply(&ALICE, "/givel", &erins_other_message))
+        .await;
+
+    harness.inline(&ERIN, "scores").await;
+    let results = last_results(&harness);
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0]["title"], "Scoreboard of Test chat");
+    assert_eq!(results[0]["description"], "Bob 2 · Erin 1");
+    assert_eq!(
+        results[0]["input_message_content"]["message_text"],
+        "*Scoreboard of Test chat*\n1\\. __Bob__ — *2* Ls\n2\\. __Erin__ — *1* Ls"
+    );
+    assert_eq!(
+        results[0]["input_message_content"]["parse_mode"],
+        "MarkdownV2"
+    );
+
+    harness.leave(&ERIN);
+    harness.inline(&ERIN, "").await;
+    assert!(last_results(&harness).is_empty());
+}
+
+#[tokio::test]
+async fn inline_scores_skip_chats_left_before_counting_results() {
+    let harness = Harness::new().await;
+    harness.join(&FRANK);
+    let franks_message = harness.message(&FRANK, "i forgot the cake");
+    harness
+        .send(harness.reply(&ALICE, "/givel", &franks_message))
+        .await;
+    // Frank has more old chats than fit in one answer, and left them all
+    let awards = AwardRepo::new(db());
+    for i in 0..inline::MAX_RESULTS as i64 + 5 {
+        awards
+            .award(
+                ChatId(-9_000_000 - i),
+                L,
+                UserId(ALICE.id),
+                UserId(FRANK.id),
+                None,
+            )
+            .await
+            .unwrap();
+    }
+
+    harness.inline(&FRANK, "scores").await;
+    let results = last_results(&harness);
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0]["title"], "Scoreboard of Test chat");
+}
+
+#[tokio::test]
+async fn inline_scores_match_the_chats_scoreboard() {
+    let harness = Harness::new().await;
+    harness.promote(&OWNER);
+    harness.join(&BOB);
+    let bobs_message = harness.message(&BOB, "i parked in the fire lane");
+    harness
+        .send(harness.reply(&ALICE, "/givel", &bobs_message))
+        .await;
+    harness
+        .send(harness.reply(&OWNER, "/give w", &bobs_message))
+        .await;
+    harness
+        .send(harness.message(&OWNER, "/settings decay halflife"))
+        .await;
+
+    harness.inline(&BOB, "scores").await;
+    let results = last_results(&harness);
+    assert_eq!(results[0]["description"], "Bob net 0");
+    assert_eq!(
+        results[0]["input_message_content"]["message_text"],
+        "*Scoreboard of Test chat*\n1\\. __Bob__ — 🤡 *1* · 👑 *1* · net *0*\n\n\
+         _Awards count half as much every 30 days_"
+    );
+}
+
+#[tokio::test]
+async fn inline_phrases_insert_learned_responses() {
+    let harness = Harness::new().await;
+    harness
+        .send(harness.message(&ALICE, "/learn inline hello there | General Kenobi"))
+        .await;
+
+    harness.inline(&BOB, "phrase Inline hello there").await;
+    let results = last_results(&harness);
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0]["title"], "General Kenobi");
+    assert_eq!(
+        results[0]["input_message_content"]["message_text"],
+        "General Kenobi"
+    );
+
+    harness.inline(&BOB, "phrase never learned inline").await;
+    assert!(last_results(&harness).is_empty());
+}
+
+#[test]
+fn inline_queries_are_read_case_insensitively() {
+    assert_eq!(inline::parse(""), Query::Scores);
+    assert_eq!(inline::parse(" Scores "), Query::Scores);
+    assert_eq!(
+        inline::parse("PHRASE  good  morning "),
+        Query::Phrase("good  morning")
+    );
+    assert_eq!(inline::parse("phrase"), Query::Unknown);
+    assert_eq!(inline::parse("weather"), Query::Unknown);
+}
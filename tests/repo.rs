@@ -0,0 +1,708 @@
+use gustyfring::{
+    award_types::{L, W},
+    db::{
+        models::{AwardType, Role},
+        pool::{self, Pool},
+        repo::{
+            AwardRepo, AwardTypeRepo, BadgeRepo, DeletionRepo, DigestRepo, DisputeRepo, JobRepo,
+            JuryRepo, MemberRepo, PhraseRepo, PollRepo, ReactionRepo, RoleRepo, SettingsRepo,
+            StatsRepo,
+        },
+    },
+};
+use sqlx::{any::AnyPoolOptions, Executor};
+use teloxide::types::{ChatId, MessageId, UserId};
+
+const CHAT: ChatId = ChatId(-100);
+const OTHER_CHAT: ChatId = ChatId(-200);
+const ALICE: UserId = UserId(1);
+const BOB: UserId = UserId(2);
+const CAROL: UserId = UserId(3);
+const DAVE: UserId = UserId(4);
+
+/// A freshly migrated database. With `TEST_DATABASE_URL` set, e.g. to a
+/// locally started Postgres server, tests run there instead of against an
+/// in-memory SQLite database.
+async fn pool() -> Pool {
+    let pool = match std::env::var("TEST_DATABASE_URL") {
+        Ok(url) => isolated_schema(&url).await,
+        Err(_) => pool::connect("sqlite::memory:").await.unwrap(),
+    };
+    pool::migrate_pool(&pool).await.unwrap();
+    pool
+}
+
+/// Connects to a schema of its own on a shared Postgres server, so tests
+/// neither see each other's rows nor leftovers from earlier runs.
+async fn isolated_schema(url: &str) -> Pool {
+    let schema = format!(
+        "test_{}",
+        nanoid::nanoid!(10, &('a'..='z').collect::<Vec<_>>())
+    );
+    pool::connect(url)
+        .await
+        .unwrap()
+        .execute(format!("CREATE SCHEMA {}", schema).as_str())
+        .await
+        .unwrap();
+
+    AnyPoolOptions::new()
+        .max_connections(5)
+        .after_connect(move |conn, _| {
+            let statement = format!("SET search_path TO {}", schema);
+            Box::pin(async move { conn.execute(statement.as_str()).await.map(|_| ()) })
+        })
+        .connect(url)
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn award_counts_per_chat() {
+    let pool = pool().await;
+    let awards = AwardRepo::new(&pool);
+
+    assert_eq!(
+        awards.award(CHAT, L, BOB, ALICE, None).await.unwrap().total,
+        1
+    );
+    assert_eq!(
+        awards.award(CHAT, L, BOB, ALICE, None).await.unwrap().total,
+        2
+    );
+    assert_eq!(
+        awards
+            .award(OTHER_CHAT, L, BOB, ALICE, None)
+            .await
+            .unwrap()
+            .total,
+        1
+    );
+    awards.award(CHAT, L, ALICE, BOB, None).await.unwrap();
+
+    let scoreboard = awards.scoreboard(CHAT).await.unwrap();
+    let scores = scoreboard
+        .iter()
+        .map(|stat| (stat.tg_user_id.as_str(), stat.amount))
+        .collect::<Vec<_>>();
+    assert_eq!(scores, [("1", 2), ("2", 1)]);
+}
+
+#[tokio::test]
+async fn award_types_are_scored_separately() {
+    let pool = pool().await;
+    let awards = AwardRepo::new(&pool);
+    awards.award(CHAT, L, ALICE, BOB, None).await.unwrap();
+    awards.award(CHAT, L, CAROL, BOB, None).await.unwrap();
+    let w = awards.award(CHAT, W, ALICE, BOB, None).await.unwrap();
+    assert_eq!(w.total, 1);
+
+    assert_eq!(awards.scoreboard(CHAT).await.unwrap()[0].amount, 2);
+    assert_eq!(awards.scoreboard_of(CHAT, W).await.unwrap()[0].amount, 1);
+    let scores = awards
+        .scores(CHAT)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|score| (score.award_type, score.amount))
+        .collect::<Vec<_>>();
+    assert_eq!(scores, [(String::from(L), 2), (String::from(W), 1)]);
+
+    // Taking back a W leaves the Ls alone
+    let w = awards.get(w.id).await.unwrap().unwrap();
+    assert_eq!(w.award_type, W);
+    awards.revoke(&w).await.unwrap();
+    assert!(awards.scoreboard_of(CHAT, W).await.unwrap().is_empty());
+    assert_eq!(awards.scoreboard(CHAT).await.unwrap()[0].amount, 2);
+    // Stats only count Ls
+    assert_eq!(StatsRepo::new(&pool).given(CHAT, ALICE).await.unwrap(), 1);
+}
+
+#[tokio::test]
+async fn received_awards_are_grouped_by_member_and_type() {
+    let pool = pool().await;
+    let awards = AwardRepo::new(&pool);
+    awards.award(CHAT, W, ALICE, CAROL, None).await.unwrap();
+    awards.award(CHAT, L, ALICE, BOB, None).await.unwrap();
+    awards.award(CHAT, W, CAROL, BOB, None).await.unwrap();
+    awards.award(CHAT, L, CAROL, BOB, None).await.unwrap();
+
+    let received = awards
+        .received_at(CHAT)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|award| (award.tg_user_id, award.award_type))
+        .collect::<Vec<_>>();
+    let bob = BOB.to_string();
+    let carol = CAROL.to_string();
+    assert_eq!(
+        received,
+        [
+            (bob.clone(), String::from(L)),
+            (bob.clone(), String::from(L)),
+            (bob, String::from(W)),
+            (carol, String::from(W)),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn undated_scores_are_what_recorded_awards_leave_out() {
+    let pool = pool().await;
+    let awards = AwardRepo::new(&pool);
+    awards.award(CHAT, L, ALICE, BOB, None).await.unwrap();
+    awards.award(CHAT, W, ALICE, BOB, None).await.unwrap();
+    // Two of Bob's Ls were counted before awards were recorded
+    sqlx::query("UPDATE Stat SET amount = amount + 2 WHERE award_type = $1")
+        .bind(L)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    let undated = awards
+        .undated(CHAT)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|stat| (stat.tg_user_id, stat.award_type, stat.amount))
+        .collect::<Vec<_>>();
+    assert_eq!(undated, [(BOB.to_string(), String::from(L), 2)]);
+}
+
+#[tokio::test]
+async fn awards_to_several_members_are_recorded_together() {
+    let pool = pool().await;
+    let awards = AwardRepo::new(&pool);
+    awards.award(CHAT, L, DAVE, BOB, None).await.unwrap();
+
+    let awarded = awards
+        .award_many(CHAT, L, ALICE, &[BOB, CAROL], Some("for the meeting"))
+        .await
+        .unwrap();
+    assert_eq!(
+        awarded
+            .iter()
+            .map(|awarded| awarded.total)
+            .collect::<Vec<_>>(),
+        [2, 1]
+    );
+    let carols = awards.get(awarded[1].id).await.unwrap().unwrap();
+    assert_eq!(carols.reason.as_deref(), Some("for the meeting"));
+    assert_eq!(carols.parent_id, None);
+}
+
+#[tokio::test]
+async fn chats_of_a_member_are_where_they_gave_or_got_awards() {
+    let pool = pool().await;
+    let awards = AwardRepo::new(&pool);
+    awards.award(CHAT, L, ALICE, BOB, None).await.unwrap();
+    awards.award(CHAT, W, CAROL, BOB, None).await.unwrap();
+    awards.award(OTHER_CHAT, L, BOB, CAROL, None).await.unwrap();
+
+    assert_eq!(awards.chats_of(BOB).await.unwrap(), [OTHER_CHAT, CHAT]);
+    assert_eq!(awards.chats_of(ALICE).await.unwrap(), [CHAT]);
+    assert!(awards.chats_of(DAVE).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn poll_votes_last_until_they_are_retracted() {
+    let pool = pool().await;
+    let polls = PollRepo::new(&pool);
+    polls.add(CHAT, MessageId(7), "poll").await.unwrap();
+    assert_eq!(polls.voters(CHAT, MessageId(8)).await.unwrap(), None);
+
+    for voter in [CAROL, ALICE, BOB, ALICE] {
+        polls.vote("poll", voter, true).await.unwrap();
+    }
+    polls.vote("poll", BOB, false).await.unwrap();
+    // Votes in polls the bot didn't send are ignored
+    polls.vote("other poll", DAVE, true).await.unwrap();
+    assert_eq!(
+        polls.voters(CHAT, MessageId(7)).await.unwrap(),
+        Some(vec![ALICE, CAROL])
+    );
+}
+
+#[tokio::test]
+async fn award_types_are_stored_per_chat() {
+    let pool = pool().await;
+    let types = AwardTypeRepo::new(&pool);
+    let cookie = AwardType {
+        chat_id: CHAT.0,
+        code: String::from("k"),
+        name: String::from("Cookie"),
+        emoji: String::from("🍪"),
+        weight: 2,
+    };
+    types.set(&cookie).await.unwrap();
+    types
+        .set(&AwardType {
+            weight: 3,
+            ..cookie.clone()
+        })
+        .await
+        .unwrap();
+
+    let stored = types.stored(CHAT).await.unwrap();
+    assert_eq!(stored.len(), 1);
+    assert_eq!(stored[0].weight, 3);
+    assert!(types.stored(OTHER_CHAT).await.unwrap().is_empty());
+
+    assert!(!types.remove(OTHER_CHAT, "k").await.unwrap());
+    assert!(types.remove(CHAT, "k").await.unwrap());
+    assert!(types.stored(CHAT).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn award_keeps_remembered_names() {
+    let pool = pool().await;
+    MemberRepo::new(&pool)
+        .remember(BOB, "Bob", None)
+        .await
+        .unwrap();
+
+    let awards = AwardRepo::new(&pool);
+    awards.award(CHAT, L, ALICE, BOB, None).await.unwrap();
+
+    let scoreboard = awards.scoreboard(CHAT).await.unwrap();
+    assert_eq!(scoreboard[0].first_name.as_deref(), Some("Bob"));
+}
+
+#[tokio::test]
+async fn usernames_belong_to_whoever_was_seen_with_them_last() {
+    let pool = pool().await;
+    let members = MemberRepo::new(&pool);
+    members.remember(BOB, "Bob", Some("bobby")).await.unwrap();
+
+    let found = members.by_username("Bobby").await.unwrap().unwrap();
+    assert_eq!(found.id, BOB.0 as i64);
+    assert_eq!(found.first_name.as_deref(), Some("Bob"));
+
+    members
+        .remember(CAROL, "Carol", Some("bobby"))
+        .await
+        .unwrap();
+    let found = members.by_username("bobby").await.unwrap().unwrap();
+    assert_eq!(found.id, CAROL.0 as i64);
+    assert!(members.by_username("nobody").await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn stats_list_the_ls_a_member_received_and_gave() {
+    let pool = pool().await;
+    let awards = AwardRepo::new(&pool);
+    let stats = StatsRepo::new(&pool);
+    MemberRepo::new(&pool)
+        .remember(ALICE, "Alice", None)
+        .await
+        .unwrap();
+    awards
+        .award(CHAT, L, ALICE, BOB, Some("burnt toast"))
+        .await
+        .unwrap();
+    awards.award(CHAT, L, CAROL, BOB, None).await.unwrap();
+    awards.award(CHAT, L, BOB, ALICE, None).await.unwrap();
+    awards.award(OTHER_CHAT, L, ALICE, BOB, None).await.unwrap();
+
+    let received = stats.received(CHAT, BOB).await.unwrap();
+    assert_eq!(
+        received
+            .iter()
+            .map(|award| award.giver_id)
+            .collect::<Vec<_>>(),
+        [ALICE.0 as i64, CAROL.0 as i64]
+    );
+    assert_eq!(received[0].giver_first_name.as_deref(), Some("Alice"));
+    assert_eq!(received[0].reason.as_deref(), Some("burnt toast"));
+    assert_eq!(stats.given(CHAT, BOB).await.unwrap(), 1);
+    assert_eq!(stats.given(CHAT, DAVE).await.unwrap(), 0);
+}
+
+#[tokio::test]
+async fn badges_are_granted_once_per_chat() {
+    let pool = pool().await;
+    let badges = BadgeRepo::new(&pool);
+    assert!(badges.grant(CHAT, BOB, "first-l").await.unwrap());
+    assert!(!badges.grant(CHAT, BOB, "first-l").await.unwrap());
+    assert!(badges.grant(OTHER_CHAT, BOB, "first-l").await.unwrap());
+    badges.grant(CHAT, CAROL, "10-ls").await.unwrap();
+
+    let earned = badges.earned(CHAT, BOB).await.unwrap();
+    assert_eq!(earned.len(), 1);
+    assert_eq!(earned[0].badge, "first-l");
+    assert_eq!(badges.earned_in_chat(CHAT).await.unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn reset_only_clears_one_chat() {
+    let pool = pool().await;
+    let awards = AwardRepo::new(&pool);
+    awards.award(CHAT, L, BOB, ALICE, None).await.unwrap();
+    awards.award(CHAT, L, ALICE, BOB, None).await.unwrap();
+    awards.award(OTHER_CHAT, L, ALICE, BOB, None).await.unwrap();
+
+    assert_eq!(awards.reset(CHAT).await.unwrap(), 2);
+    assert!(awards.scoreboard(CHAT).await.unwrap().is_empty());
+    assert_eq!(awards.scoreboard(OTHER_CHAT).await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn legacy_scores_are_moved_into_a_chat() {
+    let pool = pool().await;
+    let awards = AwardRepo::new(&pool);
+    awards.award(CHAT, L, ALICE, BOB, None).await.unwrap();
+    awards.award(ChatId(0), L, ALICE, BOB, None).await.unwrap();
+    awards
+        .award(ChatId(0), L, ALICE, CAROL, None)
+        .await
+        .unwrap();
+
+    assert_eq!(awards.assign_legacy(CHAT).await.unwrap(), 2);
+    let amounts = awards
+        .scoreboard(CHAT)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|stat| stat.amount)
+        .collect::<Vec<_>>();
+    assert_eq!(amounts, [2, 1]);
+    assert!(awards.scoreboard(ChatId(0)).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn revoking_an_award_takes_back_the_ls_added_to_it() {
+    let pool = pool().await;
+    let awards = AwardRepo::new(&pool);
+    let root = awards.award(CHAT, L, ALICE, BOB, None).await.unwrap();
+    let root = awards.get(root.id).await.unwrap().unwrap();
+    let added = awards.pile_on(&root, CAROL).await.unwrap();
+    assert_eq!(added.total, 2);
+    assert_eq!(awards.group_size(root.id).await.unwrap(), 2);
+    assert_eq!(
+        awards
+            .given_in_group(root.id, CAROL)
+            .await
+            .unwrap()
+            .unwrap()
+            .id,
+        added.id
+    );
+
+    let added = awards.get(added.id).await.unwrap().unwrap();
+    assert_eq!(awards.revoke(&added).await.unwrap(), 1);
+    assert_eq!(awards.scoreboard(CHAT).await.unwrap()[0].amount, 1);
+
+    awards.pile_on(&root, CAROL).await.unwrap();
+    assert_eq!(awards.revoke(&root).await.unwrap(), 2);
+    assert!(awards.scoreboard(CHAT).await.unwrap().is_empty());
+    assert_eq!(awards.group_size(root.id).await.unwrap(), 0);
+}
+
+#[tokio::test]
+async fn withdrawing_an_award_leaves_the_ls_added_to_it() {
+    let pool = pool().await;
+    let awards = AwardRepo::new(&pool);
+    let root = awards.award(CHAT, L, ALICE, BOB, None).await.unwrap();
+    let root = awards.get(root.id).await.unwrap().unwrap();
+    let first = awards.pile_on(&root, CAROL).await.unwrap();
+    let second = awards.pile_on(&root, DAVE).await.unwrap();
+
+    assert_eq!(awards.withdraw(&root).await.unwrap(), Some(first.id));
+    assert_eq!(awards.scoreboard(CHAT).await.unwrap()[0].amount, 2);
+    assert_eq!(awards.group_size(first.id).await.unwrap(), 2);
+
+    let second = awards.get(second.id).await.unwrap().unwrap();
+    assert_eq!(awards.withdraw(&second).await.unwrap(), Some(first.id));
+    let first = awards.get(first.id).await.unwrap().unwrap();
+    assert_eq!(awards.withdraw(&first).await.unwrap(), None);
+    assert!(awards.scoreboard(CHAT).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn dispute_votes_are_tallied_once_per_voter() {
+    let pool = pool().await;
+    let awarded = AwardRepo::new(&pool)
+        .award(CHAT, L, ALICE, BOB, None)
+        .await
+        .unwrap();
+    let disputes = DisputeRepo::new(&pool);
+
+    assert!(disputes.open(awarded.id, BOB).await.unwrap());
+    assert!(!disputes.open(awarded.id, CAROL).await.unwrap());
+
+    disputes.vote(awarded.id, CAROL, false).await.unwrap();
+    let tally = disputes.vote(awarded.id, CAROL, true).await.unwrap();
+    assert_eq!((tally.keep_votes, tally.drop_votes), (1, 0));
+    let tally = disputes.vote(awarded.id, DAVE, false).await.unwrap();
+    assert_eq!((tally.keep_votes, tally.drop_votes), (1, 1));
+
+    disputes.keep(awarded.id).await.unwrap();
+    let dispute = disputes.get(awarded.id).await.unwrap().unwrap();
+    assert_eq!(dispute.outcome.as_deref(), Some("kept"));
+}
+
+#[tokio::test]
+async fn pending_awards_are_confirmed_at_most_once() {
+    let pool = pool().await;
+    let jury = JuryRepo::new(&pool);
+    let confirmed = jury.propose(CHAT, L, ALICE, BOB, None, 100).await.unwrap();
+    let expired = jury.propose(CHAT, L, ALICE, BOB, None, 50).await.unwrap();
+
+    let tally = jury.vote(confirmed, CAROL, true).await.unwrap();
+    assert_eq!((tally.approvals, tally.rejections), (1, 0));
+    let pending = jury.get(confirmed).await.unwrap().unwrap();
+    assert_eq!(jury.confirm(&pending).await.unwrap().unwrap().total, 1);
+    assert!(jury.confirm(&pending).await.unwrap().is_none());
+
+    let closed = jury.closed(75).await.unwrap();
+    assert_eq!(
+        closed.iter().map(|pending| pending.id).collect::<Vec<_>>(),
+        [expired]
+    );
+    assert!(jury.discard(expired).await.unwrap());
+    assert!(!jury.discard(expired).await.unwrap());
+    assert_eq!(
+        AwardRepo::new(&pool).scoreboard(CHAT).await.unwrap()[0].amount,
+        1
+    );
+}
+
+#[tokio::test]
+async fn reactions_award_once_per_reactor_and_message() {
+    let pool = pool().await;
+    let reactions = ReactionRepo::new(&pool);
+    let message = MessageId(7);
+    reactions.remember_author(CHAT, message, BOB).await.unwrap();
+    assert_eq!(reactions.author(CHAT, message).await.unwrap(), Some(BOB));
+    assert_eq!(reactions.author(OTHER_CHAT, message).await.unwrap(), None);
+
+    let awarded = reactions.award(CHAT, message, ALICE, BOB).await.unwrap();
+    assert_eq!(awarded.unwrap().total, 1);
+    assert!(reactions
+        .award(CHAT, message, ALICE, BOB)
+        .await
+        .unwrap()
+        .is_none());
+    let award_id = reactions.award_id(CHAT, message, ALICE).await.unwrap();
+    assert_eq!(award_id, Some(awarded.unwrap().id));
+
+    let awards = AwardRepo::new(&pool);
+    let award = awards.get(award_id.unwrap()).await.unwrap().unwrap();
+    awards.revoke(&award).await.unwrap();
+    assert_eq!(
+        reactions.award_id(CHAT, message, ALICE).await.unwrap(),
+        None
+    );
+}
+
+#[tokio::test]
+async fn deletions_come_due_in_order() {
+    let pool = pool().await;
+    let deletions = DeletionRepo::new(&pool);
+    deletions.schedule(CHAT, MessageId(2), 200).await.unwrap();
+    deletions.schedule(CHAT, MessageId(1), 100).await.unwrap();
+    deletions.schedule(CHAT, MessageId(1), 300).await.unwrap();
+    deletions
+        .schedule(OTHER_CHAT, MessageId(1), 400)
+        .await
+        .unwrap();
+
+    let due = deletions.due(250, 10).await.unwrap();
+    assert_eq!(
+        due.iter()
+            .map(|due| (due.message_id, due.delete_at))
+            .collect::<Vec<_>>(),
+        [(1, 100), (2, 200)]
+    );
+    deletions.remove(CHAT, MessageId(1)).await.unwrap();
+    assert_eq!(deletions.due(250, 10).await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn jobs_are_held_by_one_bot_at_a_time() {
+    let pool = pool().await;
+    let jobs = JobRepo::new(&pool);
+    jobs.register("digest").await.unwrap();
+
+    assert!(jobs.claim("digest", 60, "a", 1000, 1600).await.unwrap());
+    assert!(!jobs.claim("digest", 60, "b", 1000, 1600).await.unwrap());
+    jobs.finish("digest", "a", 1000, None).await.unwrap();
+    assert!(!jobs.claim("digest", 60, "b", 1059, 1659).await.unwrap());
+    assert!(jobs.claim("digest", 60, "b", 1060, 1660).await.unwrap());
+
+    // A bot that died holding the job is taken over once its lock runs out
+    assert!(jobs.claim("digest", 60, "c", 1660, 2260).await.unwrap());
+    jobs.finish("digest", "b", 1060, Some("late"))
+        .await
+        .unwrap();
+    let job = jobs.get("digest").await.unwrap().unwrap();
+    assert_eq!(job.locked_by.as_deref(), Some("c"));
+    assert_eq!(job.last_run_at, Some(1000));
+}
+
+#[tokio::test]
+async fn digests_count_recent_awards_and_reasons() {
+    let pool = pool().await;
+    let awards = AwardRepo::new(&pool);
+    let digests = DigestRepo::new(&pool);
+    awards
+        .award(CHAT, L, ALICE, BOB, Some("burnt toast"))
+        .await
+        .unwrap();
+    let root = awards
+        .award(CHAT, L, BOB, CAROL, Some("fell asleep"))
+        .await
+        .unwrap();
+    let root = awards.get(root.id).await.unwrap().unwrap();
+    awards.pile_on(&root, DAVE).await.unwrap();
+    awards.award(OTHER_CHAT, L, ALICE, BOB, None).await.unwrap();
+
+    let received = digests.received_since(CHAT, 0).await.unwrap();
+    assert_eq!(
+        received
+            .iter()
+            .map(|count| (count.member_id, count.ls))
+            .collect::<Vec<_>>(),
+        [(CAROL.0 as i64, 2), (BOB.0 as i64, 1)]
+    );
+    let reasons = digests.top_reasons(CHAT, 0, 1).await.unwrap();
+    assert_eq!(reasons[0].reason, "fell asleep");
+    assert_eq!(reasons[0].ls, 2);
+    assert!(digests
+        .received_since(CHAT, i64::MAX)
+        .await
+        .unwrap()
+        .is_empty());
+
+    assert_eq!(digests.last_posted(CHAT).await.unwrap(), None);
+    digests.mark_posted(CHAT, 100).await.unwrap();
+    digests.mark_posted(CHAT, 200).await.unwrap();
+    assert_eq!(digests.last_posted(CHAT).await.unwrap(), Some(200));
+}
+
+#[tokio::test]
+async fn learning_a_phrase_twice_adds_responses() {
+    let pool = pool().await;
+    let phrases = PhraseRepo::new(&pool);
+
+    phrases
+        .learn(ALICE, "hello there", "general kenobi")
+        .await
+        .unwrap();
+    phrases.learn(BOB, "hello there", "hi").await.unwrap();
+
+    let mut responses = phrases
+        .responses("hello there")
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|turn| turn.response)
+        .collect::<Vec<_>>();
+    responses.sort();
+    assert_eq!(responses, ["general kenobi", "hi"]);
+    assert!(phrases.responses("goodbye").await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn grant_admin_is_idempotent() {
+    let pool = pool().await;
+    let members = MemberRepo::new(&pool);
+
+    members.grant_admin(ALICE).await.unwrap();
+    members.grant_admin(ALICE).await.unwrap();
+}
+
+#[tokio::test]
+async fn chat_roles_are_overridden_per_chat() {
+    let pool = pool().await;
+    let roles = RoleRepo::new(&pool);
+
+    roles
+        .set_chat_role(CHAT, ALICE, Some(Role::Member))
+        .await
+        .unwrap();
+    roles
+        .set_chat_role(CHAT, ALICE, Some(Role::ChatAdmin))
+        .await
+        .unwrap();
+    assert_eq!(
+        roles.chat_role(CHAT, ALICE).await.unwrap(),
+        Some(Role::ChatAdmin)
+    );
+    assert_eq!(roles.chat_role(OTHER_CHAT, ALICE).await.unwrap(), None);
+
+    roles.set_chat_role(CHAT, ALICE, None).await.unwrap();
+    assert_eq!(roles.chat_role(CHAT, ALICE).await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn changed_settings_are_kept_per_chat() {
+    let pool = pool().await;
+    let settings = SettingsRepo::new(&pool);
+
+    settings.set(CHAT, "cooldown", "30").await.unwrap();
+    settings.set(CHAT, "cooldown", "60").await.unwrap();
+    settings.set(OTHER_CHAT, "nlu", "off").await.unwrap();
+
+    let changed = settings.changed(CHAT).await.unwrap();
+    assert_eq!(changed.len(), 1);
+    assert_eq!(
+        (changed[0].name.as_str(), changed[0].value.as_str()),
+        ("cooldown", "60")
+    );
+}
+
+#[tokio::test]
+async fn forgetting_a_phrase_removes_its_responses() {
+    let pool = pool().await;
+    let phrases = PhraseRepo::new(&pool);
+    phrases.learn(ALICE, "bye", "see ya").await.unwrap();
+
+    assert!(phrases.forget("bye").await.unwrap());
+    assert!(!phrases.forget("bye").await.unwrap());
+    assert!(phrases.responses("bye").await.unwrap().is_empty());
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn concurrent_awards_are_all_counted() {
+    const AWARDS: usize = 50;
+
+    // Separate connections to a file contend for locks the way the bot does,
+    // unlike the shared cache behind in-memory databases
+    let path = std::env::temp_dir().join(format!("gus-test-{}.sqlite3", nanoid::nanoid!()));
+    let pool = match std::env::var("TEST_DATABASE_URL") {
+        Ok(_) => pool().await,
+        Err(_) => {
+            let pool = pool::connect(&format!("sqlite://{}?mode=rwc", path.display()))
+                .await
+                .unwrap();
+            pool::migrate_pool(&pool).await.unwrap();
+            pool
+        }
+    };
+
+    let tasks = (0..AWARDS)
+        .map(|_| {
+            let pool = pool.clone();
+            tokio::spawn(async move {
+                let awarded = AwardRepo::new(&pool).award(CHAT, L, ALICE, BOB, None).await;
+                awarded.map(|awarded| awarded.total)
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let mut totals = Vec::new();
+    for task in tasks {
+        totals.push(task.await.unwrap().unwrap());
+    }
+    totals.sort();
+
+    // Every award saw a distinct total, so none of them were lost
+    assert_eq!(totals, (1..=AWARDS as i32).collect::<Vec<_>>());
+    let scoreboard = AwardRepo::new(&pool).scoreboard(CHAT).await.unwrap();
+    assert_eq!(scoreboard[0].amount, AWARDS as i32);
+
+    pool.close().await;
+    std::fs::remove_file(path).ok();
+}
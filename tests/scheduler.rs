@@ -0,0 +1,91 @@
+mod common;
+
+use common::Harness;
+use gustyfring::{
+    common::time::unix_time,
+    db::{pool::db, repo::JobRepo},
+    scheduler::Scheduler,
+};
+use miette::miette;
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+const MINUTE: Duration = Duration::from_secs(60);
+
+/// A scheduler with a job called `name` that counts its runs in `runs`.
+fn counting(harness: &Harness, name: &'static str, runs: &Arc<AtomicUsize>) -> Scheduler {
+    let runs = runs.clone();
+    Scheduler::new(harness.bot.clone()).every(name, MINUTE, move |_| {
+        let runs = runs.clone();
+        async move {
+            runs.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+    })
+}
+
+#[tokio::test]
+async fn jobs_run_once_per_interval() {
+    let harness = Harness::new().await;
+    let runs = Arc::new(AtomicUsize::new(0));
+    let scheduler = counting(&harness, "interval", &runs);
+    let now = unix_time();
+
+    assert_eq!(scheduler.run_pending(now).await.unwrap(), ["interval"]);
+    assert!(scheduler.run_pending(now + 30).await.unwrap().is_empty());
+    assert_eq!(scheduler.run_pending(now + 60).await.unwrap(), ["interval"]);
+    assert_eq!(runs.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn jobs_shared_by_several_bots_run_once() {
+    let harness = Harness::new().await;
+    let runs = Arc::new(AtomicUsize::new(0));
+    let bots = (0..4)
+        .map(|_| counting(&harness, "shared", &runs))
+        .collect::<Vec<_>>();
+    let now = unix_time();
+
+    futures::future::join_all(bots.iter().map(|bot| bot.run_pending(now))).await;
+    assert_eq!(runs.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn failed_runs_are_recorded() {
+    let harness = Harness::new().await;
+    let scheduler = Scheduler::new(harness.bot.clone())
+        .every("failing", MINUTE, |_| async { Err(miette!("out of Ls")) });
+    let now = unix_time();
+
+    assert_eq!(scheduler.run_pending(now).await.unwrap(), ["failing"]);
+    let job = JobRepo::new(db()).get("failing").await.unwrap().unwrap();
+    assert_eq!(job.last_run_at, Some(now));
+    assert!(job.last_error.unwrap().contains("out of Ls"));
+    assert!(job.locked_by.is_none());
+    assert!(scheduler.run_pending(now + 1).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn started_jobs_run_until_shut_down() {
+    let harness = Harness::new().await;
+    let runs = Arc::new(AtomicUsize::new(0));
+    let scheduler = counting(&harness, "background", &runs)
+        .start()
+        .await
+        .unwrap();
+
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while runs.load(Ordering::SeqCst) == 0 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .unwrap();
+    scheduler.shutdown().await;
+    assert_eq!(runs.load(Ordering::SeqCst), 1);
+}
@@ -0,0 +1,140 @@
+mod common;
+
+use chrono_tz::Tz;
+use common::{Harness, ALICE, BOB, CAROL, DAVE};
+use gustyfring::{db::pool::db, stats};
+use serde_json::json;
+
+const DAY: i64 = 24 * 60 * 60;
+
+#[tokio::test]
+async fn mystats_shows_totals_givers_and_reasons() {
+    let harness = Harness::new().await;
+    harness.join(&BOB);
+    let bobs_message = harness.message(&BOB, "i put the milk in first");
+    for (giver, command) in [
+        (&ALICE, "/givel for the milk"),
+        (&CAROL, "/givel"),
+        (&ALICE, "/givel again"),
+    ] {
+        harness
+            .send(harness.reply(giver, command, &bobs_message))
+            .await;
+    }
+    let alices_message = harness.message(&ALICE, "tea is overrated");
+    harness
+        .send(harness.reply(&BOB, "/givel", &alices_message))
+        .await;
+
+    harness.send(harness.message(&BOB, "/mystats")).await;
+    let stats = harness.last_message();
+    assert!(stats.starts_with("*L stats for __Bob__*"));
+    assert!(stats.contains("Total: *3 Ls*, \\#1 in this chat"));
+    assert!(stats.contains("Given: 1 · Received: 3"));
+    assert!(stats.contains("Top giver: __Alice__ with 2 Ls"));
+    assert!(stats.contains("Longest streak: 1 day in a row"));
+    assert!(stats.contains("from __Alice__ “for the milk”"));
+    assert!(stats.contains("Latest L: "));
+    assert!(stats.contains("“again”"));
+    assert!(stats.contains("Last 30 days: ▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁█ \\(3 Ls, up from 0\\)"));
+}
+
+#[tokio::test]
+async fn stats_are_shown_for_whoever_is_replied_to_or_mentioned() {
+    let harness = Harness::new().await;
+    harness.join(&CAROL);
+    let carols_message = harness.message(&CAROL, "socks with sandals");
+    harness
+        .send(harness.reply(&ALICE, "/givel", &carols_message))
+        .await;
+
+    harness
+        .send(harness.reply(&DAVE, "/stats", &carols_message))
+        .await;
+    assert!(harness
+        .last_message()
+        .starts_with("*L stats for __Carol__*"));
+
+    harness.send(harness.message(&DAVE, "/stats @Carol")).await;
+    let stats = harness.last_message();
+    assert!(stats.starts_with("*L stats for __Carol__*"));
+    assert!(stats.contains("Total: *1 L*, \\#1 in this chat"));
+
+    let mut mention = harness.message(&DAVE, "/stats Alice");
+    mention["entities"] = json!([{
+        "type": "text_mention",
+        "offset": 7,
+        "length": 5,
+        "user": { "id": ALICE.id, "is_bot": false, "first_name": ALICE.first_name },
+    }]);
+    harness.send(mention).await;
+    let stats = harness.last_message();
+    assert!(stats.starts_with("*L stats for __Alice__*"));
+    assert!(stats.contains("Total: *0 Ls*"));
+    assert!(stats.contains("Given: 1 · Received: 0"));
+
+    harness.send(harness.message(&DAVE, "/stats")).await;
+    assert!(harness.last_message().starts_with("*L stats for __Dave__*"));
+
+    harness.send(harness.message(&DAVE, "/stats @nobody")).await;
+    assert_eq!(harness.last_message(), "I haven't seen @nobody yet");
+}
+
+#[tokio::test]
+async fn streaks_count_days_in_a_row_in_the_chats_time_zone() {
+    let harness = Harness::new().await;
+    let bobs_message = harness.message(&BOB, "pineapple belongs on pizza");
+    for giver in [&ALICE, &CAROL, &DAVE] {
+        harness
+            .send(harness.reply(giver, "/givel", &bobs_message))
+            .await;
+    }
+    // Spread the Ls over three days in a row, the last one today
+    for (giver, days_ago) in [(&ALICE, 2), (&CAROL, 1)] {
+        sqlx::query(
+            "UPDATE Award SET created_at = created_at - $1 WHERE chat_id = $2 AND giver_id = $3",
+        )
+        .bind(days_ago * DAY)
+        .bind(harness.chat_id)
+        .bind(giver.id as i64)
+        .execute(db())
+        .await
+        .unwrap();
+    }
+
+    harness.send(harness.message(&BOB, "/mystats")).await;
+    let stats = harness.last_message();
+    assert!(stats.contains("Longest streak: 3 days in a row"));
+    assert!(stats.contains("▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁███ \\(3 Ls, up from 0\\)"));
+}
+
+#[test]
+fn streaks_break_on_days_without_ls() {
+    let utc: Tz = "UTC".parse().unwrap();
+    // Two Ls on day 0, then days 1 and 3
+    let times = [0, 60, DAY, 3 * DAY];
+    assert_eq!(stats::longest_streak(&times, utc), 2);
+    assert_eq!(stats::longest_streak(&[], utc), 0);
+
+    // 23:00 and 01:00 UTC fall on the same day in Tokyo
+    let tokyo: Tz = "Asia/Tokyo".parse().unwrap();
+    let times = [23 * 60 * 60, DAY + 60 * 60];
+    assert_eq!(stats::longest_streak(&times, utc), 2);
+    assert_eq!(stats::longest_streak(&times, tokyo), 1);
+}
+
+#[test]
+fn trend_compares_with_the_period_before() {
+    let utc: Tz = "UTC".parse().unwrap();
+    let now = 100 * DAY;
+    let times = [now - 45 * DAY, now - 29 * DAY, now, now];
+    let (trend, previous_period) = stats::trend(&times, utc, now);
+    assert_eq!(trend.len(), stats::TREND_DAYS);
+    assert_eq!(trend[0], 1);
+    assert_eq!(trend[stats::TREND_DAYS - 1], 2);
+    assert_eq!(trend.iter().sum::<i64>(), 3);
+    assert_eq!(previous_period, 1);
+
+    assert_eq!(stats::sparkline(&[0, 1, 2, 8]), "▁▂▃█");
+    assert_eq!(stats::sparkline(&[0, 0]), "▁▁");
+}
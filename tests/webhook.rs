@@ -0,0 +1,72 @@
+use futures::StreamExt;
+use gustyfring::{config::WebhookConfig, webhook};
+use reqwest::StatusCode;
+use std::net::{SocketAddr, TcpListener};
+use teloxide::{dispatching::update_listeners::AsUpdateStream, types::UpdateKind};
+
+const SECRET_TOKEN: &str = "test-secret";
+
+const UPDATE: &str = r#"{
+    "update_id": 1,
+    "message": {
+        "message_id": 1,
+        "date": 0,
+        "chat": { "id": 1, "type": "private", "first_name": "Gus" },
+        "from": { "id": 1, "is_bot": false, "first_name": "Gus" },
+        "text": "hello"
+    }
+}"#;
+
+fn free_address() -> SocketAddr {
+    TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+}
+
+#[tokio::test]
+async fn webhook_accepts_updates_with_secret_token() {
+    let address = free_address();
+    let config = WebhookConfig {
+        url: format!("http://{}/webhook", address).parse().unwrap(),
+        address,
+        secret_token: Some(SECRET_TOKEN.to_owned()),
+    };
+
+    let (mut listener, stopped, router) = webhook::listener(webhook::options(&config));
+    tokio::spawn(webhook::serve(router, address, stopped));
+    // Give the server a moment to bind
+    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+
+    let client = reqwest::Client::new();
+    let post = |secret: Option<&'static str>| {
+        let mut request = client
+            .post(config.url.clone())
+            .header("Content-Type", "application/json")
+            .body(UPDATE);
+        if let Some(secret) = secret {
+            request = request.header("X-Telegram-Bot-Api-Secret-Token", secret);
+        }
+        request.send()
+    };
+
+    let response = post(None).await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let response = post(Some("wrong-secret")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let response = post(Some(SECRET_TOKEN)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let update = Box::pin(listener.as_stream())
+        .next()
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(update.id, 1);
+    let UpdateKind::Message(message) = update.kind else {
+        panic!("Expected a message update");
+    };
+    assert_eq!(message.text(), Some("hello"));
+}